@@ -1,4 +1,47 @@
+from .iati_json_parser import Activity, Budget, Organisation, Transaction
+from .iati_json_parser import ErrorEntry, HealthReport, PendingManifest, Settings, Snapshot
 from .iati_json_parser import convert as convert_rs
+from .iati_json_parser import convert_snapshot as convert_snapshot_rs
+from .iati_json_parser import diff as diff_rs
+from .iati_json_parser import stats as stats_rs
+from .iati_json_parser import allocations as allocations_rs
+from .iati_json_parser import organisations as organisations_rs
+from .iati_json_parser import geojson as geojson_rs
+from .iati_json_parser import activities, dump_health, list_snapshots, prune_snapshots
 
-def convert(input, pretty=False, schemas=None):
-    return convert_rs(input, pretty)
+# build_index and search only exist when built with the `index` feature
+try:
+    from .iati_json_parser import build_index
+    from .iati_json_parser import search as search_rs
+except ImportError:
+    search_rs = None
+
+def convert(input=None, pretty=False, schemas=None, settings=None, since=None, manifest=None):
+    result, pending = convert_rs(input, pretty, settings, since, manifest)
+    if manifest is None:
+        return result
+    return result, pending
+
+def convert_snapshot(name, pretty=False, settings=None):
+    return convert_snapshot_rs(name, pretty, settings)
+
+def diff(old, new, pretty=False, settings=None):
+    return diff_rs(old, new, pretty, settings)
+
+def stats(input, pretty=False, settings=None):
+    return stats_rs(input, pretty, settings)
+
+def allocations(input, sector_vocabulary="1", region_vocabulary="1", pretty=False, settings=None):
+    return allocations_rs(input, sector_vocabulary, region_vocabulary, pretty, settings)
+
+def organisations(input, pretty=False, settings=None):
+    return organisations_rs(input, pretty, settings)
+
+def geojson(input, pretty=False, settings=None):
+    return geojson_rs(input, pretty, settings)
+
+if search_rs is not None:
+    def search(index_dir, query="", filters=None, limit=20, pretty=False, settings=None):
+        if filters is not None:
+            filters = {k: [v] if isinstance(v, str) else list(v) for k, v in filters.items()}
+        return search_rs(index_dir, query, filters, limit, pretty, settings)
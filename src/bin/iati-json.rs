@@ -0,0 +1,486 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde_json::{Map, Value};
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use iati_json_parser::converter::allocation::{dump_allocations, AllocationOptions};
+use iati_json_parser::converter::feed::ChangeFeed;
+use iati_json_parser::converter::geojson::feature_collection;
+#[cfg(feature = "index")]
+use iati_json_parser::converter::index;
+use iati_json_parser::converter::model::Code;
+use iati_json_parser::converter::organisations::dump_organisations;
+use iati_json_parser::converter::pipeline::{
+    convert_records, download_source, load_records, prepare_zip, validate_path,
+};
+use iati_json_parser::converter::settings::Settings;
+use iati_json_parser::converter::stats::dump_stats;
+use iati_json_parser::converter::utils::download_zip;
+
+/// Converts IATI activity XML to JSON without going through Python.
+///
+/// Progress is logged to stderr; output goes to stdout unless `--output` is given.
+#[derive(Parser)]
+#[command(name = "iati-json", version)]
+struct Cli {
+    /// Worker threads for parsing; defaults to one per core.
+    #[arg(long, global = true)]
+    threads: Option<usize>,
+
+    #[command(flatten)]
+    source: SourceArgs,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// The subset of `Settings` exposed on the command line.
+#[derive(Args)]
+struct SourceArgs {
+    /// URL of the zipped registry dump.
+    #[arg(long, global = true)]
+    source_url: Option<String>,
+    /// Directory dumps are downloaded and extracted into.
+    #[arg(long, global = true)]
+    cache_dir: Option<String>,
+    /// Directory holding an earlier extracted dump used to back-fill broken files.
+    #[arg(long, global = true)]
+    archive_dir: Option<String>,
+    /// Skip the download when today's snapshot already exists.
+    #[arg(long, global = true)]
+    reuse_download: bool,
+    /// Never touch the network; uses the most recent snapshot.
+    #[arg(long, global = true)]
+    offline: bool,
+    /// Convert straight from a zip instead of extracting it.
+    #[arg(long, global = true)]
+    stream_zip: bool,
+    /// Publisher folder or glob to keep; repeatable.
+    #[arg(long, global = true)]
+    include: Vec<String>,
+    /// Publisher folder or glob to drop; repeatable.
+    #[arg(long, global = true)]
+    exclude: Vec<String>,
+    /// Language of narratives that don't declare one; defaults to `en`.
+    #[arg(long, global = true)]
+    default_lang: Option<String>,
+    /// Write narratives as one string in this language, e.g. `fr`.
+    #[arg(long, global = true)]
+    preferred_lang: Option<String>,
+    /// `field=weight` for the search document, e.g. `title=5`; repeatable.
+    /// When given, only the listed fields are indexed.
+    #[arg(long, global = true, value_parser = parse_weight)]
+    search_weight: Vec<(String, f64)>,
+    /// Convert files in path order so repeated runs give identical output.
+    #[arg(long, global = true)]
+    deterministic: bool,
+    /// Currency exchange rates are quoted in; adds `meta.converted_totals`.
+    #[arg(long, global = true)]
+    report_currency: Option<String>,
+    /// `currency=rate`, the value of one unit in the report currency, e.g.
+    /// `EUR=1.08`; repeatable.
+    #[arg(long, global = true, value_parser = parse_rate)]
+    exchange_rate: Vec<(String, f64)>,
+    /// Local copy of org-id.guide's agency list to check organisation refs against.
+    #[arg(long, global = true)]
+    org_id_list: Option<String>,
+}
+
+/// Splits `key=number`; `form` names the two parts for the error message.
+fn parse_pair(arg: &str, form: &str) -> Result<(String, f64), String> {
+    let (key, number) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected {}, got {:?}", form, arg))?;
+    let number = number
+        .parse()
+        .map_err(|_| format!("{:?} is not a number", number))?;
+    Ok((key.to_owned(), number))
+}
+
+fn parse_weight(arg: &str) -> Result<(String, f64), String> {
+    parse_pair(arg, "field=weight")
+}
+
+fn parse_rate(arg: &str) -> Result<(String, f64), String> {
+    parse_pair(arg, "currency=rate")
+}
+
+impl SourceArgs {
+    fn settings(&self) -> Settings {
+        let defaults = Settings::default();
+        Settings {
+            source_url: self.source_url.clone().unwrap_or(defaults.source_url),
+            cache_dir: self.cache_dir.clone().unwrap_or(defaults.cache_dir),
+            archive_dir: self.archive_dir.clone().unwrap_or(defaults.archive_dir),
+            reuse_download: self.reuse_download,
+            offline: self.offline,
+            stream_zip: self.stream_zip,
+            include: self.include.clone(),
+            exclude: self.exclude.clone(),
+            default_lang: self.default_lang.clone().unwrap_or(defaults.default_lang),
+            preferred_lang: self.preferred_lang.clone(),
+            search_weights: if self.search_weight.is_empty() {
+                defaults.search_weights
+            } else {
+                self.search_weight.iter().cloned().collect()
+            },
+            deterministic: self.deterministic,
+            report_currency: self.report_currency.clone(),
+            exchange_rates: self.exchange_rate.iter().cloned().collect(),
+            org_id_list: self.org_id_list.clone(),
+            ..defaults
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// One JSON array.
+    Json,
+    /// One activity per line.
+    Jsonl,
+}
+
+/// How records are written out.
+#[derive(Args)]
+struct OutputArgs {
+    #[arg(long, value_enum, default_value = "json")]
+    format: Format,
+    /// Indent JSON output; ignored for jsonl.
+    #[arg(long)]
+    pretty: bool,
+    /// Comma-separated top-level fields to keep, e.g. `iati-identifier,title`.
+    #[arg(long, value_delimiter = ',')]
+    fields: Vec<String>,
+    /// File to write to instead of stdout.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Convert a file, folder or zip (or today's download) to JSON.
+    Convert {
+        /// XML file, dump folder or zip; downloads the dump when omitted.
+        path: Option<String>,
+        /// Only keep activities updated after this timestamp.
+        #[arg(long)]
+        since: Option<String>,
+        /// File hash manifest used to skip files unchanged since the last run.
+        #[arg(long)]
+        manifest: Option<String>,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Check every activity file converts, exiting non-zero if any fail.
+    Validate {
+        path: String,
+        /// Print every file checked, not just failures.
+        #[arg(long)]
+        verbose: bool,
+    },
+    /// Print the activities with a given iati-identifier.
+    Find {
+        /// Converted JSON file, snapshot name, or anything `convert` accepts.
+        path: String,
+        iati_identifier: String,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Count activities by publisher, country, sector and status, and sum
+    /// transactions by type, currency and year.
+    Stats {
+        /// Converted JSON file, snapshot name, or anything `convert` accepts.
+        path: String,
+        #[arg(long)]
+        pretty: bool,
+    },
+    /// Split transaction values by sector and recipient country or region.
+    Allocate {
+        /// Dump folder, zip, XML file or snapshot name.
+        path: String,
+        /// Sector vocabulary to split by, a code or a name such as DAC-3.
+        #[arg(long, default_value = "1")]
+        sector_vocabulary: String,
+        /// Region vocabulary split alongside countries.
+        #[arg(long, default_value = "1")]
+        region_vocabulary: String,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// List the organisations named across a dump, merged by ref.
+    Organisations {
+        /// Dump folder, zip, XML file or snapshot name.
+        path: String,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Write activity locations as a GeoJSON FeatureCollection.
+    Geojson {
+        /// Converted JSON file, snapshot name, or anything `convert` accepts.
+        path: String,
+        #[arg(long)]
+        pretty: bool,
+        /// Write to this file instead of stdout.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Build a full-text search index of converted activities.
+    #[cfg(feature = "index")]
+    Index {
+        /// Converted JSON file, snapshot name, or anything `convert` accepts.
+        path: String,
+        index_dir: PathBuf,
+    },
+    /// Search an index built by `index`.
+    #[cfg(feature = "index")]
+    Search {
+        index_dir: PathBuf,
+        /// Words to find; all must match unless joined by OR. Empty matches everything.
+        #[arg(default_value = "")]
+        query: String,
+        /// Recipient country code; repeatable.
+        #[arg(long)]
+        country: Vec<String>,
+        /// DAC sector code or 3-digit category; repeatable.
+        #[arg(long)]
+        sector: Vec<String>,
+        /// Publisher; repeatable.
+        #[arg(long)]
+        publisher: Vec<String>,
+        /// Activity status code; repeatable.
+        #[arg(long)]
+        status: Vec<String>,
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Download the dump into the snapshot store and print where it is.
+    Download {
+        /// Also extract it, printing the extracted folder instead.
+        #[arg(long)]
+        extract: bool,
+    },
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> eyre::Result<ExitCode> {
+    if let Some(threads) = cli.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()?;
+    }
+    let settings = cli.source.settings();
+
+    match cli.command {
+        Command::Convert {
+            path,
+            since,
+            manifest,
+            output,
+        } => {
+            let extraction_path = match path {
+                Some(p) => p,
+                None => download_source(&settings)?,
+            };
+            let feed = if since.is_some() || manifest.is_some() {
+                Some(ChangeFeed::new(since.as_deref(), manifest.as_deref())?)
+            } else {
+                None
+            };
+            let (records, feed) = convert_records(&extraction_path, &settings, feed)?;
+            write_records(records, &output)?;
+            if let Some(feed) = feed {
+                feed.commit()?;
+            }
+        }
+        Command::Validate { path, verbose } => {
+            let checks = validate_path(&path, &settings)?;
+            let failed = checks.iter().filter(|c| c.error.is_some()).count();
+            let activities: usize = checks.iter().map(|c| c.activities).sum();
+
+            let mut out = io::stdout().lock();
+            for check in checks.iter().filter(|c| verbose || c.error.is_some()) {
+                writeln!(out, "{}", serde_json::to_string(check)?)?;
+            }
+            eprintln!(
+                "{} files checked, {} failed, {} activities",
+                checks.len(),
+                failed,
+                activities
+            );
+            if failed > 0 {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Command::Find {
+            path,
+            iati_identifier,
+            output,
+        } => {
+            let found: Vec<Value> = load_records(&path, &settings)?
+                .into_iter()
+                .filter(|r| {
+                    r.get("iati-identifier")
+                        .and_then(|id| Code(id.clone()).as_string())
+                        .is_some_and(|id| id == iati_identifier.trim())
+                })
+                .collect();
+            if found.is_empty() {
+                eprintln!("No activity found with iati-identifier {}", iati_identifier);
+                return Ok(ExitCode::FAILURE);
+            }
+            write_records(found, &output)?;
+        }
+        Command::Stats { path, pretty } => {
+            let stats = dump_stats(&path, &settings)?;
+            let mut out = io::stdout().lock();
+            if pretty {
+                serde_json::to_writer_pretty(&mut out, &stats)?;
+            } else {
+                serde_json::to_writer(&mut out, &stats)?;
+            }
+            writeln!(out)?;
+        }
+        Command::Allocate {
+            path,
+            sector_vocabulary,
+            region_vocabulary,
+            output,
+        } => {
+            let options = AllocationOptions {
+                sector_vocabulary,
+                region_vocabulary,
+            };
+            let rows = dump_allocations(&path, &settings, &options)?
+                .into_iter()
+                .map(serde_json::to_value)
+                .collect::<Result<Vec<_>, _>>()?;
+            write_records(rows, &output)?;
+        }
+        Command::Organisations { path, output } => {
+            let orgs = dump_organisations(&path, &settings)?
+                .into_iter()
+                .map(serde_json::to_value)
+                .collect::<Result<Vec<_>, _>>()?;
+            write_records(orgs, &output)?;
+        }
+        Command::Geojson {
+            path,
+            pretty,
+            output,
+        } => {
+            let collection = feature_collection(&load_records(&path, &settings)?);
+            let writer: Box<dyn Write> = match &output {
+                Some(path) => Box::new(File::create(path)?),
+                None => Box::new(io::stdout().lock()),
+            };
+            let mut out = BufWriter::new(writer);
+            if pretty {
+                serde_json::to_writer_pretty(&mut out, &collection)?;
+            } else {
+                serde_json::to_writer(&mut out, &collection)?;
+            }
+            writeln!(out)?;
+            out.flush()?;
+        }
+        #[cfg(feature = "index")]
+        Command::Index { path, index_dir } => {
+            let count = index::build_index(&path, &settings, &index_dir)?;
+            eprintln!("Indexed {} activities in {}", count, index_dir.display());
+        }
+        #[cfg(feature = "index")]
+        Command::Search {
+            index_dir,
+            query,
+            country,
+            sector,
+            publisher,
+            status,
+            limit,
+            output,
+        } => {
+            let filters = index::SearchFilters {
+                country,
+                sector,
+                publisher,
+                status,
+            };
+            let hits =
+                index::search_index(&index_dir, &query, &filters, &settings.search_weights, limit)?;
+            let hits = hits
+                .into_iter()
+                .map(serde_json::to_value)
+                .collect::<Result<Vec<_>, _>>()?;
+            write_records(hits, &output)?;
+        }
+        Command::Download { extract } => {
+            let zip_path = download_zip(&settings)?;
+            if extract {
+                println!("{}", prepare_zip(&zip_path, &settings)?);
+            } else {
+                println!("{}", zip_path.display());
+            }
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Keeps only the requested top-level fields, in the order requested.
+fn select_fields(record: Value, fields: &[String]) -> Value {
+    match record {
+        Value::Object(mut o) if !fields.is_empty() => {
+            let mut selected = Map::new();
+            for field in fields {
+                if let Some(v) = o.remove(field) {
+                    selected.insert(field.clone(), v);
+                }
+            }
+            Value::Object(selected)
+        }
+        other => other,
+    }
+}
+
+fn write_records(records: Vec<Value>, args: &OutputArgs) -> eyre::Result<()> {
+    let writer: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout().lock()),
+    };
+    let mut out = BufWriter::new(writer);
+
+    let records: Vec<Value> = records
+        .into_iter()
+        .map(|r| select_fields(r, &args.fields))
+        .collect();
+
+    match args.format {
+        Format::Json if args.pretty => serde_json::to_writer_pretty(&mut out, &records)?,
+        Format::Json => serde_json::to_writer(&mut out, &records)?,
+        Format::Jsonl => {
+            for record in &records {
+                serde_json::to_writer(&mut out, record)?;
+                writeln!(out)?;
+            }
+        }
+    }
+    if matches!(args.format, Format::Json) {
+        writeln!(out)?;
+    }
+    out.flush()?;
+    Ok(())
+}
@@ -0,0 +1,286 @@
+//! Splits transaction values across sectors and recipient countries or
+//! regions by their percentages: one row per transaction, sector and
+//! recipient, the usual way to answer "how much went to health in Uganda".
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::converter::model::{
+    sector_vocabulary, transaction_type_code, Activity, Code, Recipient, Sector,
+};
+use crate::converter::pipeline::for_each_activity;
+use crate::converter::settings::Settings;
+
+/// Which vocabularies splits are made in. Percentages only add up to 100
+/// within one vocabulary, so mixing them would count spend twice.
+#[derive(Clone, Debug)]
+pub struct AllocationOptions {
+    /// Sector vocabulary code, `1` (DAC 5-digit) by default. The 1.0x names
+    /// `DAC` and `DAC-3` are read as `1` and `2`.
+    pub sector_vocabulary: String,
+    /// Region vocabulary code split alongside countries, `1` (OECD DAC) by default.
+    pub region_vocabulary: String,
+}
+
+impl Default for AllocationOptions {
+    fn default() -> Self {
+        AllocationOptions {
+            sector_vocabulary: "1".to_owned(),
+            region_vocabulary: "1".to_owned(),
+        }
+    }
+}
+
+/// The share of one transaction's value going to one sector and one
+/// recipient country or region.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Allocation {
+    pub iati_identifier: Option<String>,
+    pub publisher: Option<String>,
+    /// IATI 2.x transaction type code.
+    pub transaction_type: Option<u8>,
+    pub transaction_date: Option<String>,
+    /// The transaction's currency, or the activity's default.
+    pub currency: Option<String>,
+    pub sector_vocabulary: Option<String>,
+    /// `None` when neither the transaction nor the activity has a sector in the vocabulary.
+    pub sector: Option<String>,
+    /// `transaction` or `activity`: where the sector split was read from.
+    pub sector_from: Option<&'static str>,
+    /// `country` or `region`.
+    pub recipient_type: Option<&'static str>,
+    /// `None` when neither the transaction nor the activity has a recipient.
+    pub recipient: Option<String>,
+    pub recipient_vocabulary: Option<String>,
+    /// `transaction` or `activity`: where the recipient split was read from.
+    pub recipient_from: Option<&'static str>,
+    /// Fraction of the transaction value in this row, to six decimal places.
+    pub share: f64,
+    /// Share of the transaction value, rounded to cents.
+    pub value: f64,
+}
+
+/// One side of the split: a code and its fraction.
+#[derive(Clone, Debug, Default)]
+struct Part {
+    code: Option<String>,
+    vocabulary: Option<String>,
+    kind: Option<&'static str>,
+    from: Option<&'static str>,
+    share: f64,
+}
+
+fn code_string(code: &Option<Code>) -> Option<String> {
+    code.as_ref()?.as_string().filter(|c| !c.is_empty())
+}
+
+/// Vocabulary of a region, `1` when it isn't stated.
+fn vocabulary(code: &Option<Code>) -> String {
+    code_string(code).unwrap_or_else(|| "1".to_owned())
+}
+
+/// Turns percentages into fractions adding up to 1. Missing percentages count
+/// as zero, unless none are usable, in which case the split is even.
+fn fractions(percentages: &[Option<f64>]) -> Vec<f64> {
+    let given = |p: &Option<f64>| p.filter(|p| p.is_finite() && *p > 0.0).unwrap_or(0.0);
+    let total: f64 = percentages.iter().map(given).sum();
+    if total > 0.0 {
+        percentages.iter().map(|p| given(p) / total).collect()
+    } else {
+        vec![1.0 / percentages.len() as f64; percentages.len()]
+    }
+}
+
+fn sector_parts<'a>(
+    own: Vec<&'a Sector>,
+    activity: Vec<&'a Sector>,
+    options: &AllocationOptions,
+) -> Vec<Part> {
+    // Read like a published vocabulary, so `DAC` and `DAC-3` work too.
+    let wanted = sector_vocabulary(Some(&Code(Value::String(
+        options.sector_vocabulary.clone(),
+    ))));
+    let in_vocabulary = |sectors: Vec<&'a Sector>| -> Vec<&'a Sector> {
+        sectors
+            .into_iter()
+            .filter(|s| sector_vocabulary(s.vocabulary.as_ref()) == wanted)
+            .filter(|s| code_string(&s.code).is_some())
+            .collect()
+    };
+    let part = |s: &Sector, from, share| Part {
+        code: code_string(&s.code),
+        vocabulary: Some(wanted.clone()),
+        kind: None,
+        from: Some(from),
+        share,
+    };
+
+    // Transaction sectors carry no percentages; several split evenly.
+    let own = in_vocabulary(own);
+    if !own.is_empty() {
+        let share = 1.0 / own.len() as f64;
+        return own.iter().map(|s| part(s, "transaction", share)).collect();
+    }
+    let activity = in_vocabulary(activity);
+    if !activity.is_empty() {
+        let percentages: Vec<_> = activity
+            .iter()
+            .map(|s| s.percentage.as_ref().and_then(Code::as_f64))
+            .collect();
+        return activity
+            .iter()
+            .zip(fractions(&percentages))
+            .map(|(s, share)| part(s, "activity", share))
+            .collect();
+    }
+    vec![Part {
+        vocabulary: Some(wanted),
+        share: 1.0,
+        ..Default::default()
+    }]
+}
+
+fn recipient_part(r: &Recipient, kind: &'static str, from: &'static str, share: f64) -> Part {
+    Part {
+        code: code_string(&r.code),
+        vocabulary: (kind == "region").then(|| vocabulary(&r.vocabulary)),
+        kind: Some(kind),
+        from: Some(from),
+        share,
+    }
+}
+
+fn recipient_parts(
+    country: Option<&Recipient>,
+    region: Option<&Recipient>,
+    activity: &Activity,
+    options: &AllocationOptions,
+) -> Vec<Part> {
+    if let Some(country) = country.filter(|c| code_string(&c.code).is_some()) {
+        return vec![recipient_part(country, "country", "transaction", 1.0)];
+    }
+    if let Some(region) = region.filter(|r| code_string(&r.code).is_some()) {
+        return vec![recipient_part(region, "region", "transaction", 1.0)];
+    }
+
+    // Countries and regions of one vocabulary share the activity's 100%.
+    let recipients: Vec<(&Recipient, &'static str)> = activity
+        .recipient_countries()
+        .map(|c| (c, "country"))
+        .chain(
+            activity
+                .recipient_regions()
+                .filter(|r| vocabulary(&r.vocabulary) == options.region_vocabulary)
+                .map(|r| (r, "region")),
+        )
+        .filter(|(r, _)| code_string(&r.code).is_some())
+        .collect();
+    if recipients.is_empty() {
+        return vec![Part {
+            share: 1.0,
+            ..Default::default()
+        }];
+    }
+    let percentages: Vec<_> = recipients
+        .iter()
+        .map(|(r, _)| r.percentage.as_ref().and_then(Code::as_f64))
+        .collect();
+    recipients
+        .iter()
+        .zip(fractions(&percentages))
+        .map(|((r, kind), share)| recipient_part(r, kind, "activity", share))
+        .collect()
+}
+
+/// Splits each of an activity's transactions by sector and recipient.
+///
+/// A transaction's own sectors and recipient are used when it has them,
+/// otherwise the activity's, read by their percentages. Percentages that
+/// don't add up to 100 are scaled so the whole value is allocated, and a
+/// transaction with no sector or recipient gets a row with that side empty.
+pub fn allocate(activity: &Activity, options: &AllocationOptions) -> Vec<Allocation> {
+    let default_currency = code_string(&activity.default_currency);
+    let activity_sectors: Vec<&Sector> = activity.sectors().collect();
+
+    let mut rows = Vec::new();
+    for t in activity.transactions() {
+        let amount = match t.amount() {
+            Some(amount) => amount,
+            None => continue,
+        };
+        let value = match amount.amount().and_then(Code::as_f64) {
+            Some(value) => value,
+            None => continue,
+        };
+
+        let own_sectors: Vec<&Sector> = t
+            .sector
+            .iter()
+            .flat_map(|s| s.iter())
+            .filter_map(|s| s.parsed())
+            .collect();
+        let sectors = sector_parts(own_sectors, activity_sectors.clone(), options);
+        let recipients = recipient_parts(
+            t.recipient_country.as_ref().and_then(|c| c.parsed()),
+            t.recipient_region.as_ref().and_then(|r| r.parsed()),
+            activity,
+            options,
+        );
+
+        for sector in &sectors {
+            for recipient in &recipients {
+                let share = sector.share * recipient.share;
+                rows.push(Allocation {
+                    iati_identifier: activity.identifier(),
+                    publisher: None,
+                    transaction_type: t
+                        .type_code()
+                        .and_then(Code::as_string)
+                        .and_then(|c| transaction_type_code(&c)),
+                    transaction_date: t.date().and_then(Code::as_string),
+                    currency: code_string(&amount.currency).or_else(|| default_currency.clone()),
+                    sector_vocabulary: sector.vocabulary.clone(),
+                    sector: sector.code.clone(),
+                    sector_from: sector.from,
+                    recipient_type: recipient.kind,
+                    recipient: recipient.code.clone(),
+                    recipient_vocabulary: recipient.vocabulary.clone(),
+                    recipient_from: recipient.from,
+                    share: (share * 1e6).round() / 1e6,
+                    value: (value * share * 100.0).round() / 100.0,
+                });
+            }
+        }
+    }
+    rows
+}
+
+/// Allocation rows for every activity in a dump folder, zip, single file or
+/// snapshot, in file order, covering the activities `convert` keeps with the
+/// same `settings`. Files that fail to convert are reported and skipped.
+pub fn dump_allocations(
+    path: &str,
+    settings: &Settings,
+    options: &AllocationOptions,
+) -> eyre::Result<Vec<Allocation>> {
+    for_each_activity(
+        path,
+        settings,
+        Vec::new,
+        |mut rows, dump, publisher| {
+            rows.extend(
+                allocate(&dump.activity, options)
+                    .into_iter()
+                    .map(|row| Allocation {
+                        publisher: publisher.map(str::to_owned),
+                        ..row
+                    }),
+            );
+            rows
+        },
+        |mut a, b| {
+            a.extend(b);
+            a
+        },
+    )
+}
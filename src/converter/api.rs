@@ -0,0 +1,346 @@
+use serde_json::Value;
+use zip::ZipArchive;
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::vec;
+
+use crate::converter::feed::ChangeFeed;
+use crate::converter::layout::{detect_source, detect_zip_source};
+use crate::converter::pipeline::{activity_files, run_conversion};
+use crate::converter::provenance::{modified_at, snapshot_downloaded_at};
+use crate::converter::settings::Settings;
+use crate::converter::utils::{supplement_root_files, FilterOptions};
+use crate::converter::{
+    convert_bytes, is_activity_file, sort_entries, zip_activity_entries, ConvertContext,
+};
+
+/// Builds a [`Converter`]. Every option falls back to `Settings::default()`.
+#[derive(Clone, Debug, Default)]
+pub struct ConverterBuilder {
+    settings: Settings,
+    since: Option<String>,
+    manifest: Option<String>,
+    supplement: bool,
+}
+
+impl ConverterBuilder {
+    /// Replaces all settings at once; later builder calls still apply on top.
+    pub fn settings(mut self, settings: Settings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn cache_dir(mut self, dir: impl Into<String>) -> Self {
+        self.settings.cache_dir = dir.into();
+        self
+    }
+
+    pub fn archive_dir(mut self, dir: impl Into<String>) -> Self {
+        self.settings.archive_dir = dir.into();
+        self
+    }
+
+    /// Adds a publisher folder or glob to keep.
+    pub fn include(mut self, rule: impl Into<String>) -> Self {
+        self.settings.include.push(rule.into());
+        self
+    }
+
+    /// Adds a publisher folder or glob to drop.
+    pub fn exclude(mut self, rule: impl Into<String>) -> Self {
+        self.settings.exclude.push(rule.into());
+        self
+    }
+
+    /// Convert files in path order so repeated runs give identical output.
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.settings.deterministic = deterministic;
+        self
+    }
+
+    /// Only keep activities updated after this timestamp.
+    pub fn since(mut self, timestamp: impl Into<String>) -> Self {
+        self.since = Some(timestamp.into());
+        self
+    }
+
+    /// File hash manifest used to skip files unchanged since the last run.
+    /// It is only rewritten by committing the feed from
+    /// [`Converter::convert_changes`] or [`Activities::commit`].
+    pub fn manifest(mut self, path: impl Into<String>) -> Self {
+        self.manifest = Some(path.into());
+        self
+    }
+
+    /// Back-fill files listed in a dump folder's errors.txt before converting
+    /// it, writing the copies into the dump. Off by default.
+    pub fn supplement(mut self, supplement: bool) -> Self {
+        self.supplement = supplement;
+        self
+    }
+
+    /// Checks the filter rules and timestamp before any conversion runs.
+    pub fn build(self) -> eyre::Result<Converter> {
+        self.settings.path_filter()?;
+        let options = self.settings.filter_options()?;
+        if self.since.is_some() {
+            ChangeFeed::new(self.since.as_deref(), None)?;
+        }
+        Ok(Converter {
+            settings: self.settings,
+            options,
+            since: self.since,
+            manifest: self.manifest,
+            supplement: self.supplement,
+        })
+    }
+}
+
+/// Converts IATI activity XML into the same records `convert` returns to Python.
+///
+/// ```no_run
+/// use iati_json_parser::converter::Converter;
+///
+/// let converter = Converter::builder().include("gb-*").build()?;
+/// for activity in converter.activities("raw_data/iati-data-main")? {
+///     println!("{}", activity?["iati-identifier"]);
+/// }
+/// # Ok::<(), eyre::Report>(())
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Converter {
+    settings: Settings,
+    /// Read from `settings` once, as loading them may read the org-id list.
+    options: FilterOptions,
+    since: Option<String>,
+    manifest: Option<String>,
+    supplement: bool,
+}
+
+impl Converter {
+    pub fn builder() -> ConverterBuilder {
+        ConverterBuilder::default()
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    fn context(&self) -> eyre::Result<ConvertContext> {
+        let feed = match (&self.since, &self.manifest) {
+            (None, None) => None,
+            _ => Some(ChangeFeed::new(
+                self.since.as_deref(),
+                self.manifest.as_deref(),
+            )?),
+        };
+        Ok(ConvertContext {
+            filter: self.settings.path_filter()?,
+            feed,
+            options: self.options.clone(),
+            deterministic: self.settings.deterministic,
+            supplement: self.supplement,
+            ..Default::default()
+        })
+    }
+
+    /// Activities in a single XML file. Filters and the manifest don't apply.
+    pub fn convert_file(&self, path: impl AsRef<Path>) -> eyre::Result<Vec<Value>> {
+        let path = path.as_ref();
+        if !path.is_file() {
+            return Err(eyre::eyre!("{} is not a file.", path.display()));
+        }
+
+        let feed = match &self.since {
+            Some(since) => Some(ChangeFeed::new(Some(since), None)?),
+            None => None,
+        };
+        let ctx = ConvertContext {
+            feed,
+            options: self.options.clone(),
+            ..Default::default()
+        };
+        Ok(convert_bytes(path, fs::read(path)?, &ctx)?.unwrap_or_default())
+    }
+
+    /// Activities in a dump folder, back-filling files listed in errors.txt
+    /// first when built with [`supplement`](ConverterBuilder::supplement).
+    /// Files that fail to convert are logged and skipped.
+    pub fn convert_dir(&self, path: impl AsRef<Path>) -> eyre::Result<Vec<Value>> {
+        let path = path.as_ref();
+        if !path.is_dir() {
+            return Err(eyre::eyre!("{} is not a directory.", path.display()));
+        }
+        Ok(self.run(path)?.0)
+    }
+
+    /// Activities in a zipped dump, read without extracting it.
+    pub fn convert_zip(&self, path: impl AsRef<Path>) -> eyre::Result<Vec<Value>> {
+        let path = path.as_ref();
+        if !(path.is_file() && is_zip(path)) {
+            return Err(eyre::eyre!("{} is not a zip file.", path.display()));
+        }
+        Ok(self.run(path)?.0)
+    }
+
+    /// Activities in a dump folder or zip together with the change feed, whose
+    /// manifest is written by [`ChangeFeed::commit`] once they are stored.
+    pub fn convert_changes(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> eyre::Result<(Vec<Value>, Option<ChangeFeed>)> {
+        let path = path.as_ref();
+        if !(path.is_dir() || path.is_file() && is_zip(path)) {
+            return Err(eyre::eyre!(
+                "{} is not a directory or zip file.",
+                path.display()
+            ));
+        }
+        self.run(path)
+    }
+
+    fn run(&self, path: &Path) -> eyre::Result<(Vec<Value>, Option<ChangeFeed>)> {
+        let mut ctx = self.context()?;
+        let value = run_conversion(&path.to_string_lossy(), &self.settings, &mut ctx)?;
+        match value {
+            Value::Array(records) => Ok((records, ctx.feed)),
+            _ => Err(eyre::eyre!(
+                "{} did not produce an array of activities.",
+                path.display()
+            )),
+        }
+    }
+
+    /// Lazily converts a file, dump folder or zip one file at a time, so only
+    /// one file's activities are held in memory.
+    pub fn activities(&self, path: impl AsRef<Path>) -> eyre::Result<Activities> {
+        let path = path.as_ref();
+        let mut ctx = self.context()?;
+
+        let input = if path.is_file() && is_zip(path) {
+            ctx.downloaded_at = modified_at(path);
+            ctx.source = Some(detect_zip_source(path)?);
+            let mut entries = zip_activity_entries(path, &ctx.filter)?;
+            if ctx.deterministic {
+                sort_entries(&mut entries);
+            }
+            Input::Zip {
+                archive: ZipArchive::new(File::open(path)?)?,
+                entries: entries.into_iter(),
+            }
+        } else if path.is_dir() {
+            let source = detect_source(path)?;
+            let data_dir = source.data_dir();
+            if self.supplement {
+                supplement_root_files(&data_dir, &self.settings)?;
+            }
+
+            ctx.downloaded_at = snapshot_downloaded_at(&source.root, &self.settings);
+            let files = activity_files(&data_dir, &ctx.filter)?;
+            ctx.source = Some(source);
+            Input::Files(files.into_iter())
+        } else if path.is_file() && is_activity_file(path) {
+            Input::Files(vec![path.to_path_buf()].into_iter())
+        } else {
+            return Err(eyre::eyre!(
+                "{} is not an XML file, a zip archive or a directory.",
+                path.display()
+            ));
+        };
+
+        Ok(Activities {
+            input,
+            pending: Vec::new().into_iter(),
+            ctx,
+        })
+    }
+}
+
+fn is_zip(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "zip")
+}
+
+enum Input {
+    Files(vec::IntoIter<PathBuf>),
+    Zip {
+        archive: ZipArchive<File>,
+        entries: vec::IntoIter<(usize, String)>,
+    },
+}
+
+/// Iterator over converted activities, returned by [`Converter::activities`].
+///
+/// A file that can't be read or converted yields one `Err` and iteration
+/// carries on with the next file. The change feed manifest, if any, is
+/// written by [`commit`](Activities::commit).
+pub struct Activities {
+    input: Input,
+    pending: vec::IntoIter<Value>,
+    ctx: ConvertContext,
+}
+
+impl Activities {
+    /// Writes the change feed manifest with the files read so far. Call it
+    /// once the activities have been stored, after iterating with `by_ref()`.
+    pub fn commit(self) -> eyre::Result<()> {
+        match &self.ctx.feed {
+            Some(feed) => feed.commit(),
+            None => Ok(()),
+        }
+    }
+
+    /// Reads the next file, or `None` when there are no files left.
+    fn next_file(&mut self) -> Option<eyre::Result<Option<Vec<Value>>>> {
+        match &mut self.input {
+            Input::Files(files) => {
+                let path = files.next()?;
+                Some(
+                    fs::read(&path)
+                        .map_err(|e| eyre::eyre!("Error reading XML file {:?}: {}", path, e))
+                        .and_then(|bytes| convert_bytes(&path, bytes, &self.ctx)),
+                )
+            }
+            Input::Zip { archive, entries } => {
+                let (index, name) = entries.next()?;
+                let mut bytes = Vec::new();
+                let read = archive
+                    .by_index(index)
+                    .map_err(eyre::Report::from)
+                    .and_then(|mut f| Ok(f.read_to_end(&mut bytes)?));
+                Some(match read {
+                    Ok(_) => convert_bytes(Path::new(&name), bytes, &self.ctx),
+                    Err(e) => Err(eyre::eyre!("Error reading zip entry {:?}: {}", name, e)),
+                })
+            }
+        }
+    }
+}
+
+impl Iterator for Activities {
+    type Item = eyre::Result<Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(activity) = self.pending.next() {
+                return Some(Ok(activity));
+            }
+            match self.next_file() {
+                Some(Ok(records)) => self.pending = records.unwrap_or_default().into_iter(),
+                Some(Err(e)) => return Some(Err(e)),
+                None => return None,
+            }
+        }
+    }
+}
+
+/// Activities in a single XML file with default settings.
+pub fn convert_file(path: impl AsRef<Path>) -> eyre::Result<Vec<Value>> {
+    Converter::default().convert_file(path)
+}
+
+/// Activities in a dump folder with default settings.
+pub fn convert_dir(path: impl AsRef<Path>) -> eyre::Result<Vec<Value>> {
+    Converter::default().convert_dir(path)
+}
@@ -0,0 +1,192 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use std::collections::BTreeMap;
+
+/// Fields left out of comparisons because they change on every download.
+pub const IGNORED_FIELDS: [&str; 1] = ["_source"];
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// A value present only in the newer conversion, e.g. a new transaction.
+    Added,
+    /// A value present only in the older conversion.
+    Removed,
+    /// A scalar whose value differs, e.g. a status code or budget value.
+    Changed,
+}
+
+/// One field-level difference within an activity.
+#[derive(Clone, Debug, Serialize)]
+pub struct FieldChange {
+    /// Slash-separated path from the activity root, e.g. `activity-status/code`.
+    pub path: String,
+    pub kind: ChangeKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new: Option<Value>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ActivityChange {
+    #[serde(rename = "iati-identifier")]
+    pub iati_identifier: String,
+    pub changes: Vec<FieldChange>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct DiffSummary {
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+    pub unchanged: usize,
+    /// Records without an `iati-identifier`, which can't be matched up.
+    pub unidentified: usize,
+}
+
+/// Activities added, removed and changed between two conversions.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ChangeReport {
+    pub summary: DiffSummary,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<ActivityChange>,
+}
+
+fn identifier(activity: &Value) -> Option<String> {
+    match activity.get("iati-identifier")? {
+        Value::String(s) => Some(s.trim().to_owned()),
+        other => Some(other.to_string()),
+    }
+}
+
+/// Indexes records by `iati-identifier`; the first record wins for duplicates.
+fn index(records: &[Value], unidentified: &mut usize) -> BTreeMap<String, Value> {
+    let mut by_id = BTreeMap::new();
+    for record in records {
+        match identifier(record) {
+            Some(id) => {
+                by_id.entry(id).or_insert_with(|| record.clone());
+            }
+            None => *unidentified += 1,
+        }
+    }
+    by_id
+}
+
+/// Compares two sets of converted activities keyed by `iati-identifier`.
+pub fn diff_activities(old: &[Value], new: &[Value]) -> ChangeReport {
+    let mut report = ChangeReport::default();
+    let old_by_id = index(old, &mut report.summary.unidentified);
+    let new_by_id = index(new, &mut report.summary.unidentified);
+
+    for (id, new_activity) in &new_by_id {
+        match old_by_id.get(id) {
+            None => report.added.push(id.clone()),
+            Some(old_activity) => {
+                let mut changes = Vec::new();
+                diff_values("", old_activity, new_activity, &mut changes);
+                if changes.is_empty() {
+                    report.summary.unchanged += 1;
+                } else {
+                    report.changed.push(ActivityChange {
+                        iati_identifier: id.clone(),
+                        changes,
+                    });
+                }
+            }
+        }
+    }
+    report.removed = old_by_id
+        .keys()
+        .filter(|id| !new_by_id.contains_key(*id))
+        .cloned()
+        .collect();
+
+    report.summary.added = report.added.len();
+    report.summary.removed = report.removed.len();
+    report.summary.changed = report.changed.len();
+    report
+}
+
+fn join_path(base: &str, key: &str) -> String {
+    if base.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{}/{}", base, key)
+    }
+}
+
+/// Recursively records the differences between `old` and `new` under `path`.
+///
+/// Objects are compared key by key. Arrays are compared as multisets, so a
+/// new transaction shows up as one `added` entry rather than every later
+/// element shifting position.
+pub fn diff_values(path: &str, old: &Value, new: &Value, out: &mut Vec<FieldChange>) {
+    match (old, new) {
+        (Value::Object(o), Value::Object(n)) => {
+            for (k, ov) in o {
+                if path.is_empty() && IGNORED_FIELDS.contains(&k.as_str()) {
+                    continue;
+                }
+                match n.get(k) {
+                    Some(nv) => diff_values(&join_path(path, k), ov, nv, out),
+                    None => out.push(FieldChange {
+                        path: join_path(path, k),
+                        kind: ChangeKind::Removed,
+                        old: Some(ov.clone()),
+                        new: None,
+                    }),
+                }
+            }
+            for (k, nv) in n {
+                if path.is_empty() && IGNORED_FIELDS.contains(&k.as_str()) {
+                    continue;
+                }
+                if !o.contains_key(k) {
+                    out.push(FieldChange {
+                        path: join_path(path, k),
+                        kind: ChangeKind::Added,
+                        old: None,
+                        new: Some(nv.clone()),
+                    });
+                }
+            }
+        }
+        (Value::Array(o), Value::Array(n)) => {
+            let mut unmatched: Vec<Option<&Value>> = n.iter().map(Some).collect();
+            let mut removed = Vec::new();
+            for ov in o {
+                match unmatched.iter_mut().find(|slot| slot.is_some_and(|nv| nv == ov)) {
+                    Some(slot) => *slot = None,
+                    None => removed.push(ov),
+                }
+            }
+            for ov in removed {
+                out.push(FieldChange {
+                    path: path.to_owned(),
+                    kind: ChangeKind::Removed,
+                    old: Some(ov.clone()),
+                    new: None,
+                });
+            }
+            for nv in unmatched.into_iter().flatten() {
+                out.push(FieldChange {
+                    path: path.to_owned(),
+                    kind: ChangeKind::Added,
+                    old: None,
+                    new: Some(nv.clone()),
+                });
+            }
+        }
+        _ if old != new => out.push(FieldChange {
+            path: path.to_owned(),
+            kind: ChangeKind::Changed,
+            old: Some(old.clone()),
+            new: Some(new.clone()),
+        }),
+        _ => {}
+    }
+}
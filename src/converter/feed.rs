@@ -0,0 +1,138 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde_json::Value;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Parses the date forms publishers use for `last-updated-datetime`:
+/// RFC 3339, a naive date-time (taken as UTC) or a bare date.
+pub fn parse_iati_datetime(raw: &str) -> Option<DateTime<Utc>> {
+    let raw = raw.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(raw) {
+        return Some(dt.with_timezone(&Utc));
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(raw, format) {
+            return Some(dt.and_utc());
+        }
+    }
+    NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|dt| dt.and_utc())
+}
+
+/// Restricts a conversion to what changed since the last sync.
+///
+/// `since` keeps activities updated after the timestamp; activities without a
+/// parseable `last-updated-datetime` are kept, as we can't tell they're
+/// unchanged. With a manifest, files whose hash matches the previous run are
+/// skipped before parsing; a file's hash is recorded only once it converts. The manifest isn't rewritten until [`commit`]
+/// is called, so a run whose output never got stored can be repeated.
+///
+/// [`commit`]: ChangeFeed::commit
+#[derive(Default)]
+pub struct ChangeFeed {
+    pub since: Option<DateTime<Utc>>,
+    pub manifest: Option<PathBuf>,
+    previous: HashMap<String, String>,
+    seen: Mutex<BTreeMap<String, String>>,
+}
+
+impl ChangeFeed {
+    pub fn new(since: Option<&str>, manifest: Option<&str>) -> eyre::Result<ChangeFeed> {
+        let since = since
+            .map(|s| {
+                parse_iati_datetime(s).ok_or_else(|| eyre::eyre!("Couldn't parse timestamp '{}'.", s))
+            })
+            .transpose()?;
+
+        let manifest = manifest.map(PathBuf::from);
+        let previous = match &manifest {
+            Some(path) if path.is_file() => {
+                serde_json::from_reader(BufReader::new(File::open(path)?))?
+            }
+            _ => HashMap::new(),
+        };
+
+        Ok(ChangeFeed {
+            since,
+            manifest,
+            previous,
+            seen: Mutex::new(BTreeMap::new()),
+        })
+    }
+
+    /// Whether the file described by `source` (its `_source` object) is
+    /// identical to the previous run.
+    pub fn file_unchanged(&self, source: &Value) -> bool {
+        match source_hash(source) {
+            Some((path, hash)) => {
+                self.manifest.is_some() && self.previous.get(path).is_some_and(|h| h == hash)
+            }
+            None => false,
+        }
+    }
+
+    /// Records the file described by `source` for the next manifest. Call it
+    /// only once the file has converted, so a broken file is retried next run.
+    pub fn record_file(&self, source: &Value) {
+        if let (Some((path, hash)), Ok(mut seen)) = (source_hash(source), self.seen.lock()) {
+            seen.insert(path.to_owned(), hash.to_owned());
+        }
+    }
+
+    /// Whether a filtered activity belongs in the feed.
+    pub fn keep(&self, record: &Value) -> bool {
+        let since = match self.since {
+            Some(since) => since,
+            None => return true,
+        };
+        match record
+            .get("last-updated-datetime")
+            .and_then(Value::as_str)
+            .and_then(parse_iati_datetime)
+        {
+            Some(updated) => updated > since,
+            None => true,
+        }
+    }
+
+    /// Writes the hashes of every file converted in this run to the manifest.
+    /// Entries for files not converted this run, such as those left out by
+    /// include/exclude rules or that failed, keep their previous hash, so
+    /// widening the filter again doesn't force a full re-conversion. Call it
+    /// once the converted records have been stored.
+    pub fn commit(&self) -> eyre::Result<()> {
+        let path = match &self.manifest {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let seen = self
+            .seen
+            .lock()
+            .map_err(|_| eyre::eyre!("Manifest lock poisoned"))?;
+        let mut hashes: BTreeMap<&str, &str> = self
+            .previous
+            .iter()
+            .map(|(p, h)| (p.as_str(), h.as_str()))
+            .collect();
+        hashes.extend(seen.iter().map(|(p, h)| (p.as_str(), h.as_str())));
+        fs::write(path, serde_json::to_string_pretty(&hashes)?)?;
+        Ok(())
+    }
+}
+
+/// The `path` and `sha256` of a `_source` object.
+fn source_hash(source: &Value) -> Option<(&str, &str)> {
+    Some((
+        source.get("path")?.as_str()?,
+        source.get("sha256")?.as_str()?,
+    ))
+}
@@ -0,0 +1,126 @@
+use glob::Pattern;
+
+use std::path::{Component, Path, PathBuf};
+
+/// Include/exclude rules applied to dataset files in a dump.
+///
+/// A rule without glob metacharacters matches a publisher folder name
+/// exactly (e.g. `"dfid"`); anything else is treated as a glob against the
+/// path relative to the dump's `data/` folder (e.g. `"gb-*"` or
+/// `"usaid/*-old.xml"`), whether the dump is a folder or a zip. Files other
+/// than `.xml` (metadata.json, errors.txt, ...) are never filtered out. An
+/// `.xml` file outside any publisher folder (at the root, or directly in
+/// `data/`) only matches glob rules: exclude-only rules let it through, and
+/// include rules drop it unless a glob includes it.
+#[derive(Clone, Debug, Default)]
+pub struct PathFilter {
+    include: Vec<Rule>,
+    exclude: Vec<Rule>,
+}
+
+#[derive(Clone, Debug)]
+enum Rule {
+    Publisher(String),
+    Glob(Pattern),
+}
+
+impl Rule {
+    fn parse(raw: &str) -> eyre::Result<Rule> {
+        if raw.contains(['*', '?', '[']) {
+            let pattern = Pattern::new(raw)
+                .map_err(|e| eyre::eyre!("Invalid filter pattern '{}': {}", raw, e))?;
+            Ok(Rule::Glob(pattern))
+        } else {
+            Ok(Rule::Publisher(raw.to_owned()))
+        }
+    }
+
+    fn matches(&self, rel_path: &Path, publisher: Option<&str>) -> bool {
+        match self {
+            Rule::Publisher(p) => publisher == Some(p.as_str()),
+            Rule::Glob(g) => g.matches_path(rel_path),
+        }
+    }
+}
+
+impl PathFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> eyre::Result<PathFilter> {
+        Ok(PathFilter {
+            include: include
+                .iter()
+                .map(|r| Rule::parse(r))
+                .collect::<eyre::Result<_>>()?,
+            exclude: exclude
+                .iter()
+                .map(|r| Rule::parse(r))
+                .collect::<eyre::Result<_>>()?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Whether the file at `rel_path` (relative to the dump's data folder,
+    /// see [`data_relative`]) should be kept.
+    pub fn allows(&self, rel_path: &Path) -> bool {
+        if self.is_empty() || rel_path.extension().is_none_or(|ext| ext != "xml") {
+            return true;
+        }
+        let publisher = publisher_of(rel_path);
+        let publisher = publisher.as_deref();
+
+        if !self.include.is_empty() && !self.include.iter().any(|r| r.matches(rel_path, publisher))
+        {
+            return false;
+        }
+        !self.exclude.iter().any(|r| r.matches(rel_path, publisher))
+    }
+}
+
+/// Publisher folder of a dataset path: the component following `data/`, or
+/// the immediate parent folder of an `.xml` file when there is no `data`
+/// component.
+pub fn publisher_of(rel_path: &Path) -> Option<String> {
+    let parts: Vec<_> = rel_path
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(s) => Some(s.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect();
+
+    if parts.len() < 2 {
+        return None;
+    }
+    let folders = &parts[..parts.len() - 1];
+
+    match folders.iter().rposition(|p| p == "data") {
+        Some(i) if i + 1 < folders.len() => Some(folders[i + 1].clone()),
+        Some(_) => None,
+        None if rel_path.extension().is_some_and(|ext| ext == "xml") => folders.last().cloned(),
+        None => None,
+    }
+}
+
+/// A zip member's path from inside its `data/` folder, as filters match it:
+/// `iati-data-main/data/dfid/a.xml` is `dfid/a.xml`. Paths without a `data`
+/// folder are returned whole.
+pub fn data_relative(path: &Path) -> PathBuf {
+    let components: Vec<_> = path.components().collect();
+    let folders = components.len().saturating_sub(1);
+    match components[..folders]
+        .iter()
+        .rposition(|c| c.as_os_str() == "data")
+    {
+        Some(i) => components[i + 1..].iter().collect(),
+        None => path.to_path_buf(),
+    }
+}
+
+/// Rejects archive member paths that are absolute or climb out of the
+/// extraction directory.
+pub fn is_safe_relative(path: &Path) -> bool {
+    path.components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
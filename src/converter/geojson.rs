@@ -0,0 +1,129 @@
+//! GeoJSON export of activity locations for mapping tools: a
+//! FeatureCollection with one Point per location that has a valid position,
+//! carrying key fields of its activity as flat properties.
+
+use serde_json::{json, Map, Value};
+
+use crate::converter::model::{Code, FilteredActivity, FilteredLocation, LangText};
+
+fn text(text: &Option<LangText>) -> Value {
+    text.as_ref()
+        .and_then(LangText::text)
+        .map_or(Value::Null, |t| Value::String(t.to_owned()))
+}
+
+fn code(code: Option<&Code>) -> Value {
+    code.and_then(Code::as_string)
+        .map_or(Value::Null, Value::String)
+}
+
+/// Codes joined with commas, which GIS tools show better than arrays.
+fn joined(codes: impl Iterator<Item = Option<String>>) -> Value {
+    let codes: Vec<String> = codes.flatten().collect();
+    if codes.is_empty() {
+        Value::Null
+    } else {
+        Value::String(codes.join(","))
+    }
+}
+
+/// Properties shared by every feature of one activity.
+fn activity_properties(activity: &FilteredActivity, record: &Value) -> Map<String, Value> {
+    let mut properties = Map::new();
+    let mut add = |key: &str, value: Value| {
+        properties.insert(key.to_owned(), value);
+    };
+    add("iati_identifier", code(activity.iati_identifier.as_ref()));
+    add("title", text(&activity.title));
+    add(
+        "publisher",
+        record
+            .pointer("/_source/publisher")
+            .cloned()
+            .unwrap_or(Value::Null),
+    );
+    let org = activity.reporting_org.as_ref();
+    add("reporting_org", org.map_or(Value::Null, |o| text(&o.name)));
+    add("reporting_org_ref", code(org.and_then(|o| o.id.as_ref())));
+    add(
+        "activity_status",
+        code(
+            activity
+                .activity_status
+                .as_ref()
+                .and_then(|s| s.parsed()?.code.as_ref()),
+        ),
+    );
+    add(
+        "recipient_countries",
+        joined(
+            activity
+                .recipient_countries()
+                .map(|c| c.code.as_ref().and_then(Code::as_string)),
+        ),
+    );
+    add(
+        "sectors",
+        joined(
+            activity
+                .dac_sectors()
+                .map(|s| s.dac_code.as_ref().and_then(Code::as_string)),
+        ),
+    );
+    let totals = activity.totals();
+    add(
+        "currency",
+        totals.map_or(Value::Null, |t| json!(t.currency)),
+    );
+    add(
+        "commitment",
+        totals.map_or(Value::Null, |t| json!(t.commitment.total)),
+    );
+    add(
+        "disbursement",
+        totals.map_or(Value::Null, |t| json!(t.disbursement.total)),
+    );
+    properties
+}
+
+fn location_feature(location: &FilteredLocation, properties: &Map<String, Value>) -> Option<Value> {
+    let point = location.point?;
+    let mut properties = properties.clone();
+    properties.insert("location_ref".to_owned(), code(location.reference.as_ref()));
+    properties.insert("location_name".to_owned(), text(&location.name));
+    properties.insert(
+        "location_reach".to_owned(),
+        code(location.location_reach.as_ref()),
+    );
+    properties.insert("exactness".to_owned(), code(location.exactness.as_ref()));
+    Some(json!({
+        "type": "Feature",
+        // GeoJSON puts longitude first.
+        "geometry": {"type": "Point", "coordinates": [point.lon, point.lat]},
+        "properties": properties,
+    }))
+}
+
+/// Point features for the located places of one converted activity record.
+pub fn location_features(record: &Value) -> Vec<Value> {
+    let activity = match FilteredActivity::from_value(record) {
+        Ok(activity) => activity,
+        Err(e) => {
+            let file = record.pointer("/_source/path").and_then(Value::as_str);
+            eprintln!("Skipping an activity from {}: {}", file.unwrap_or("records"), e);
+            return Vec::new();
+        }
+    };
+    let properties = activity_properties(&activity, record);
+    activity
+        .locations()
+        .filter_map(|l| location_feature(l, &properties))
+        .collect()
+}
+
+/// A FeatureCollection of every located place in `records`. Locations
+/// without a valid position are left out.
+pub fn feature_collection(records: &[Value]) -> Value {
+    let features: Vec<Value> = records.iter().flat_map(location_features).collect();
+    json!({"type": "FeatureCollection", "features": features})
+}
@@ -0,0 +1,268 @@
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use serde::Serialize;
+
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::path::{Component, Path, PathBuf};
+
+/// What we know about a dataset listed in errors.txt after conversion setup.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    /// The dump contains a non-empty copy despite the error.
+    Present,
+    /// The file was missing or empty and has been back-filled.
+    Supplemented,
+    /// The file exists but is empty and couldn't be replaced.
+    Empty,
+    /// No copy in the dump and none could be found.
+    Missing,
+}
+
+impl FileStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileStatus::Present => "present",
+            FileStatus::Supplemented => "supplemented",
+            FileStatus::Empty => "empty",
+            FileStatus::Missing => "missing",
+        }
+    }
+}
+
+py_struct! {
+    /// One parsed line of errors.txt, cross-referenced with the data folder.
+    #[derive(Clone, Debug, Serialize)]
+    pub struct ErrorEntry {
+        /// 1-based line number in errors.txt.
+        #[pyo3(get)]
+        pub line: usize,
+        /// HTTP status code, when the error was an HTTP failure.
+        #[pyo3(get)]
+        pub status_code: Option<u16>,
+        /// `http` for status codes, otherwise the error name as written (e.g. `ConnectionError`).
+        #[pyo3(get)]
+        pub kind: String,
+        #[pyo3(get)]
+        pub publisher: String,
+        /// Dataset name without the `.xml` extension.
+        #[pyo3(get)]
+        pub dataset: String,
+        #[pyo3(get)]
+        pub url: Option<String>,
+        /// Anything after the URL on the line.
+        #[pyo3(get)]
+        pub detail: Option<String>,
+        pub status: FileStatus,
+        /// Where a supplemented copy came from, or why supplementing failed.
+        #[pyo3(get)]
+        pub note: Option<String>,
+        /// Name of the supplement source that provided the file.
+        #[pyo3(get)]
+        pub supplied_by: Option<String>,
+        /// Outcome of each supplement source tried, in order.
+        #[pyo3(get)]
+        pub attempts: Vec<String>,
+    }
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl ErrorEntry {
+    #[getter(status)]
+    fn py_status(&self) -> &'static str {
+        self.status.as_str()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "ErrorEntry({}/{} {} {})",
+            self.publisher,
+            self.dataset,
+            self.kind,
+            self.status.as_str()
+        )
+    }
+}
+
+impl ErrorEntry {
+    pub fn file_name(&self) -> String {
+        format!("{}.xml", self.dataset)
+    }
+
+    /// Expected location of the dataset file under `data_dir`. Entries from
+    /// [`parse_error_line`] always stay inside it.
+    pub fn path_in(&self, data_dir: &Path) -> PathBuf {
+        data_dir.join(&self.publisher).join(self.file_name())
+    }
+}
+
+py_struct! {
+    /// Health of a dump as described by its errors.txt.
+    #[derive(Clone, Debug, Default, Serialize)]
+    pub struct HealthReport {
+        /// Path of the errors.txt that was read, if there was one.
+        #[pyo3(get)]
+        pub errors_txt: Option<String>,
+        #[pyo3(get)]
+        pub entries: Vec<ErrorEntry>,
+        /// Lines that couldn't be parsed, verbatim.
+        #[pyo3(get)]
+        pub malformed_lines: Vec<String>,
+    }
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl HealthReport {
+    #[getter(present)]
+    fn py_present(&self) -> usize {
+        self.present()
+    }
+
+    #[getter(supplemented)]
+    fn py_supplemented(&self) -> usize {
+        self.supplemented()
+    }
+
+    #[getter(missing)]
+    fn py_missing(&self) -> usize {
+        self.missing()
+    }
+
+    pub fn to_json(&self) -> PyResult<String> {
+        serde_json::to_string(self)
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))
+    }
+
+    fn __repr__(&self) -> String {
+        self.summary()
+    }
+}
+
+impl HealthReport {
+    pub fn present(&self) -> usize {
+        self.count(FileStatus::Present)
+    }
+
+    pub fn supplemented(&self) -> usize {
+        self.count(FileStatus::Supplemented)
+    }
+
+    pub fn missing(&self) -> usize {
+        self.count(FileStatus::Missing) + self.count(FileStatus::Empty)
+    }
+
+    pub fn count(&self, status: FileStatus) -> usize {
+        self.entries.iter().filter(|e| e.status == status).count()
+    }
+
+    pub fn summary(&self) -> String {
+        match &self.errors_txt {
+            None => "No errors.txt file found.".to_owned(),
+            Some(_) => format!(
+                "{} errors ({} malformed lines): {} present, {} supplemented, {} missing.",
+                self.entries.len(),
+                self.malformed_lines.len(),
+                self.present(),
+                self.supplemented(),
+                self.missing()
+            ),
+        }
+    }
+}
+
+/// Whether `name` is a single plain file or folder name, so joining it onto
+/// the data folder can't climb out of it.
+fn is_plain_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!(components.next(), Some(Component::Normal(_))) && components.next().is_none()
+}
+
+/// Parses one line of errors.txt: `<status-or-error> <publisher> <dataset> [<url> [detail...]]`.
+/// Lines whose publisher or dataset isn't a plain name (e.g. `../x`) are rejected.
+pub fn parse_error_line(line_no: usize, line: &str) -> Option<ErrorEntry> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() < 3 {
+        return None;
+    }
+
+    let status_code = parts[0].parse::<u16>().ok();
+    let kind = match status_code {
+        Some(_) => "http".to_owned(),
+        None => parts[0].trim_end_matches(':').to_owned(),
+    };
+    let dataset = parts[2].trim_end_matches(".xml").to_owned();
+    if !is_plain_name(parts[1]) || !is_plain_name(&dataset) {
+        return None;
+    }
+    let detail = (parts.len() > 4).then(|| parts[4..].join(" "));
+
+    Some(ErrorEntry {
+        line: line_no,
+        status_code,
+        kind,
+        publisher: parts[1].to_owned(),
+        dataset,
+        url: parts.get(3).map(|u| u.to_string()),
+        detail,
+        status: FileStatus::Missing,
+        note: None,
+        supplied_by: None,
+        attempts: Vec::new(),
+    })
+}
+
+/// Current status of a dataset file on disk.
+pub fn file_status(path: &Path) -> FileStatus {
+    match fs::metadata(path) {
+        Ok(m) if m.is_file() && m.len() > 0 => FileStatus::Present,
+        Ok(m) if m.is_file() => FileStatus::Empty,
+        _ => FileStatus::Missing,
+    }
+}
+
+/// Reads errors.txt next to `data_dir` and records whether each listed file
+/// is present. Returns an empty report when there is no errors.txt.
+pub fn read_health(data_dir: &Path) -> eyre::Result<HealthReport> {
+    let root_dir = data_dir.parent().unwrap_or(Path::new("."));
+    let errors_txt_path = root_dir.join("errors.txt");
+
+    if !errors_txt_path.exists() {
+        return Ok(HealthReport::default());
+    }
+
+    let errors_file = File::open(&errors_txt_path)
+        .map_err(|e| eyre::eyre!("Failed to open {}: {}", errors_txt_path.display(), e))?;
+    let lines: Vec<String> = BufReader::new(errors_file)
+        .lines()
+        .collect::<Result<_, io::Error>>()
+        .map_err(|e| {
+            eyre::eyre!(
+                "Failed to read lines from {}: {}",
+                errors_txt_path.display(),
+                e
+            )
+        })?;
+
+    let mut report = HealthReport {
+        errors_txt: Some(errors_txt_path.to_string_lossy().into_owned()),
+        ..Default::default()
+    };
+
+    for (i, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_error_line(i + 1, line) {
+            Some(mut entry) => {
+                entry.status = file_status(&entry.path_in(data_dir));
+                report.entries.push(entry);
+            }
+            None => report.malformed_lines.push(line.clone()),
+        }
+    }
+
+    Ok(report)
+}
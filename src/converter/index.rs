@@ -0,0 +1,416 @@
+//! Local full-text index over converted activities, built with the `index`
+//! feature.
+//!
+//! Text comes from each record's `meta.search` document, split into `title`,
+//! `description`, `org` and `text` (results, indicators and sector names).
+//! Recipient countries, sector codes, publishers and activity statuses are
+//! indexed as exact codes for filtering. Only the identifier, title,
+//! reporting org name and publisher are stored, to show with each hit.
+
+use serde::Serialize;
+use serde_json::Value;
+use tantivy::collector::TopDocs;
+use tantivy::query::{AllQuery, BooleanQuery, Occur, Query, QueryParser, TermQuery};
+use tantivy::schema::{Field, IndexRecordOption, Schema, Value as _, STORED, STRING, TEXT};
+use tantivy::{Index, IndexWriter, TantivyDocument, Term};
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::converter::model::{FilteredActivity, LangText};
+use crate::converter::pipeline::{fold_dump, load_records};
+use crate::converter::settings::Settings;
+use crate::converter::{convert_xml_to_json, decode_xml, filter_activities};
+
+const WRITER_MEMORY: usize = 100_000_000;
+
+/// The index schema's fields.
+struct Fields {
+    id: Field,
+    title: Field,
+    description: Field,
+    org: Field,
+    text: Field,
+    country: Field,
+    sector: Field,
+    publisher: Field,
+    status: Field,
+    display_title: Field,
+    display_org: Field,
+}
+
+impl Fields {
+    fn schema() -> Schema {
+        let mut builder = Schema::builder();
+        builder.add_text_field("id", STRING | STORED);
+        builder.add_text_field("title", TEXT);
+        builder.add_text_field("description", TEXT);
+        builder.add_text_field("org", TEXT);
+        builder.add_text_field("text", TEXT);
+        builder.add_text_field("country", STRING);
+        builder.add_text_field("sector", STRING);
+        builder.add_text_field("publisher", STRING | STORED);
+        builder.add_text_field("status", STRING);
+        builder.add_text_field("display_title", STORED);
+        builder.add_text_field("display_org", STORED);
+        builder.build()
+    }
+
+    fn of(schema: &Schema) -> eyre::Result<Fields> {
+        let field = |name: &str| {
+            schema
+                .get_field(name)
+                .map_err(|_| eyre::eyre!("Search index has no {:?} field; rebuild it.", name))
+        };
+        Ok(Fields {
+            id: field("id")?,
+            title: field("title")?,
+            description: field("description")?,
+            org: field("org")?,
+            text: field("text")?,
+            country: field("country")?,
+            sector: field("sector")?,
+            publisher: field("publisher")?,
+            status: field("status")?,
+            display_title: field("display_title")?,
+            display_org: field("display_org")?,
+        })
+    }
+
+    /// Index field holding text from a `meta.search` field.
+    fn for_search_field(&self, field: &str) -> Field {
+        match field {
+            "title" => self.title,
+            f if f.starts_with("description") => self.description,
+            "reporting-org" | "participating-org" => self.org,
+            _ => self.text,
+        }
+    }
+
+    fn document(
+        &self,
+        record: &Value,
+        publisher: Option<&str>,
+    ) -> Result<TantivyDocument, serde_json::Error> {
+        let mut doc = TantivyDocument::default();
+
+        let activity = FilteredActivity::from_value(record)?;
+        if let Some(id) = activity.identifier() {
+            doc.add_text(self.id, id);
+        }
+        if let Some(title) = activity.title.as_ref().and_then(LangText::text) {
+            doc.add_text(self.display_title, title);
+        }
+        let org = activity.reporting_org.as_ref().and_then(|o| o.name.as_ref());
+        if let Some(org) = org.and_then(LangText::text) {
+            doc.add_text(self.display_org, org);
+        }
+
+        let entries = record.pointer("/meta/search").and_then(Value::as_array);
+        match entries {
+            Some(entries) => {
+                for entry in entries {
+                    let field = entry.get("field").and_then(Value::as_str).unwrap_or("");
+                    if let Some(text) = entry.get("text").and_then(Value::as_str) {
+                        doc.add_text(self.for_search_field(field), text);
+                    }
+                }
+            }
+            // Converted without a search document: fall back to the title,
+            // descriptions and reporting org name.
+            None => {
+                let mut add = |field: Field, text: &Option<LangText>| {
+                    for text in lang_texts(text.as_ref()) {
+                        doc.add_text(field, text);
+                    }
+                };
+                add(self.title, &activity.title);
+                for description in activity.description.iter().flatten() {
+                    add(self.description, &Some(description.narrative.clone()));
+                }
+                if let Some(org) = &activity.reporting_org {
+                    add(self.org, &org.name);
+                }
+            }
+        }
+
+        for country in activity.recipient_countries() {
+            if let Some(code) = country.code.as_ref().and_then(|c| c.as_string()) {
+                doc.add_text(self.country, code.to_ascii_uppercase());
+            }
+        }
+        for sector in activity.sectors() {
+            if let Some(code) = sector.dac_code.as_ref().and_then(|c| c.as_string()) {
+                doc.add_text(self.sector, code);
+            }
+            // A 5-digit DAC code also matches its 3-digit category, e.g. 14030 under 140.
+            if let Some(category) = &sector.dac_category {
+                doc.add_text(self.sector, category);
+            }
+        }
+        if let Some(publisher) = publisher {
+            doc.add_text(self.publisher, publisher);
+        }
+        let status = activity
+            .activity_status
+            .as_ref()
+            .and_then(|s| s.parsed())
+            .and_then(|s| s.code.as_ref()?.as_string());
+        if let Some(status) = status {
+            doc.add_text(self.status, status);
+        }
+        Ok(doc)
+    }
+}
+
+fn lang_texts(text: Option<&LangText>) -> Vec<&str> {
+    match text {
+        Some(LangText::One(text)) => vec![text.as_str()],
+        Some(LangText::ByLang(texts)) => texts.values().map(String::as_str).collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Opens or creates the index in `index_dir` and empties it for a rebuild.
+fn rebuild_writer(index_dir: &Path) -> eyre::Result<(IndexWriter, Fields)> {
+    fs::create_dir_all(index_dir)?;
+    let index = if index_dir.join("meta.json").exists() {
+        let index = Index::open_in_dir(index_dir)?;
+        if index.schema() != Fields::schema() {
+            return Err(eyre::eyre!(
+                "{} holds an index with a different schema; remove it first.",
+                index_dir.display()
+            ));
+        }
+        index
+    } else {
+        Index::create_in_dir(index_dir, Fields::schema())?
+    };
+    let fields = Fields::of(&index.schema())?;
+
+    let writer: IndexWriter = index.writer(WRITER_MEMORY)?;
+    writer.delete_all_documents()?;
+    Ok((writer, fields))
+}
+
+fn record_publisher(record: &Value) -> Option<&str> {
+    record.pointer("/_source/publisher").and_then(Value::as_str)
+}
+
+/// Builds a search index of converted `records` in `index_dir`, replacing
+/// whatever an earlier build left there. Records that aren't activities are
+/// reported and skipped. Returns the number of activities indexed.
+pub fn index_records(records: &[Value], index_dir: &Path) -> eyre::Result<usize> {
+    let (mut writer, fields) = rebuild_writer(index_dir)?;
+    let mut count = 0;
+    for (i, record) in records.iter().enumerate() {
+        let publisher = record_publisher(record);
+        if add_record(&writer, &fields, record, publisher, format_args!("record {}", i))? {
+            count += 1;
+        }
+    }
+    writer.commit()?;
+    Ok(count)
+}
+
+/// Adds one record to the index, reporting and skipping a record that isn't
+/// an activity. Returns whether it was added.
+fn add_record(
+    writer: &IndexWriter,
+    fields: &Fields,
+    record: &Value,
+    publisher: Option<&str>,
+    origin: fmt::Arguments,
+) -> eyre::Result<bool> {
+    match fields.document(record, publisher) {
+        Ok(doc) => {
+            writer.add_document(doc)?;
+            Ok(true)
+        }
+        Err(e) => {
+            eprintln!("Skipping {}: {}", origin, e);
+            Ok(false)
+        }
+    }
+}
+
+/// Builds a search index of a dump folder, zip, single file, snapshot or a
+/// JSON file written by `convert` in `index_dir`, replacing whatever an
+/// earlier build left there. Dump files are converted and added in parallel
+/// through one writer, without keeping the records; files that fail to
+/// convert and records that aren't activities are reported and skipped.
+/// Returns the number of activities indexed.
+pub fn build_index(path: &str, settings: &Settings, index_dir: &Path) -> eyre::Result<usize> {
+    let p = Path::new(path);
+    if p.is_file() && p.extension().is_some_and(|ext| ext == "json") {
+        return index_records(&load_records(path, settings)?, index_dir);
+    }
+
+    let options = settings.filter_options()?;
+    let (mut writer, fields) = rebuild_writer(index_dir)?;
+    let count = fold_dump(
+        path,
+        settings,
+        || Ok(0),
+        |count: eyre::Result<usize>, file| {
+            let mut count = count?;
+            let records = file
+                .bytes
+                .and_then(|b| decode_xml(b, file.path))
+                .and_then(|xml| convert_xml_to_json(&xml))
+                .map(|json| filter_activities(&json, &options, file.path));
+            let records = match records {
+                Ok(records) => records,
+                Err(e) => {
+                    eprintln!("Skipping {}: {}", file.path.display(), e);
+                    return Ok(count);
+                }
+            };
+            for (i, record) in records.iter().enumerate() {
+                let publisher = file.publisher.as_deref();
+                let origin = format_args!("record {} of {}", i, file.path.display());
+                if add_record(&writer, &fields, record, publisher, origin)? {
+                    count += 1;
+                }
+            }
+            Ok(count)
+        },
+        |a, b| Ok(a? + b?),
+    )??;
+    writer.commit()?;
+    Ok(count)
+}
+
+/// Exact-match filters. Values for one key are alternatives; keys must all match.
+#[derive(Clone, Debug, Default)]
+pub struct SearchFilters {
+    /// Recipient country codes, e.g. `KE`.
+    pub country: Vec<String>,
+    /// DAC sector codes, 5-digit or a 3-digit category such as `140` for WASH.
+    pub sector: Vec<String>,
+    pub publisher: Vec<String>,
+    /// Activity status codes.
+    pub status: Vec<String>,
+}
+
+impl SearchFilters {
+    /// Reads filters from a `{"country": ["KE"], ...}` map, rejecting unknown keys.
+    pub fn from_map(map: BTreeMap<String, Vec<String>>) -> eyre::Result<SearchFilters> {
+        let mut filters = SearchFilters::default();
+        for (key, values) in map {
+            let target = match key.as_str() {
+                "country" => &mut filters.country,
+                "sector" => &mut filters.sector,
+                "publisher" => &mut filters.publisher,
+                "status" => &mut filters.status,
+                other => return Err(eyre::eyre!(
+                    "Unknown search filter {:?}; expected country, sector, publisher or status.",
+                    other
+                )),
+            };
+            target.extend(values);
+        }
+        Ok(filters)
+    }
+}
+
+/// One search result, best first.
+#[derive(Clone, Debug, Serialize)]
+pub struct SearchHit {
+    pub score: f32,
+    pub iati_identifier: Option<String>,
+    /// The title in the preferred language, or the first language alphabetically.
+    pub title: Option<String>,
+    /// The reporting org's name, picked by language as `title` is.
+    pub reporting_org: Option<String>,
+    pub publisher: Option<String>,
+}
+
+/// Searches an index built by [`build_index`].
+///
+/// `query` uses tantivy's query syntax over the title, description, org and
+/// text fields, with every word required unless joined by `OR`; quote phrases
+/// and prefix a field (`title:water`) to narrow it. An empty query matches
+/// every activity, so filters can be used on their own. `weights` are the
+/// `meta.search` weights, used to boost matches by field.
+pub fn search_index(
+    index_dir: &Path,
+    query: &str,
+    filters: &SearchFilters,
+    weights: &BTreeMap<String, f64>,
+    limit: usize,
+) -> eyre::Result<Vec<SearchHit>> {
+    let index = Index::open_in_dir(index_dir)
+        .map_err(|e| eyre::eyre!("Can't open search index {}: {}", index_dir.display(), e))?;
+    let fields = Fields::of(&index.schema())?;
+
+    let text_query: Box<dyn Query> = if query.trim().is_empty() {
+        Box::new(AllQuery)
+    } else {
+        let text_fields = [fields.title, fields.description, fields.org, fields.text];
+        let mut parser = QueryParser::for_index(&index, text_fields.to_vec());
+        parser.set_conjunction_by_default();
+        for field in text_fields {
+            let boost = weights
+                .iter()
+                .filter(|(name, _)| fields.for_search_field(name) == field)
+                .map(|(_, w)| *w)
+                .fold(0.0, f64::max);
+            if boost > 0.0 {
+                parser.set_field_boost(field, boost as f32);
+            }
+        }
+        parser.parse_query(query)?
+    };
+
+    let mut clauses = vec![(Occur::Must, text_query)];
+    let exact = [
+        (fields.country, &filters.country),
+        (fields.sector, &filters.sector),
+        (fields.publisher, &filters.publisher),
+        (fields.status, &filters.status),
+    ];
+    for (field, values) in exact {
+        if values.is_empty() {
+            continue;
+        }
+        let any: Vec<(Occur, Box<dyn Query>)> = values
+            .iter()
+            .map(|v| {
+                // Countries are indexed upper-case.
+                let v = if field == fields.country {
+                    v.trim().to_ascii_uppercase()
+                } else {
+                    v.trim().to_owned()
+                };
+                let term = Term::from_field_text(field, &v);
+                let query: Box<dyn Query> =
+                    Box::new(TermQuery::new(term, IndexRecordOption::Basic));
+                (Occur::Should, query)
+            })
+            .collect();
+        clauses.push((Occur::Must, Box::new(BooleanQuery::new(any))));
+    }
+    let query = BooleanQuery::new(clauses);
+
+    let searcher = index.reader()?.searcher();
+    let mut hits = Vec::new();
+    for (score, address) in searcher.search(&query, &TopDocs::with_limit(limit))? {
+        let doc: TantivyDocument = searcher.doc(address)?;
+        let stored = |field: Field| {
+            doc.get_first(field)
+                .and_then(|v| v.as_str())
+                .map(str::to_owned)
+        };
+        hits.push(SearchHit {
+            score,
+            iati_identifier: stored(fields.id),
+            title: stored(fields.display_title),
+            reporting_org: stored(fields.display_org),
+            publisher: stored(fields.publisher),
+        });
+    }
+    Ok(hits)
+}
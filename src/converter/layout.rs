@@ -0,0 +1,392 @@
+use serde_json::Value;
+use zip::ZipArchive;
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::converter::filter::{is_safe_relative, publisher_of};
+use crate::converter::utils::find_data_folder;
+
+/// Publisher and registry metadata for a single dataset file.
+#[derive(Clone, Debug, Default)]
+pub struct DatasetInfo {
+    /// Registry publisher id, usually the folder the file sits in.
+    pub publisher: Option<String>,
+    /// Registry dataset name, the file name without `.xml`.
+    pub dataset: String,
+    /// Contents of the dataset's registry metadata file, when the layout has one.
+    pub metadata: Option<Value>,
+}
+
+/// Read access to the files a layout looks at, on disk or inside a zip.
+pub trait SourceTree: Send + Sync {
+    fn is_file(&self, path: &Path) -> bool;
+
+    fn is_dir(&self, path: &Path) -> bool;
+
+    /// Paths directly inside `dir`.
+    fn children(&self, dir: &Path) -> Vec<PathBuf>;
+
+    fn read(&self, path: &Path) -> Option<Vec<u8>>;
+}
+
+/// The local filesystem.
+pub struct Disk;
+
+impl SourceTree for Disk {
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn children(&self, dir: &Path) -> Vec<PathBuf> {
+        fs::read_dir(dir)
+            .map(|entries| entries.flatten().map(|e| e.path()).collect())
+            .unwrap_or_default()
+    }
+
+    fn read(&self, path: &Path) -> Option<Vec<u8>> {
+        fs::read(path).ok()
+    }
+}
+
+/// The entry names of a zipped dump and the contents of its JSON files, so
+/// the layout and registry metadata can be read without extracting it.
+/// Paths are relative to the archive root.
+pub struct ZipTree {
+    dirs: HashSet<PathBuf>,
+    files: HashSet<PathBuf>,
+    json: HashMap<PathBuf, Vec<u8>>,
+}
+
+impl ZipTree {
+    pub fn open(zip_path: &Path) -> eyre::Result<Self> {
+        let mut archive = ZipArchive::new(File::open(zip_path)?)?;
+        let mut tree = ZipTree {
+            dirs: HashSet::from([PathBuf::new()]),
+            files: HashSet::new(),
+            json: HashMap::new(),
+        };
+
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i)?;
+            let name = match file.enclosed_name() {
+                Some(name) if is_safe_relative(name) => name.to_path_buf(),
+                _ => continue,
+            };
+            tree.dirs
+                .extend(name.ancestors().skip(1).map(Path::to_path_buf));
+            if file.is_dir() {
+                tree.dirs.insert(name);
+            } else {
+                if name.extension().is_some_and(|ext| ext == "json") {
+                    let mut bytes = Vec::new();
+                    file.read_to_end(&mut bytes)?;
+                    tree.json.insert(name.clone(), bytes);
+                }
+                tree.files.insert(name);
+            }
+        }
+
+        Ok(tree)
+    }
+}
+
+impl SourceTree for ZipTree {
+    fn is_file(&self, path: &Path) -> bool {
+        self.files.contains(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.dirs.contains(path)
+    }
+
+    fn children(&self, dir: &Path) -> Vec<PathBuf> {
+        self.dirs
+            .iter()
+            .chain(&self.files)
+            .filter(|p| !p.as_os_str().is_empty() && p.parent() == Some(dir))
+            .cloned()
+            .collect()
+    }
+
+    fn read(&self, path: &Path) -> Option<Vec<u8>> {
+        self.json.get(path).cloned()
+    }
+}
+
+/// A way registry data can be laid out on disk or in a zip.
+pub trait SourceLayout: Sync {
+    fn name(&self) -> &'static str;
+
+    /// Whether `root` is laid out this way.
+    fn detect(&self, tree: &dyn SourceTree, root: &Path) -> bool;
+
+    /// Folder holding the XML files.
+    fn data_dir(&self, root: &Path) -> PathBuf {
+        root.join("data")
+    }
+
+    /// Maps an XML file under `data_dir` to its publisher and metadata.
+    fn dataset_info(&self, tree: &dyn SourceTree, root: &Path, xml_path: &Path) -> DatasetInfo;
+}
+
+/// Open Data Services daily dump: `metadata.json`, `errors.txt` and
+/// `data/<publisher>/<dataset>.xml`.
+pub struct OdsDump;
+
+/// codeforIATI iati-data repository: `data/<publisher>/<dataset>.xml` with
+/// `metadata/<publisher>/<dataset>.json` alongside.
+pub struct CodeforIati;
+
+/// Registry package export: `data/<publisher>/<dataset>.xml` with a flat
+/// `metadata/<dataset>.json` per dataset.
+pub struct RegistryMetadata;
+
+/// Any folder of XML files; the publisher is the folder each file sits in.
+pub struct FlatFolder;
+
+/// Layouts in detection order, most specific first. `FlatFolder` takes any
+/// folder, so it is last and only used once no other layout was found.
+pub static LAYOUTS: &[&dyn SourceLayout] =
+    &[&OdsDump, &CodeforIati, &RegistryMetadata, &FlatFolder];
+
+fn dataset_name(xml_path: &Path) -> String {
+    xml_path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn read_metadata(tree: &dyn SourceTree, path: &Path) -> Option<Value> {
+    let content = tree.read(path)?;
+    serde_json::from_slice(&content).ok()
+}
+
+/// Metadata file at `metadata/<publisher>/<dataset>.json`, if present.
+fn nested_metadata(
+    tree: &dyn SourceTree,
+    root: &Path,
+    publisher: Option<&str>,
+    dataset: &str,
+) -> Option<Value> {
+    let publisher = publisher?;
+    read_metadata(
+        tree,
+        &root
+            .join("metadata")
+            .join(publisher)
+            .join(format!("{}.json", dataset)),
+    )
+}
+
+/// Publisher from the folder, metadata from `metadata/<publisher>/<dataset>.json`.
+fn nested_dataset_info(tree: &dyn SourceTree, root: &Path, xml_path: &Path) -> DatasetInfo {
+    let publisher = publisher_of(xml_path);
+    let dataset = dataset_name(xml_path);
+    let metadata = nested_metadata(tree, root, publisher.as_deref(), &dataset);
+    DatasetInfo {
+        publisher,
+        dataset,
+        metadata,
+    }
+}
+
+fn has_nested_metadata(tree: &dyn SourceTree, root: &Path) -> bool {
+    tree.children(&root.join("metadata"))
+        .iter()
+        .any(|p| tree.is_dir(p))
+}
+
+fn has_flat_metadata(tree: &dyn SourceTree, root: &Path) -> bool {
+    tree.children(&root.join("metadata"))
+        .iter()
+        .any(|p| p.extension().is_some_and(|ext| ext == "json"))
+}
+
+impl SourceLayout for OdsDump {
+    fn name(&self) -> &'static str {
+        "ods-dump"
+    }
+
+    fn detect(&self, tree: &dyn SourceTree, root: &Path) -> bool {
+        tree.is_file(&root.join("metadata.json")) && tree.is_dir(&root.join("data"))
+    }
+
+    fn dataset_info(&self, tree: &dyn SourceTree, root: &Path, xml_path: &Path) -> DatasetInfo {
+        nested_dataset_info(tree, root, xml_path)
+    }
+}
+
+impl SourceLayout for CodeforIati {
+    fn name(&self) -> &'static str {
+        "codeforiati"
+    }
+
+    fn detect(&self, tree: &dyn SourceTree, root: &Path) -> bool {
+        tree.is_dir(&root.join("data")) && has_nested_metadata(tree, root)
+    }
+
+    fn dataset_info(&self, tree: &dyn SourceTree, root: &Path, xml_path: &Path) -> DatasetInfo {
+        nested_dataset_info(tree, root, xml_path)
+    }
+}
+
+impl SourceLayout for RegistryMetadata {
+    fn name(&self) -> &'static str {
+        "registry-metadata"
+    }
+
+    fn detect(&self, tree: &dyn SourceTree, root: &Path) -> bool {
+        tree.is_dir(&root.join("data")) && has_flat_metadata(tree, root)
+    }
+
+    fn dataset_info(&self, tree: &dyn SourceTree, root: &Path, xml_path: &Path) -> DatasetInfo {
+        let dataset = dataset_name(xml_path);
+        let metadata = read_metadata(tree, &root.join("metadata").join(format!("{}.json", dataset)));
+
+        // Registry packages name their publisher under `organization.name`
+        let publisher = metadata
+            .as_ref()
+            .and_then(|m| m.get("organization"))
+            .and_then(|o| o.get("name"))
+            .and_then(Value::as_str)
+            .map(str::to_owned)
+            .or_else(|| publisher_of(xml_path));
+
+        DatasetInfo {
+            publisher,
+            dataset,
+            metadata,
+        }
+    }
+}
+
+impl SourceLayout for FlatFolder {
+    fn name(&self) -> &'static str {
+        "flat"
+    }
+
+    fn detect(&self, tree: &dyn SourceTree, root: &Path) -> bool {
+        tree.is_dir(root)
+    }
+
+    fn data_dir(&self, root: &Path) -> PathBuf {
+        root.to_path_buf()
+    }
+
+    fn dataset_info(&self, _tree: &dyn SourceTree, _root: &Path, xml_path: &Path) -> DatasetInfo {
+        DatasetInfo {
+            publisher: publisher_of(xml_path),
+            dataset: dataset_name(xml_path),
+            metadata: None,
+        }
+    }
+}
+
+/// A source root together with the layout it was recognised as. For a zip,
+/// `root` and the paths passed to `dataset_info` are archive entry names.
+pub struct DetectedSource {
+    pub root: PathBuf,
+    pub layout: &'static dyn SourceLayout,
+    tree: Box<dyn SourceTree>,
+}
+
+impl DetectedSource {
+    pub fn data_dir(&self) -> PathBuf {
+        self.layout.data_dir(&self.root)
+    }
+
+    pub fn dataset_info(&self, xml_path: &Path) -> DatasetInfo {
+        self.layout.dataset_info(self.tree.as_ref(), &self.root, xml_path)
+    }
+}
+
+fn detect_with(
+    tree: &dyn SourceTree,
+    root: &Path,
+    layouts: &[&'static dyn SourceLayout],
+) -> Option<&'static dyn SourceLayout> {
+    layouts.iter().find(|layout| layout.detect(tree, root)).copied()
+}
+
+/// A layout other than `FlatFolder` that `root` is laid out as.
+fn detect_at(tree: &dyn SourceTree, root: &Path) -> Option<&'static dyn SourceLayout> {
+    detect_with(tree, root, &LAYOUTS[..LAYOUTS.len() - 1])
+}
+
+fn detected(
+    root: &Path,
+    layout: &'static dyn SourceLayout,
+    tree: impl SourceTree + 'static,
+) -> DetectedSource {
+    DetectedSource {
+        root: root.to_path_buf(),
+        layout,
+        tree: Box::new(tree),
+    }
+}
+
+/// Works out how the data at `path` is laid out.
+///
+/// Checks `path` itself, its parent when `path` is a `data` folder, then the
+/// folder containing the first nested `data` folder (for dynamically named
+/// archive roots such as `iati-data-main/`). Anything else is a flat folder.
+pub fn detect_source(path: &Path) -> eyre::Result<DetectedSource> {
+    if !path.is_dir() {
+        return Err(eyre::eyre!("{} is not a directory.", path.display()));
+    }
+
+    if let Some(layout) = detect_at(&Disk, path) {
+        return Ok(detected(path, layout, Disk));
+    }
+
+    if path.file_name().is_some_and(|name| name == "data") {
+        if let Some(parent) = path.parent() {
+            if let Some(layout) = detect_at(&Disk, parent) {
+                return Ok(detected(parent, layout, Disk));
+            }
+        }
+    }
+
+    if let Some(data_dir) = find_data_folder(path)? {
+        if let Some(root) = data_dir.parent() {
+            if let Some(layout) = detect_at(&Disk, root) {
+                return Ok(detected(root, layout, Disk));
+            }
+        }
+    }
+
+    detect_with(&Disk, path, LAYOUTS)
+        .map(|layout| detected(path, layout, Disk))
+        .ok_or_else(|| eyre::eyre!("{} matches no source layout.", path.display()))
+}
+
+/// Works out how the data in a zipped dump is laid out, as `detect_source`
+/// does for a folder: the archive root, then the folder containing the
+/// shallowest `data` folder, and otherwise a flat folder.
+pub fn detect_zip_source(zip_path: &Path) -> eyre::Result<DetectedSource> {
+    let tree = ZipTree::open(zip_path)?;
+
+    let nested_root = tree
+        .dirs
+        .iter()
+        .filter(|d| d.file_name().is_some_and(|name| name == "data"))
+        .min_by_key(|d| (d.components().count(), d.to_path_buf()))
+        .and_then(|d| d.parent())
+        .map(Path::to_path_buf);
+
+    for root in std::iter::once(PathBuf::new()).chain(nested_root) {
+        if let Some(layout) = detect_at(&tree, &root) {
+            return Ok(detected(&root, layout, tree));
+        }
+    }
+
+    Ok(detected(Path::new(""), &FlatFolder, tree))
+}
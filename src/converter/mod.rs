@@ -10,17 +10,96 @@ use rayon::prelude::*;
 
 use indicatif::ProgressBar;
 
-use crate::converter::utils::filter_activity;
+use zip::ZipArchive;
+
+use crate::converter::feed::ChangeFeed;
+use crate::converter::filter::{data_relative, is_safe_relative, PathFilter};
+use crate::converter::layout::DetectedSource;
+use crate::converter::provenance::{attach_source, source_record};
+use crate::converter::utils::{filter_activity_with, FilterOptions};
 
 use self::utils::{preprocess_xml, try_convert_xml_to_json};
 
+pub mod allocation;
+pub mod api;
+pub mod diff;
+pub mod feed;
+pub mod filter;
+pub mod geojson;
+pub mod health;
+#[cfg(feature = "index")]
+pub mod index;
+pub mod layout;
+pub mod model;
+pub mod organisations;
+pub mod orgid;
+pub mod pipeline;
+pub mod provenance;
+pub mod rollup;
+pub mod search;
+pub mod settings;
+pub mod snapshot;
+pub mod stats;
+pub mod supplement;
 pub mod utils;
 
+pub use self::api::{convert_dir, convert_file, Activities, Converter, ConverterBuilder};
 
 use eyre::Report;
 
-fn read_xml(path: &Path) -> Result<String, Report> {
-    let attempt = fs::read_to_string(path);
+/// Everything `walk_and_convert` and `convert_zip` need besides the path.
+#[derive(Default)]
+pub struct ConvertContext {
+    pub filter: PathFilter,
+    /// Layout of the folder or zip being read; `None` for a single file.
+    pub source: Option<DetectedSource>,
+    /// When the dump was downloaded, recorded in each record's `_source`.
+    pub downloaded_at: Option<String>,
+    /// Limits output to activities changed since the last sync.
+    pub feed: Option<ChangeFeed>,
+    pub options: FilterOptions,
+    /// Visit files in path order so every run gives the same output order.
+    pub deterministic: bool,
+    /// Back-fill files listed in a dump folder's errors.txt, writing into the dump.
+    pub supplement: bool,
+}
+
+impl ConvertContext {
+    /// Whether a file can be skipped because the change feed saw it unchanged.
+    fn skip_file(&self, source: &Value) -> bool {
+        self.feed.as_ref().is_some_and(|f| f.file_unchanged(source))
+    }
+
+    /// Whether the filter keeps a file of the tree being walked, matched
+    /// relative to the dump's data folder.
+    fn allows(&self, path: &Path) -> bool {
+        let data_dir = self.source.as_ref().map(DetectedSource::data_dir);
+        let relative = data_dir
+            .as_deref()
+            .and_then(|d| path.strip_prefix(d).ok())
+            .unwrap_or(path);
+        self.filter.allows(relative)
+    }
+
+    /// Records a file that converted with the change feed.
+    fn converted_file(&self, source: &Value) {
+        if let Some(feed) = &self.feed {
+            feed.record_file(source);
+        }
+    }
+
+    /// Drops records the change feed doesn't want.
+    fn retain_changed(&self, records: &mut Vec<Value>) {
+        if let Some(feed) = &self.feed {
+            records.retain(|r| feed.keep(r));
+        }
+    }
+}
+
+/// Decodes raw file bytes as UTF-8, falling back to UTF-16 when a BOM is
+/// present. `path` is only used in error messages.
+pub fn decode_xml(bytes: Vec<u8>, path: &Path) -> Result<String, Report> {
+    let attempt = String::from_utf8(bytes);
     match attempt {
         Ok(content) => {
             // Check for XML declaration or absence of HTML declaration
@@ -33,9 +112,8 @@ fn read_xml(path: &Path) -> Result<String, Report> {
                 Ok(content)
             }
         }
-        Err(_e) => {
-            // Read raw bytes
-            let bytes = fs::read(path)?;
+        Err(e) => {
+            let bytes = e.into_bytes();
 
             // Check the first two bytes to guess the encoding
             let decoded = if bytes.starts_with(&[0xFF, 0xFE]) {
@@ -73,6 +151,11 @@ fn read_xml(path: &Path) -> Result<String, Report> {
     }
 }
 
+/// Whether `path` names an activity file we convert (org files are skipped).
+pub fn is_activity_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "xml")
+        && !path.to_string_lossy().ends_with("-org.xml")
+}
 
 pub fn read_and_decode_xml(path: &Path) -> Result<String> {
     let mut xml_contents = String::new();
@@ -85,6 +168,7 @@ pub fn read_and_decode_xml(path: &Path) -> Result<String> {
     Ok(xml_contents)
 }
 
+#[allow(clippy::needless_borrow)]
 pub fn convert_xml_to_json(xml_content: &String) -> Result<Value> {
     let mut config = Config::new_with_defaults();
 
@@ -105,13 +189,101 @@ pub fn convert_xml_to_json(xml_content: &String) -> Result<Value> {
     }
 }
 
+/// The `iati-activity` elements of a converted document, unfiltered. As in
+/// `filter_activities`, a document without `iati-activities` is read as a
+/// single activity.
+pub fn activity_elements(json: &Value) -> Vec<&Value> {
+    let Some(activities) = json.get("iati-activities") else {
+        return vec![json];
+    };
+    match activities.get("iati-activity") {
+        Some(Value::Array(activities)) => activities.iter().collect(),
+        Some(activity) => vec![activity],
+        None => Vec::new(),
+    }
+}
+
+/// Pulls the `iati-activity` elements out of a converted document and runs
+/// each through `filter_activity`. An activity that doesn't fit the typed
+/// model is reported with `path`, the file it came from, and left out.
+pub fn filter_activities(json: &Value, options: &FilterOptions, path: &Path) -> Vec<Value> {
+    let mut unified = Vec::new();
+
+    if let Some(activities) = json.get("iati-activities") {
+        if let Some(activity_array) = activities.get("iati-activity").and_then(Value::as_array) {
+            eprintln!("Number of activities found: {}", activity_array.len());
+
+            for activity in activity_array {
+                unified.extend(filter_checked(activity, options, path));
+            }
+        } else if let Some(activity) = activities.get("iati-activity") {
+            // Handle the single "iati-activity" that's not in array format
+            unified.extend(filter_checked(activity, options, path));
+        } else {
+            eprintln!("\n'iati-activity' key inside 'iati-activities' either doesn't exist or isn't an array.");
+        }
+    } else {
+        unified.extend(filter_checked(json, options, path));
+    }
+
+    unified
+}
+
+/// `filter_activity_with`, reporting an activity that doesn't fit the model.
+fn filter_checked(activity: &Value, options: &FilterOptions, path: &Path) -> Option<Value> {
+    match filter_activity_with(activity, options) {
+        Ok(filtered) => Some(filtered),
+        Err(e) => {
+            let id = activity
+                .get("iati-identifier")
+                .map(Value::to_string)
+                .unwrap_or_else(|| "without an identifier".to_owned());
+            eprintln!("Skipping activity {} in {:?}: {}", id, path, e);
+            None
+        }
+    }
+}
+
+/// Converts the raw bytes of one activity file into filtered records tagged
+/// with `_source`. Returns `None` when the change feed saw the file unchanged.
+pub fn convert_bytes(
+    path: &Path,
+    bytes: Vec<u8>,
+    ctx: &ConvertContext,
+) -> Result<Option<Vec<Value>>> {
+    let source = source_record(
+        ctx.source.as_ref(),
+        path,
+        &bytes,
+        ctx.downloaded_at.as_deref(),
+    );
+    if ctx.skip_file(&source) {
+        return Ok(None);
+    }
+
+    let xml_content = decode_xml(bytes, path)
+        .map_err(|e| eyre::eyre!("Error reading XML file {:?}: {}", path, e))?;
+
+    let json = convert_xml_to_json(&xml_content)
+        .map_err(|e| eyre::eyre!("Error converting XML file {:?} to JSON: {}", path, e))?;
+
+    let mut unified = filter_activities(&json, &ctx.options, path);
+    ctx.converted_file(&source);
+    ctx.retain_changed(&mut unified);
+    attach_source(&mut unified, &source);
+
+    Ok(Some(unified))
+}
+
 // Parallel version of walk_and_convert
+#[allow(clippy::needless_borrow)]
 pub fn walk_and_convert(
     dir: &Path,
     current_count: &mut usize,
     pb: &ProgressBar,
+    ctx: &ConvertContext,
 ) -> eyre::Result<Vec<Value>> {
-    let entries: Vec<_> = fs::read_dir(dir)?
+    let mut entries: Vec<_> = fs::read_dir(dir)?
         .map(|entry_res| {
             entry_res
                 .map_err(eyre::Report::from) // Convert std::io::Error to eyre::Report
@@ -119,6 +291,11 @@ pub fn walk_and_convert(
         })
         .collect::<eyre::Result<Vec<_>>>()?; // Now this will work since we've converted each entry
 
+    // read_dir order depends on the filesystem; results keep the order of `entries`
+    if ctx.deterministic {
+        entries.sort();
+    }
+
     let thread_results: Vec<_> = entries
         .par_iter()
         .map_init(
@@ -130,63 +307,31 @@ pub fn walk_and_convert(
                     .to_string_lossy();
 
                 if path.is_dir() {
-                    walk_and_convert(&path, &mut 0, &pb)
-                } else if path.is_file()
-                    && path.extension().map_or(false, |ext| ext == "xml")
-                    && !path.to_string_lossy().ends_with("-org.xml")
-                {
-                    let xml_content = match read_xml(&path) {
-                        Ok(content) => content,
+                    walk_and_convert(path, &mut 0, pb, ctx)
+                } else if path.is_file() && is_activity_file(path) && ctx.allows(path) {
+                    let bytes = match fs::read(path) {
+                        Ok(bytes) => bytes,
                         Err(e) => {
                             return Err(eyre::eyre!("Error reading XML file {:?}: {}", &path, e));
                         }
                     };
-
-                    let json = match convert_xml_to_json(&xml_content) {
-                        Ok(j) => j,
-                        Err(e) => {
-                            return Err(eyre::eyre!(
-                                "Error converting XML file {:?} to JSON: {}",
-                                &path,
-                                e
-                            ));
+                    let unified = match convert_bytes(path, bytes, ctx)? {
+                        Some(unified) => unified,
+                        None => {
+                            pb.inc(1);
+                            return Ok(Vec::new());
                         }
                     };
 
-                    let mut unified = Vec::new();
-
-                    if let Some(activities) = json.get("iati-activities") {
-                    
-                        if let Some(activity_array) = activities.get("iati-activity").and_then(Value::as_array) {
-
-                            println!("Number of activities found: {}", activity_array.len());
-
-                            for activity in activity_array {
-                                let filtered = filter_activity(activity);
-                                unified.push(filtered);
-                            }
-                        } else if activities.get("iati-activity").is_some() {
-                            // Handle the single "iati-activity" that's not in array format
-                            let filtered = filter_activity(&activities.get("iati-activity").unwrap()); // unwrap is safe here due to the previous check
-                            unified.push(filtered);
-                        } else {
-                            println!("\n'iati-activity' key inside 'iati-activities' either doesn't exist or isn't an array.");
-                        }
-                    } else {
-                        let filtered = filter_activity(&json);
-                        unified.push(filtered);
-                    }
-                    
-
                     *local_count += 1;
 
-                    println!(
+                    eprintln!(
                         "Processing file: '{}'; in folder: {:?}",
                         file_name,
                         path.parent().unwrap_or(&Path::new("Unknown"))
                     );
 
-                    println!("Final output length: {}", unified.len());
+                    eprintln!("Final output length: {}", unified.len());
 
 
                     pb.inc(1);
@@ -210,7 +355,76 @@ pub fn walk_and_convert(
                 results.extend(local_unified);
             }
             Err(e) => {
-                println!("Error processing a file: {}", e);
+                eprintln!("Error processing a file: {}", e);
+            }
+        }
+    }
+
+    Ok(results)
+}
+
+
+/// Converts every activity file inside a zipped dump without extracting it.
+///
+/// Entries are read in parallel, each worker holding its own handle on the
+/// archive. Failures are reported per entry, as in `walk_and_convert`.
+pub fn convert_zip(
+    zip_path: &Path,
+    current_count: &mut usize,
+    pb: &ProgressBar,
+    ctx: &ConvertContext,
+) -> eyre::Result<Vec<Value>> {
+    let mut entries = zip_activity_entries(zip_path, &ctx.filter)?;
+    if ctx.deterministic {
+        sort_entries(&mut entries);
+    }
+
+    let thread_results: Vec<_> = entries
+        .par_iter()
+        .map_init(
+            || File::open(zip_path).map_err(eyre::Report::from).and_then(|f| Ok(ZipArchive::new(f)?)),
+            |archive, (index, name)| {
+                let archive = match archive {
+                    Ok(a) => a,
+                    Err(e) => return Err(eyre::eyre!("Error opening {:?}: {}", zip_path, e)),
+                };
+                let entry_path = Path::new(name);
+
+                let mut bytes = Vec::new();
+                if let Err(e) = archive
+                    .by_index(*index)
+                    .map_err(eyre::Report::from)
+                    .and_then(|mut f| Ok(f.read_to_end(&mut bytes)?))
+                {
+                    return Err(eyre::eyre!("Error reading zip entry {:?}: {}", name, e));
+                }
+
+                let unified = match convert_bytes(entry_path, bytes, ctx)? {
+                    Some(unified) => unified,
+                    None => {
+                        pb.inc(1);
+                        return Ok(Vec::new());
+                    }
+                };
+
+                eprintln!("Processing zip entry: '{}'", name);
+                eprintln!("Final output length: {}", unified.len());
+
+                pb.inc(1);
+                Ok(unified)
+            },
+        )
+        .collect::<Vec<eyre::Result<Vec<Value>>>>();
+
+    let mut results: Vec<Value> = Vec::new();
+    for local_unified_result in thread_results.into_iter() {
+        match local_unified_result {
+            Ok(local_unified) => {
+                *current_count += local_unified.len();
+                results.extend(local_unified);
+            }
+            Err(e) => {
+                eprintln!("Error processing a file: {}", e);
             }
         }
     }
@@ -218,3 +432,35 @@ pub fn walk_and_convert(
     Ok(results)
 }
 
+/// Sorts zip entries by path, the order an extracted dump is walked in
+/// deterministic mode, rather than the order they were added to the archive.
+pub fn sort_entries(entries: &mut [(usize, String)]) {
+    entries.sort_by(|(_, a), (_, b)| Path::new(a).cmp(Path::new(b)));
+}
+
+/// Indices and names of the activity files in a zip that pass `filter`.
+/// Entries with unsafe names are skipped.
+pub fn zip_activity_entries(
+    zip_path: &Path,
+    filter: &PathFilter,
+) -> eyre::Result<Vec<(usize, String)>> {
+    let mut archive = ZipArchive::new(File::open(zip_path)?)?;
+
+    let mut entries = Vec::new();
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i)?;
+        let relative = match file.enclosed_name() {
+            Some(name) if is_safe_relative(name) => name.to_path_buf(),
+            _ => {
+                eprintln!("Skipping unsafe zip entry '{}'", file.name());
+                continue;
+            }
+        };
+        if !file.is_dir() && is_activity_file(&relative) && filter.allows(&data_relative(&relative))
+        {
+            entries.push((i, file.name().to_owned()));
+        }
+    }
+
+    Ok(entries)
+}
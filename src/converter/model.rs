@@ -0,0 +1,1153 @@
+//! Typed view of an `iati-activity` as produced by `convert_xml_to_json`.
+//!
+//! The structs mirror quickxml's JSON shape (attributes and child elements as
+//! keys, a lone child as an object, repeated children as an array) so they
+//! serialise back to the same values they were read from, though modelled
+//! fields come first, in declaration order. Anything not modelled
+//! is kept in `extra`, and elements that don't have the expected structure,
+//! such as v1.0x plain-text titles, are kept verbatim as [`Element::Raw`].
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use std::collections::BTreeMap;
+
+use crate::converter::orgid::OrgId;
+use crate::converter::rollup::Totals;
+use crate::converter::search::SearchEntry;
+
+/// An attribute or text value. With quickxml's `json_types`, numeric-looking
+/// codes such as `14030` arrive as numbers and others such as `C01` as strings.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Code(pub Value);
+
+impl Code {
+    /// The value as text, e.g. `"14030"` for the number `14030`.
+    pub fn as_string(&self) -> Option<String> {
+        match &self.0 {
+            Value::String(s) => Some(s.trim().to_owned()),
+            Value::Number(n) => Some(n.to_string()),
+            Value::Bool(b) => Some(b.to_string()),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match &self.0 {
+            Value::Number(n) => n.as_f64(),
+            Value::String(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+/// A lone child element or a list of repeated ones, kept as it was read.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum OneOrMany<T> {
+    Many(Vec<T>),
+    One(T),
+}
+
+impl<T> OneOrMany<T> {
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        match self {
+            OneOrMany::Many(items) => items.iter(),
+            OneOrMany::One(item) => std::slice::from_ref(item).iter(),
+        }
+    }
+
+    /// The first element, which is the only one when there's just one.
+    pub fn first(&self) -> Option<&T> {
+        self.iter().next()
+    }
+
+    pub fn into_vec(self) -> Vec<T> {
+        match self {
+            OneOrMany::Many(items) => items,
+            OneOrMany::One(item) => vec![item],
+        }
+    }
+}
+
+/// An element read as `T` when it has the expected structure, otherwise kept as is.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Element<T> {
+    Parsed(T),
+    Raw(Value),
+}
+
+impl<T> Element<T> {
+    pub fn parsed(&self) -> Option<&T> {
+        match self {
+            Element::Parsed(t) => Some(t),
+            Element::Raw(_) => None,
+        }
+    }
+}
+
+/// Zero or more occurrences of a child element.
+pub type Elements<T> = Option<OneOrMany<Element<T>>>;
+
+/// Parsed occurrences of a child element, skipping any kept raw.
+pub fn parsed<T>(elements: &Elements<T>) -> impl Iterator<Item = &T> {
+    elements
+        .iter()
+        .flat_map(OneOrMany::iter)
+        .filter_map(Element::parsed)
+}
+
+/// The error for a value that should have been a JSON object.
+fn not_an_object(value: &Value, expected: &str) -> serde_json::Error {
+    let found = match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    };
+    serde::de::Error::custom(format!("expected {}, found {}", expected, found))
+}
+
+/// A `narrative`: plain text, or text tagged with `xml:lang`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Narrative {
+    Tagged {
+        #[serde(rename = "xml:lang", default, skip_serializing_if = "Option::is_none")]
+        lang: Option<String>,
+        #[serde(rename = "#text", default, skip_serializing_if = "Option::is_none")]
+        text: Option<Code>,
+    },
+    Text(Code),
+}
+
+impl Narrative {
+    pub fn text(&self) -> Option<String> {
+        match self {
+            Narrative::Tagged { text, .. } => text.as_ref()?.as_string(),
+            Narrative::Text(code) => code.as_string(),
+        }
+    }
+
+    /// The narrative's own `xml:lang`, if it has one.
+    pub fn lang(&self) -> Option<&str> {
+        match self {
+            Narrative::Tagged { lang, .. } => lang.as_deref(),
+            Narrative::Text(_) => None,
+        }
+    }
+}
+
+pub type Narratives = OneOrMany<Narrative>;
+
+impl OneOrMany<Narrative> {
+    /// Text by language, with narratives that have no `xml:lang` put under
+    /// `default_lang`. Several narratives in one language are joined by a newline.
+    pub fn by_lang(&self, default_lang: &str) -> BTreeMap<String, String> {
+        let mut texts = BTreeMap::<String, String>::new();
+        for narrative in self.iter() {
+            let text = match narrative.text().filter(|t| !t.is_empty()) {
+                Some(text) => text,
+                None => continue,
+            };
+            let lang = narrative
+                .lang()
+                .map(|l| l.trim().to_ascii_lowercase())
+                .filter(|l| !l.is_empty())
+                .unwrap_or_else(|| default_lang.to_owned());
+            texts
+                .entry(lang)
+                .and_modify(|t| {
+                    t.push('\n');
+                    t.push_str(&text);
+                })
+                .or_insert(text);
+        }
+        texts
+    }
+}
+
+/// Text by language of an element's narratives, or of its plain text when it
+/// has none (as in v1.0x), which is taken to be in `default_lang`.
+pub fn texts_by_lang(
+    narratives: Option<&Narratives>,
+    plain: Option<&Value>,
+    default_lang: &str,
+) -> BTreeMap<String, String> {
+    let mut texts = narratives
+        .map(|n| n.by_lang(default_lang))
+        .unwrap_or_default();
+    if texts.is_empty() {
+        if let Some(text) = plain.and_then(|v| Code(v.clone()).as_string()) {
+            if !text.is_empty() {
+                texts.insert(default_lang.to_owned(), text);
+            }
+        }
+    }
+    texts
+}
+
+/// Narratives as written by `filter_activity`: text keyed by language, e.g.
+/// `{"en": "Water", "fr": "Eau"}`, or a single string when converted with a
+/// preferred language.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum LangText {
+    One(String),
+    ByLang(BTreeMap<String, String>),
+}
+
+impl LangText {
+    /// The text in `lang`. A single string is returned whatever `lang` is.
+    pub fn get(&self, lang: &str) -> Option<&str> {
+        match self {
+            LangText::One(text) => Some(text),
+            LangText::ByLang(texts) => texts.get(lang).map(String::as_str),
+        }
+    }
+
+    /// The single string, or the text in the first language alphabetically.
+    pub fn text(&self) -> Option<&str> {
+        match self {
+            LangText::One(text) => Some(text),
+            LangText::ByLang(texts) => texts.values().next().map(String::as_str),
+        }
+    }
+}
+
+/// An element whose content is its `narrative` children, e.g. `title`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TextElement {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub narrative: Option<Narratives>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Element<TextElement> {
+    /// Text by language, see [`texts_by_lang`].
+    pub fn texts(&self, default_lang: &str) -> BTreeMap<String, String> {
+        match self {
+            Element::Parsed(t) => {
+                texts_by_lang(t.narrative.as_ref(), t.extra.get("#text"), default_lang)
+            }
+            Element::Raw(v) => texts_by_lang(None, Some(v), default_lang),
+        }
+    }
+}
+
+/// An element carrying a `code` attribute, e.g. `activity-status`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CodeElement {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<Code>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vocabulary: Option<Code>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub narrative: Option<Narratives>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// An element carrying an `iso-date` attribute, e.g. `transaction-date`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DateElement {
+    #[serde(rename = "iso-date", default, skip_serializing_if = "Option::is_none")]
+    pub iso_date: Option<Code>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Description {
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<Code>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub narrative: Option<Narratives>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// `reporting-org`, `participating-org` or a transaction's provider/receiver.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Organisation {
+    #[serde(
+        rename = "activity-id",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub activity_id: Option<Code>,
+    #[serde(
+        rename = "crs-channel-code",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub crs_channel_code: Option<Code>,
+    #[serde(
+        rename = "provider-activity-id",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub provider_activity_id: Option<Code>,
+    #[serde(
+        rename = "receiver-activity-id",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub receiver_activity_id: Option<Code>,
+    #[serde(rename = "ref", default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<Code>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<Code>,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<Code>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub narrative: Option<Narratives>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Sector {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<Code>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub percentage: Option<Code>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vocabulary: Option<Code>,
+    #[serde(
+        rename = "vocabulary-uri",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub vocabulary_uri: Option<Code>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub narrative: Option<Narratives>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// `recipient-country` or `recipient-region`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Recipient {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<Code>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub percentage: Option<Code>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vocabulary: Option<Code>,
+    #[serde(
+        rename = "vocabulary-uri",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub vocabulary_uri: Option<Code>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub narrative: Option<Narratives>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PolicyMarker {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<Code>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub significance: Option<Code>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vocabulary: Option<Code>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub narrative: Option<Narratives>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RelatedActivity {
+    #[serde(rename = "ref", default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<Code>,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<Code>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A monetary `value` element with its attributes.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Amount {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<Code>,
+    #[serde(
+        rename = "value-date",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub value_date: Option<Code>,
+    #[serde(rename = "#text", default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<Code>,
+    /// Some converters emit the amount under `value` rather than `#text`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Code>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Amount {
+    pub fn amount(&self) -> Option<&Code> {
+        self.text.as_ref().or(self.value.as_ref())
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Transaction {
+    #[serde(
+        rename = "transaction-type",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub transaction_type: Option<Element<CodeElement>>,
+    #[serde(
+        rename = "transaction-date",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub transaction_date: Option<Element<DateElement>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Element<Amount>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<Element<TextElement>>,
+    #[serde(
+        rename = "provider-org",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub provider_org: Option<Element<Organisation>>,
+    #[serde(
+        rename = "receiver-org",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub receiver_org: Option<Element<Organisation>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sector: Elements<Sector>,
+    #[serde(
+        rename = "recipient-country",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub recipient_country: Option<Element<Recipient>>,
+    #[serde(
+        rename = "recipient-region",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub recipient_region: Option<Element<Recipient>>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Transaction {
+    pub fn type_code(&self) -> Option<&Code> {
+        self.transaction_type.as_ref()?.parsed()?.code.as_ref()
+    }
+
+    pub fn date(&self) -> Option<&Code> {
+        self.transaction_date.as_ref()?.parsed()?.iso_date.as_ref()
+    }
+
+    pub fn amount(&self) -> Option<&Amount> {
+        self.value.as_ref()?.parsed()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Budget {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<Code>,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<Code>,
+    #[serde(
+        rename = "period-start",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub period_start: Option<Element<DateElement>>,
+    #[serde(
+        rename = "period-end",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub period_end: Option<Element<DateElement>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Element<Amount>>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Budget {
+    pub fn amount(&self) -> Option<&Amount> {
+        self.value.as_ref()?.parsed()
+    }
+}
+
+/// `location/point`, with `pos` as the `"lat lon"` string publishers write.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Point {
+    #[serde(rename = "srsName", default, skip_serializing_if = "Option::is_none")]
+    pub srs_name: Option<Code>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pos: Option<Code>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Location {
+    #[serde(rename = "ref", default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<Code>,
+    #[serde(
+        rename = "location-reach",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub location_reach: Option<Element<CodeElement>>,
+    #[serde(
+        rename = "location-id",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub location_id: Elements<CodeElement>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<Element<TextElement>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<Element<TextElement>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub administrative: Elements<CodeElement>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub point: Option<Element<Point>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exactness: Option<Element<CodeElement>>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Indicator {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub measure: Option<Code>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<Element<TextElement>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<Element<TextElement>>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A `result`, with its indicators. Baselines and periods are kept in `extra`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ActivityResult {
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<Code>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<Element<TextElement>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<Element<TextElement>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub indicator: Elements<Indicator>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// One `iati-activity`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Activity {
+    #[serde(
+        rename = "default-currency",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub default_currency: Option<Code>,
+    #[serde(
+        rename = "last-updated-datetime",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_updated_datetime: Option<Code>,
+    #[serde(rename = "xml:lang", default, skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+    #[serde(
+        rename = "iati-identifier",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub iati_identifier: Option<Code>,
+    #[serde(
+        rename = "reporting-org",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub reporting_org: Option<Element<Organisation>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<Element<TextElement>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Elements<Description>,
+    #[serde(
+        rename = "participating-org",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub participating_org: Elements<Organisation>,
+    #[serde(
+        rename = "activity-status",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub activity_status: Option<Element<CodeElement>>,
+    #[serde(
+        rename = "recipient-country",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub recipient_country: Elements<Recipient>,
+    #[serde(
+        rename = "recipient-region",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub recipient_region: Elements<Recipient>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Elements<Location>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sector: Elements<Sector>,
+    #[serde(
+        rename = "policy-marker",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub policy_marker: Elements<PolicyMarker>,
+    #[serde(rename = "aid-type", default, skip_serializing_if = "Option::is_none")]
+    pub aid_type: Elements<CodeElement>,
+    #[serde(
+        rename = "default-aid-type",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub default_aid_type: Elements<CodeElement>,
+    #[serde(
+        rename = "finance-type",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub finance_type: Elements<CodeElement>,
+    #[serde(
+        rename = "default-finance-type",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub default_finance_type: Elements<CodeElement>,
+    #[serde(
+        rename = "related-activity",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub related_activity: Elements<RelatedActivity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget: Elements<Budget>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transaction: Elements<Transaction>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Elements<ActivityResult>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Activity {
+    /// Reads an activity from quickxml's JSON. Fails if `value` isn't an
+    /// object or one of the modelled fields has a shape the model can't hold,
+    /// such as a numeric `xml:lang`.
+    pub fn from_value(value: &Value) -> Result<Activity, serde_json::Error> {
+        if !value.is_object() {
+            return Err(not_an_object(value, "an iati-activity object"));
+        }
+        Activity::deserialize(value)
+    }
+
+    pub fn identifier(&self) -> Option<String> {
+        self.iati_identifier.as_ref()?.as_string()
+    }
+
+    pub fn reporting_org(&self) -> Option<&Organisation> {
+        self.reporting_org.as_ref()?.parsed()
+    }
+
+    pub fn sectors(&self) -> impl Iterator<Item = &Sector> {
+        parsed(&self.sector)
+    }
+
+    pub fn participating_orgs(&self) -> impl Iterator<Item = &Organisation> {
+        parsed(&self.participating_org)
+    }
+
+    pub fn recipient_countries(&self) -> impl Iterator<Item = &Recipient> {
+        parsed(&self.recipient_country)
+    }
+
+    pub fn recipient_regions(&self) -> impl Iterator<Item = &Recipient> {
+        parsed(&self.recipient_region)
+    }
+
+    pub fn locations(&self) -> impl Iterator<Item = &Location> {
+        parsed(&self.location)
+    }
+
+    pub fn budgets(&self) -> impl Iterator<Item = &Budget> {
+        parsed(&self.budget)
+    }
+
+    pub fn transactions(&self) -> impl Iterator<Item = &Transaction> {
+        parsed(&self.transaction)
+    }
+
+    pub fn results(&self) -> impl Iterator<Item = &ActivityResult> {
+        parsed(&self.result)
+    }
+}
+
+/// `reporting-org` as written by `filter_activity`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReportingOrg {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Code>,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<Code>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<LangText>,
+}
+
+/// A `participating-org` as written by `filter_activity`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ParticipatingOrg {
+    #[serde(rename = "ref", default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<Code>,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<Code>,
+    /// 2.x role code, also for 1.0x roles written as names.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<Code>,
+    #[serde(rename = "role-name", default, skip_serializing_if = "Option::is_none")]
+    pub role_name: Option<String>,
+    #[serde(rename = "activity-id", default, skip_serializing_if = "Option::is_none")]
+    pub activity_id: Option<Code>,
+    #[serde(rename = "crs-channel-code", default, skip_serializing_if = "Option::is_none")]
+    pub crs_channel_code: Option<Code>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<LangText>,
+    /// `ref` split into registration agency and local ID, when there is a `ref`.
+    #[serde(rename = "org-id", default, skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<OrgId>,
+}
+
+/// A description flattened to its type and text.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FilteredDescription {
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<Code>,
+    pub narrative: LangText,
+}
+
+/// A `recipient-country` or `recipient-region` as written by `filter_activity`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FilteredRecipient {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<Code>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub percentage: Option<Code>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vocabulary: Option<Code>,
+    #[serde(rename = "vocabulary-uri", default, skip_serializing_if = "Option::is_none")]
+    pub vocabulary_uri: Option<Code>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<LangText>,
+}
+
+/// A transaction flattened to its type, date and value.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FilteredTransaction {
+    #[serde(rename = "transaction-type")]
+    pub transaction_type: Code,
+    #[serde(rename = "transaction-date")]
+    pub transaction_date: Code,
+    #[serde(rename = "transaction-value")]
+    pub transaction_value: Code,
+    #[serde(
+        rename = "transaction-currency",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub transaction_currency: Option<Code>,
+}
+
+impl FilteredTransaction {
+    /// The transaction type as an IATI 2.x code, e.g. `3` for a v1 `D`.
+    pub fn type_code(&self) -> Option<u8> {
+        transaction_type_code(&self.transaction_type.as_string()?)
+    }
+}
+
+/// Maps a transaction type, written either as a 2.x number or a 1.0x letter
+/// code, to the 2.x number.
+pub fn transaction_type_code(code: &str) -> Option<u8> {
+    match code.trim().to_ascii_uppercase().as_str() {
+        "IF" => Some(1),
+        "C" => Some(2),
+        "D" => Some(3),
+        "E" => Some(4),
+        "IR" => Some(5),
+        "LR" => Some(6),
+        "R" => Some(7),
+        "QP" => Some(8),
+        "QS" => Some(9),
+        "QR" => Some(10),
+        "CG" => Some(11),
+        other => other.parse().ok(),
+    }
+}
+
+/// Maps an organisation role, written either as a 2.x number or a 1.0x name,
+/// to the 2.x number and its name.
+pub fn org_role(code: &str) -> Option<(u8, &'static str)> {
+    const ROLES: [&str; 4] = ["Funding", "Accountable", "Extending", "Implementing"];
+    let code = code.trim();
+    let number = match code.parse::<u8>() {
+        Ok(number) => number,
+        Err(_) => ROLES.iter().position(|r| r.eq_ignore_ascii_case(code))? as u8 + 1,
+    };
+    let name = ROLES.get(usize::from(number.checked_sub(1)?))?;
+    Some((number, name))
+}
+
+/// A budget flattened to its type, status, period and value.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FilteredBudget {
+    #[serde(rename = "budget-type", default, skip_serializing_if = "Option::is_none")]
+    pub budget_type: Option<Code>,
+    #[serde(rename = "budget-status", default, skip_serializing_if = "Option::is_none")]
+    pub budget_status: Option<Code>,
+    #[serde(rename = "period-start", default, skip_serializing_if = "Option::is_none")]
+    pub period_start: Option<Code>,
+    #[serde(rename = "period-end", default, skip_serializing_if = "Option::is_none")]
+    pub period_end: Option<Code>,
+    #[serde(rename = "budget-value")]
+    pub budget_value: Code,
+    #[serde(rename = "budget-currency", default, skip_serializing_if = "Option::is_none")]
+    pub budget_currency: Option<Code>,
+}
+
+/// A position in WGS 84 degrees.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LatLon {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl LatLon {
+    /// `None` unless both are numbers in range. `0 0` is taken as a
+    /// placeholder, not a real position.
+    pub fn new(lat: f64, lon: f64) -> Option<LatLon> {
+        let valid = (-90.0..=90.0).contains(&lat)
+            && (-180.0..=180.0).contains(&lon)
+            && !(lat == 0.0 && lon == 0.0);
+        valid.then_some(LatLon { lat, lon })
+    }
+
+    /// Reads a `point/pos` string, `"lat lon"`, also accepting a comma between them.
+    pub fn parse(pos: &str) -> Option<LatLon> {
+        let mut parts = pos
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|p| !p.is_empty());
+        let lat = parts.next()?.parse().ok()?;
+        let lon = parts.next()?.parse().ok()?;
+        if parts.next().is_some() {
+            return None;
+        }
+        LatLon::new(lat, lon)
+    }
+}
+
+/// A code with its vocabulary, e.g. a `location-id` or an `administrative` area.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct VocabularyCode {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vocabulary: Option<Code>,
+    /// Administrative level, e.g. 1 for a province.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<Code>,
+    pub code: Code,
+}
+
+/// A location as written by `filter_activity`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FilteredLocation {
+    #[serde(rename = "ref", default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<Code>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<LangText>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<LangText>,
+    /// 1 where the activity happens, 2 where its beneficiaries live.
+    #[serde(rename = "location-reach", default, skip_serializing_if = "Option::is_none")]
+    pub location_reach: Option<Code>,
+    #[serde(rename = "location-id", default, skip_serializing_if = "Vec::is_empty")]
+    pub location_id: Vec<VocabularyCode>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub administrative: Vec<VocabularyCode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub point: Option<LatLon>,
+    /// `pos` as published, when it isn't a valid position.
+    #[serde(rename = "invalid-pos", default, skip_serializing_if = "Option::is_none")]
+    pub invalid_pos: Option<Code>,
+    /// 1 exact, 2 approximate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exactness: Option<Code>,
+    #[serde(rename = "location-class", default, skip_serializing_if = "Option::is_none")]
+    pub location_class: Option<Code>,
+    #[serde(
+        rename = "feature-designation",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub feature_designation: Option<Code>,
+}
+
+/// Sector vocabulary code, `1` (DAC 5-digit) when not stated. The 1.0x
+/// names `DAC` and `DAC-3` are mapped to `1` and `2`.
+pub fn sector_vocabulary(vocabulary: Option<&Code>) -> String {
+    match vocabulary.and_then(Code::as_string).as_deref().map(str::trim) {
+        None | Some("") | Some("DAC") => "1".to_owned(),
+        Some("DAC-3") => "2".to_owned(),
+        Some(other) => other.to_owned(),
+    }
+}
+
+/// A sector as written under `meta.sectors`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SectorSummary {
+    pub code: Code,
+    pub vocabulary: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vocabulary_uri: Option<Code>,
+    /// The DAC 5-digit purpose code, for vocabulary 1 only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dac_code: Option<Code>,
+    /// The DAC 3-digit category: a DAC 5-digit code's first three digits, or
+    /// a vocabulary 2 code.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dac_category: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<LangText>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub percentage: Option<Code>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PolicyMarkerSummary {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<Code>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub significance: Option<Code>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<LangText>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CodeRef {
+    pub code: Code,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RelatedRef {
+    #[serde(rename = "ref", default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<Code>,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<Code>,
+}
+
+/// Fields `filter_activity` derives rather than copies.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Meta {
+    /// Sectors by vocabulary code, highest percentage first.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sectors: Option<BTreeMap<String, Vec<SectorSummary>>>,
+    /// The main DAC sector: the highest-percentage DAC 5-digit sector, or
+    /// failing that the highest DAC 3-digit one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub purpose: Option<SectorSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy_markers: Option<Vec<PolicyMarkerSummary>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aid_type: Option<CodeRef>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finance_type: Option<CodeRef>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub related_activities: Option<Vec<RelatedRef>>,
+    /// Text for full-text indexing, see [`crate::converter::search`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search: Option<Vec<SearchEntry>>,
+    /// Transaction and budget totals in the activity's currency, see
+    /// [`crate::converter::rollup`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub totals: Option<Totals>,
+    /// The same totals converted to the exchange rates' base currency.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub converted_totals: Option<Totals>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Meta {
+    pub fn is_empty(&self) -> bool {
+        *self == Meta::default()
+    }
+}
+
+/// An activity as `convert` returns it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FilteredActivity {
+    #[serde(
+        rename = "default-currency",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub default_currency: Option<Code>,
+    #[serde(
+        rename = "last-updated-datetime",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_updated_datetime: Option<Code>,
+    #[serde(
+        rename = "iati-identifier",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub iati_identifier: Option<Code>,
+    #[serde(
+        rename = "reporting-org",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub reporting_org: Option<ReportingOrg>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<LangText>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<Vec<FilteredDescription>>,
+    #[serde(
+        rename = "participating-org",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub participating_org: Option<Vec<ParticipatingOrg>>,
+    #[serde(
+        rename = "activity-status",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub activity_status: Option<Element<CodeElement>>,
+    #[serde(
+        rename = "recipient-country",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub recipient_country: Option<Vec<FilteredRecipient>>,
+    #[serde(
+        rename = "recipient-region",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub recipient_region: Option<Vec<FilteredRecipient>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<Vec<FilteredLocation>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transaction: Option<Vec<FilteredTransaction>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget: Option<Vec<FilteredBudget>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+    /// Fields added after filtering, such as `_source`.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl FilteredActivity {
+    /// Reads a record written by `convert`. Fails if `value` isn't an object
+    /// or one of its fields doesn't have the shape `convert` writes.
+    pub fn from_value(value: &Value) -> Result<FilteredActivity, serde_json::Error> {
+        if !value.is_object() {
+            return Err(not_an_object(value, "an activity record"));
+        }
+        FilteredActivity::deserialize(value)
+    }
+
+    pub fn identifier(&self) -> Option<String> {
+        self.iati_identifier.as_ref()?.as_string()
+    }
+
+    pub fn participating_orgs(&self) -> std::slice::Iter<'_, ParticipatingOrg> {
+        self.participating_org.as_deref().unwrap_or_default().iter()
+    }
+
+    pub fn recipient_countries(&self) -> std::slice::Iter<'_, FilteredRecipient> {
+        self.recipient_country.as_deref().unwrap_or_default().iter()
+    }
+
+    pub fn recipient_regions(&self) -> std::slice::Iter<'_, FilteredRecipient> {
+        self.recipient_region.as_deref().unwrap_or_default().iter()
+    }
+
+    pub fn locations(&self) -> std::slice::Iter<'_, FilteredLocation> {
+        self.location.as_deref().unwrap_or_default().iter()
+    }
+
+    /// Sectors of every vocabulary from `meta.sectors`.
+    pub fn sectors(&self) -> impl Iterator<Item = &SectorSummary> {
+        let sectors = self.meta.as_ref().and_then(|m| m.sectors.as_ref());
+        sectors.into_iter().flat_map(|s| s.values()).flatten()
+    }
+
+    /// DAC 5-digit sectors (vocabulary 1).
+    pub fn dac_sectors(&self) -> impl Iterator<Item = &SectorSummary> {
+        self.sectors().filter(|s| s.dac_code.is_some())
+    }
+
+    pub fn transactions(&self) -> std::slice::Iter<'_, FilteredTransaction> {
+        self.transaction.as_deref().unwrap_or_default().iter()
+    }
+
+    pub fn budgets(&self) -> std::slice::Iter<'_, FilteredBudget> {
+        self.budget.as_deref().unwrap_or_default().iter()
+    }
+
+    /// Transaction and budget totals in the activity currency (`meta.totals`).
+    pub fn totals(&self) -> Option<&Totals> {
+        self.meta.as_ref()?.totals.as_ref()
+    }
+
+    /// Sum of the values of transactions of one type (2.x code), ignoring currency.
+    pub fn transaction_total(&self, type_code: u8) -> f64 {
+        self.transactions()
+            .filter(|t| t.type_code() == Some(type_code))
+            .filter_map(|t| t.transaction_value.as_f64())
+            .fold(0.0, |total, v| total + v)
+    }
+
+    /// Sum of all budget values, ignoring currency and budget type.
+    pub fn budget_total(&self) -> f64 {
+        self.budgets()
+            .filter_map(|b| b.budget_value.as_f64())
+            .fold(0.0, |total, v| total + v)
+    }
+}
@@ -0,0 +1,279 @@
+//! A registry of the organisations named across a dump: reporting orgs,
+//! participating orgs and transaction providers and receivers, merged by
+//! `ref` so each organisation is one row however many activities name it.
+
+use serde::Serialize;
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::converter::model::{org_role, texts_by_lang, Activity, Code, Element, Organisation};
+use crate::converter::orgid::{is_placeholder, parse_org_ref, AgencyList, RefIssue};
+use crate::converter::pipeline::for_each_activity;
+use crate::converter::settings::Settings;
+
+/// What organisations are merged on: their `ref`, ignoring case, or when
+/// they have none (or a placeholder), their name.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum OrgKey {
+    Ref(String),
+    Name(String),
+}
+
+/// How one activity names an organisation.
+#[derive(Clone, Debug, Default)]
+struct Mention {
+    references: BTreeSet<String>,
+    names: BTreeSet<String>,
+    types: BTreeSet<String>,
+    roles: BTreeSet<&'static str>,
+}
+
+/// An organisation's mentions so far, counted in activities.
+#[derive(Clone, Debug, Default)]
+struct Entry {
+    references: BTreeMap<String, usize>,
+    names: BTreeMap<String, usize>,
+    types: BTreeSet<String>,
+    roles: BTreeMap<&'static str, usize>,
+    activities: usize,
+}
+
+impl Entry {
+    fn add(&mut self, mention: Mention) {
+        for reference in mention.references {
+            *self.references.entry(reference).or_default() += 1;
+        }
+        for name in mention.names {
+            *self.names.entry(name).or_default() += 1;
+        }
+        self.types.extend(mention.types);
+        for role in mention.roles {
+            *self.roles.entry(role).or_default() += 1;
+        }
+        self.activities += 1;
+    }
+
+    fn merge(&mut self, other: Entry) {
+        for (reference, n) in other.references {
+            *self.references.entry(reference).or_default() += n;
+        }
+        for (name, n) in other.names {
+            *self.names.entry(name).or_default() += n;
+        }
+        self.types.extend(other.types);
+        for (role, n) in other.roles {
+            *self.roles.entry(role).or_default() += n;
+        }
+        self.activities += other.activities;
+    }
+}
+
+/// A name an organisation is given and how many activities use it.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct NameVariant {
+    pub name: String,
+    pub activities: usize,
+}
+
+/// One organisation of the registry.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct RegisteredOrg {
+    /// The `ref` as most often written; `None` for organisations merged by name.
+    #[serde(rename = "ref")]
+    pub reference: Option<String>,
+    /// The most used name.
+    pub name: Option<String>,
+    /// Every name given, most used first.
+    pub names: Vec<NameVariant>,
+    /// Organisation type codes given.
+    pub types: Vec<String>,
+    /// Activities naming the organisation in each role: `reporting`, a
+    /// participating role such as `funding` or `implementing` (`participating`
+    /// when it has none), `provider` or `receiver`.
+    pub roles: BTreeMap<String, usize>,
+    /// Activities naming the organisation at all.
+    pub activities: usize,
+    /// Registration agency prefix of `ref`.
+    pub prefix: Option<String>,
+    pub local_id: Option<String>,
+    pub ref_issues: Vec<RefIssue>,
+}
+
+/// Organisations merged across activities. Build one per file or thread and
+/// [`merge`](Registry::merge) them.
+#[derive(Clone, Debug, Default)]
+pub struct Registry {
+    entries: BTreeMap<OrgKey, Entry>,
+}
+
+/// Key, ref and names of an organisation element. Names are every language's text.
+fn org_parts(org: &Element<Organisation>, lang: &str) -> (Option<OrgKey>, Mention) {
+    let mut mention = Mention::default();
+    let (reference, texts) = match org {
+        Element::Parsed(o) => {
+            mention
+                .types
+                .extend(o.kind.as_ref().and_then(Code::as_string));
+            let texts = texts_by_lang(o.narrative.as_ref(), o.extra.get("#text"), lang);
+            (o.reference.as_ref().and_then(Code::as_string), texts)
+        }
+        Element::Raw(v) => (None, texts_by_lang(None, Some(v), lang)),
+    };
+    let names: Vec<String> = texts
+        .into_values()
+        .map(|t| t.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|t| !t.is_empty())
+        .collect();
+
+    let reference = reference
+        .map(|r| r.trim().to_owned())
+        .filter(|r| !is_placeholder(r));
+    let key = match &reference {
+        Some(r) => Some(OrgKey::Ref(r.to_uppercase())),
+        None => names.first().map(|n| OrgKey::Name(n.to_lowercase())),
+    };
+    mention.references.extend(reference);
+    mention.names.extend(names);
+    (key, mention)
+}
+
+/// The most counted key, the first alphabetically on a tie.
+fn most_used(counts: &BTreeMap<String, usize>) -> Option<String> {
+    let (value, _) = counts
+        .iter()
+        .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))?;
+    Some(value.clone())
+}
+
+impl Registry {
+    /// Adds the organisations an activity names. An organisation named
+    /// several times in one activity counts once, with each of its roles.
+    pub fn add_activity(&mut self, activity: &Activity, default_lang: &str) {
+        let lang = activity
+            .lang
+            .as_deref()
+            .map(|l| l.trim().to_ascii_lowercase())
+            .filter(|l| !l.is_empty())
+            .unwrap_or_else(|| default_lang.to_owned());
+
+        let mut mentions: BTreeMap<OrgKey, Mention> = BTreeMap::new();
+        let mut mention = |org: &Element<Organisation>, role: &'static str| {
+            let (key, found) = match org_parts(org, &lang) {
+                (Some(key), found) => (key, found),
+                (None, _) => return,
+            };
+            let mention = mentions.entry(key).or_default();
+            mention.references.extend(found.references);
+            mention.names.extend(found.names);
+            mention.types.extend(found.types);
+            mention.roles.insert(role);
+        };
+
+        if let Some(org) = &activity.reporting_org {
+            mention(org, "reporting");
+        }
+        for org in activity.participating_org.iter().flat_map(|o| o.iter()) {
+            let role = org
+                .parsed()
+                .and_then(|o| o.role.as_ref()?.as_string())
+                .and_then(|r| org_role(&r));
+            let role = match role {
+                Some((1, _)) => "funding",
+                Some((2, _)) => "accountable",
+                Some((3, _)) => "extending",
+                Some((4, _)) => "implementing",
+                _ => "participating",
+            };
+            mention(org, role);
+        }
+        for t in activity.transactions() {
+            if let Some(org) = &t.provider_org {
+                mention(org, "provider");
+            }
+            if let Some(org) = &t.receiver_org {
+                mention(org, "receiver");
+            }
+        }
+
+        for (key, mention) in mentions {
+            self.entries.entry(key).or_default().add(mention);
+        }
+    }
+
+    pub fn merge(mut self, other: Registry) -> Registry {
+        for (key, entry) in other.entries {
+            self.entries.entry(key).or_default().merge(entry);
+        }
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The organisations, most activities first, with refs checked against `agencies`.
+    pub fn organisations(self, agencies: &AgencyList) -> Vec<RegisteredOrg> {
+        let mut orgs: Vec<RegisteredOrg> = self
+            .entries
+            .into_values()
+            .map(|entry| {
+                let reference = most_used(&entry.references);
+                let org_id = reference
+                    .as_deref()
+                    .map(|r| parse_org_ref(r, agencies))
+                    .unwrap_or_default();
+                let mut names: Vec<NameVariant> = entry
+                    .names
+                    .iter()
+                    .map(|(name, n)| NameVariant {
+                        name: name.clone(),
+                        activities: *n,
+                    })
+                    .collect();
+                names.sort_by_key(|n| Reverse(n.activities));
+                RegisteredOrg {
+                    reference,
+                    name: most_used(&entry.names),
+                    names,
+                    types: entry.types.into_iter().collect(),
+                    roles: entry
+                        .roles
+                        .into_iter()
+                        .map(|(role, n)| (role.to_owned(), n))
+                        .collect(),
+                    activities: entry.activities,
+                    prefix: org_id.prefix,
+                    local_id: org_id.local_id,
+                    ref_issues: org_id.issues,
+                }
+            })
+            .collect();
+        // Stable, so ties stay in ref then name order.
+        orgs.sort_by_key(|o| Reverse(o.activities));
+        orgs
+    }
+}
+
+/// The organisation registry of a dump folder, zip, single file or snapshot,
+/// covering the activities `convert` keeps with the same `settings`. Files
+/// that fail to convert are reported and skipped.
+pub fn dump_organisations(path: &str, settings: &Settings) -> eyre::Result<Vec<RegisteredOrg>> {
+    let agencies = settings.org_agencies()?;
+    let default_lang = settings.default_lang.trim().to_ascii_lowercase();
+    let registry = for_each_activity(
+        path,
+        settings,
+        Registry::default,
+        |mut registry, dump, _| {
+            registry.add_activity(&dump.activity, &default_lang);
+            registry
+        },
+        Registry::merge,
+    )?;
+    Ok(registry.organisations(&agencies))
+}
@@ -0,0 +1,229 @@
+//! Organisation identifiers. An org `ref` should be a registration agency
+//! prefix from org-id.guide, such as `GB-COH`, followed by the ID that agency
+//! issued. This splits refs into the two and flags those that can't be right.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// A registration agency from org-id.guide.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Agency {
+    pub code: String,
+    pub name: Option<String>,
+    /// Withdrawn from the list; new identifiers shouldn't use it.
+    pub deprecated: bool,
+}
+
+/// Registration agencies by upper-cased code.
+#[derive(Clone, Debug, Default)]
+pub struct AgencyList {
+    agencies: BTreeMap<String, Agency>,
+}
+
+impl AgencyList {
+    /// Reads a local copy of org-id.guide's `download.json`. The IATI
+    /// OrganisationRegistrationAgency codelist and a bare array of agencies
+    /// are accepted too.
+    pub fn load(path: &Path) -> eyre::Result<AgencyList> {
+        let file =
+            File::open(path).map_err(|e| eyre::eyre!("Error opening {}: {}", path.display(), e))?;
+        let value: Value = serde_json::from_reader(BufReader::new(file))?;
+        AgencyList::from_value(&value)
+            .map_err(|e| eyre::eyre!("Error reading {}: {}", path.display(), e))
+    }
+
+    pub fn from_value(value: &Value) -> eyre::Result<AgencyList> {
+        let entries = match value {
+            Value::Array(entries) => entries,
+            Value::Object(o) => match o.get("lists").or_else(|| o.get("data")) {
+                Some(Value::Array(entries)) => entries,
+                _ => return Err(eyre::eyre!("expected a `lists` or `data` array")),
+            },
+            _ => return Err(eyre::eyre!("expected a list of agencies")),
+        };
+
+        let mut agencies = BTreeMap::new();
+        for entry in entries {
+            let code = match entry.get("code").and_then(Value::as_str) {
+                Some(code) if !code.trim().is_empty() => code.trim().to_owned(),
+                _ => continue,
+            };
+            // org-id.guide names by language, the codelist a plain string.
+            let name = entry.get("name").and_then(|n| match n {
+                Value::String(s) => Some(s.clone()),
+                Value::Object(o) => o.get("en").and_then(Value::as_str).map(str::to_owned),
+                _ => None,
+            });
+            let deprecated = entry.get("deprecated").and_then(Value::as_bool) == Some(true)
+                || entry.get("status").and_then(Value::as_str) == Some("withdrawn");
+            agencies.insert(
+                code.to_ascii_uppercase(),
+                Agency {
+                    code,
+                    name,
+                    deprecated,
+                },
+            );
+        }
+        Ok(AgencyList { agencies })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.agencies.is_empty()
+    }
+
+    pub fn get(&self, code: &str) -> Option<&Agency> {
+        self.agencies.get(&code.to_ascii_uppercase())
+    }
+
+    /// The agency with the longest code `reference` starts with, followed by
+    /// `-` and an ID. `XM-DAC-41114` is `XM-DAC` even if `XM` were listed.
+    fn prefix_of(&self, reference: &str) -> Option<&Agency> {
+        let upper = reference.to_ascii_uppercase();
+        upper
+            .rmatch_indices('-')
+            .filter(|(i, _)| i + 1 < upper.len())
+            .find_map(|(i, _)| self.agencies.get(&upper[..i]))
+    }
+}
+
+/// What is wrong with an org ref.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RefIssue {
+    /// Empty, or a stand-in such as `N/A`, `0` or `unknown`.
+    Placeholder,
+    /// Has whitespace or characters identifiers can't contain (`/ & | ?`).
+    Malformed,
+    /// Doesn't start with an agency prefix, e.g. a bare DAC code `41114`.
+    NoPrefix,
+    /// The prefix isn't on the agency list.
+    UnknownAgency,
+    /// The prefix's agency has been withdrawn.
+    DeprecatedAgency,
+}
+
+impl RefIssue {
+    /// The issue as it's serialised, e.g. `no-prefix`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            RefIssue::Placeholder => "placeholder",
+            RefIssue::Malformed => "malformed",
+            RefIssue::NoPrefix => "no-prefix",
+            RefIssue::UnknownAgency => "unknown-agency",
+            RefIssue::DeprecatedAgency => "deprecated-agency",
+        }
+    }
+}
+
+/// An org ref split into agency prefix and local ID.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct OrgId {
+    /// Registration agency code, as written on the agency list when it's there.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    /// The ID the agency issued.
+    #[serde(rename = "local-id", default, skip_serializing_if = "Option::is_none")]
+    pub local_id: Option<String>,
+    #[serde(
+        rename = "agency-name",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub agency_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub issues: Vec<RefIssue>,
+}
+
+impl OrgId {
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+const PLACEHOLDERS: [&str; 14] = [
+    "na",
+    "n/a",
+    "none",
+    "null",
+    "nil",
+    "unknown",
+    "notapplicable",
+    "notavailable",
+    "notknown",
+    "tbc",
+    "tbd",
+    "other",
+    "ref",
+    "orgref",
+];
+
+/// Whether `reference` stands in for a missing ref rather than being one.
+pub fn is_placeholder(reference: &str) -> bool {
+    let squashed: String = reference
+        .chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, '-' | '_' | '.'))
+        .collect::<String>()
+        .to_ascii_lowercase();
+    squashed.is_empty()
+        || PLACEHOLDERS.contains(&squashed.as_str())
+        || squashed.chars().all(|c| c == '0')
+        || squashed.chars().all(|c| c == 'x')
+}
+
+/// Splits a ref shaped like an org-id, `XX-AGENCY-id`, without checking the agency.
+fn split_prefix_shape(reference: &str) -> Option<(&str, &str)> {
+    let mut parts = reference.splitn(3, '-');
+    let country = parts.next()?;
+    let agency = parts.next()?;
+    let local = parts.next().filter(|l| !l.is_empty())?;
+    let shaped = country.len() == 2
+        && country.chars().all(|c| c.is_ascii_alphabetic())
+        && !agency.is_empty()
+        && agency.chars().all(|c| c.is_ascii_alphanumeric());
+    shaped.then(|| (&reference[..country.len() + agency.len() + 1], local))
+}
+
+/// Splits `reference` into agency prefix and local ID and lists what's
+/// wrong with it. With an empty `agencies` list, anything shaped like
+/// `XX-AGENCY-id` counts as prefixed; with one, the prefix must be listed.
+pub fn parse_org_ref(reference: &str, agencies: &AgencyList) -> OrgId {
+    let reference = reference.trim();
+    if is_placeholder(reference) {
+        return OrgId {
+            issues: vec![RefIssue::Placeholder],
+            ..Default::default()
+        };
+    }
+
+    let mut id = OrgId::default();
+    if reference
+        .chars()
+        .any(|c| c.is_whitespace() || matches!(c, '/' | '&' | '|' | '?'))
+    {
+        id.issues.push(RefIssue::Malformed);
+    }
+
+    if let Some(agency) = agencies.prefix_of(reference) {
+        id.prefix = Some(agency.code.clone());
+        id.local_id = Some(reference[agency.code.len() + 1..].to_owned());
+        id.agency_name = agency.name.clone();
+        if agency.deprecated {
+            id.issues.push(RefIssue::DeprecatedAgency);
+        }
+    } else if let Some((prefix, local_id)) = split_prefix_shape(reference) {
+        id.prefix = Some(prefix.to_owned());
+        id.local_id = Some(local_id.to_owned());
+        if !agencies.is_empty() {
+            id.issues.push(RefIssue::UnknownAgency);
+        }
+    } else {
+        id.issues.push(RefIssue::NoPrefix);
+    }
+    id
+}
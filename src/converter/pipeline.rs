@@ -0,0 +1,474 @@
+use indicatif::ProgressBar;
+use rayon::prelude::*;
+use serde::Serialize;
+use serde_json::Value;
+use zip::ZipArchive;
+
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use crate::converter::feed::ChangeFeed;
+use crate::converter::filter::{publisher_of, PathFilter};
+use crate::converter::layout::{detect_source, detect_zip_source};
+use crate::converter::model::{Activity, FilteredActivity};
+use crate::converter::provenance::{modified_at, snapshot_downloaded_at};
+use crate::converter::settings::Settings;
+use crate::converter::utils::{
+    count_xml_files, download_zip, filter_typed, process_root, supplement_root_files,
+    FilterOptions,
+};
+use crate::converter::{
+    activity_elements, convert_xml_to_json, convert_zip, decode_xml, filter_activities,
+    is_activity_file,
+    read_and_decode_xml, sort_entries, walk_and_convert, zip_activity_entries, ConvertContext,
+    Converter,
+};
+
+/// Downloads today's dump (or reuses a snapshot, per `settings`) and returns
+/// the path to convert.
+pub fn download_source(settings: &Settings) -> eyre::Result<String> {
+    eprintln!(
+        "Beginning download of source data. This is a large file, timeout set to 15 minutes."
+    );
+    let zip_path = download_zip(settings)?;
+    prepare_zip(&zip_path, settings)
+}
+
+/// Extracts a zipped dump, or passes it through when streaming from the zip.
+pub fn prepare_zip(zip_path: &Path, settings: &Settings) -> eyre::Result<String> {
+    if settings.stream_zip {
+        Ok(zip_path.to_string_lossy().into_owned())
+    } else {
+        process_root(zip_path, settings)
+    }
+}
+
+pub fn to_output(value: &Value, pretty: Option<bool>) -> eyre::Result<String> {
+    if pretty.unwrap_or(false) {
+        Ok(serde_json::to_string_pretty(value)?)
+    } else {
+        Ok(serde_json::to_string(value)?)
+    }
+}
+
+/// Runs the conversion pipeline over a file, folder or zip. A folder's
+/// errors.txt files are back-filled first, as `convert` has always done. The
+/// change feed comes back with the records; commit it once they have been
+/// written out.
+pub fn convert_to_value(
+    extraction_path: &str,
+    settings: &Settings,
+    feed: Option<ChangeFeed>,
+) -> eyre::Result<(Value, Option<ChangeFeed>)> {
+    let mut ctx = ConvertContext {
+        filter: settings.path_filter()?,
+        feed,
+        options: settings.filter_options()?,
+        deterministic: settings.deterministic,
+        supplement: true,
+        ..Default::default()
+    };
+    let value = run_conversion(extraction_path, settings, &mut ctx)?;
+    Ok((value, ctx.feed))
+}
+
+/// Filtered records of a file, folder or zip together with the change feed.
+/// A single activity file gives the records `Converter::convert_file` does,
+/// not the raw document `convert_to_value` returns for it.
+pub fn convert_records(
+    path: &str,
+    settings: &Settings,
+    feed: Option<ChangeFeed>,
+) -> eyre::Result<(Vec<Value>, Option<ChangeFeed>)> {
+    let p = Path::new(path);
+    if feed.is_none() && p.is_file() && is_activity_file(p) {
+        let converter = Converter::builder().settings(settings.clone()).build()?;
+        return Ok((converter.convert_file(p)?, None));
+    }
+
+    match convert_to_value(path, settings, feed)? {
+        (Value::Array(records), feed) => Ok((records, feed)),
+        _ => Err(eyre::eyre!(
+            "{} did not produce an array of activities.",
+            path
+        )),
+    }
+}
+
+pub fn run_conversion(
+    extraction_path: &str,
+    settings: &Settings,
+    ctx: &mut ConvertContext,
+) -> eyre::Result<Value> {
+    let p = Path::new(extraction_path);
+
+    eprintln!("root path is {}", &p.display());
+
+    let mut current_count = 0;
+
+    if p.is_file() && p.extension().is_some_and(|ext| ext == "zip") {
+        // Read activity files straight out of the archive
+        let total_files = zip_activity_entries(p, &ctx.filter)?.len();
+        eprintln!("total size is: {}", total_files);
+
+        ctx.downloaded_at = modified_at(p);
+        ctx.source = Some(detect_zip_source(p)?);
+
+        let pb = ProgressBar::new(total_files as u64);
+        let unified_json = convert_zip(p, &mut current_count, &pb, ctx)?;
+
+        return Ok(Value::Array(unified_json));
+    }
+
+    if p.is_dir() {
+        let source = detect_source(p)?;
+        let data_dir = source.data_dir();
+        eprintln!(
+            "Detected '{}' layout, reading {}",
+            source.layout.name(),
+            data_dir.display()
+        );
+
+        if ctx.supplement {
+            let health = supplement_root_files(&data_dir, settings)?;
+            eprintln!("Dump health: {}", health.summary());
+        }
+
+        let total_files = count_xml_files(&data_dir)?;
+        eprintln!("total size is: {}", total_files);
+
+        // Dumps extracted from a snapshot carry the snapshot's download time
+        ctx.downloaded_at = snapshot_downloaded_at(&source.root, settings);
+        ctx.source = Some(source);
+
+        let pb = ProgressBar::new(total_files as u64);
+        let unified_json = walk_and_convert(&data_dir, &mut current_count, &pb, ctx)?;
+
+        Ok(Value::Array(unified_json))
+    } else if p.is_file() && is_activity_file(p) {
+        // A single file comes back as its raw document, not filtered records
+        if ctx.feed.is_some() {
+            return Err(eyre::eyre!(
+                "A change feed (since or manifest) needs a folder or zip, not a single file."
+            ));
+        }
+        eprintln!("total size is: 1");
+
+        let xml_content = read_and_decode_xml(p)?;
+
+        convert_xml_to_json(&xml_content)
+    } else {
+        Err(eyre::eyre!("Invalid path provided. Ensure it's either an XML file, a zip archive or a directory containing XML files."))
+    }
+}
+
+/// Activities from a converted JSON file, a snapshot, or a fresh conversion.
+pub fn load_records(source: &str, settings: &Settings) -> eyre::Result<Vec<Value>> {
+    let p = Path::new(source);
+
+    if p.is_file() && p.extension().is_some_and(|ext| ext == "json") {
+        match serde_json::from_reader(BufReader::new(File::open(p)?))? {
+            Value::Array(records) => Ok(records),
+            _ => Err(eyre::eyre!(
+                "{} did not produce an array of activities.",
+                source
+            )),
+        }
+    } else if let Some(snapshot) = settings.snapshot_store().get(source) {
+        let extraction_path = prepare_zip(Path::new(&snapshot.zip_path), settings)?;
+        Ok(convert_records(&extraction_path, settings, None)?.0)
+    } else {
+        Ok(convert_records(source, settings, None)?.0)
+    }
+}
+
+/// Outcome of reading and converting one activity file during validation.
+#[derive(Clone, Debug, Serialize)]
+pub struct FileCheck {
+    pub path: String,
+    pub activities: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Activity files under the data folder `data_dir` that pass `filter`, in path order.
+pub fn activity_files(data_dir: &Path, filter: &PathFilter) -> eyre::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    collect_activity_files(data_dir, data_dir, filter, &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+fn collect_activity_files(
+    data_dir: &Path,
+    dir: &Path,
+    filter: &PathFilter,
+    files: &mut Vec<PathBuf>,
+) -> eyre::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_activity_files(data_dir, &path, filter, files)?;
+        } else if is_activity_file(&path) && filter.allows(path.strip_prefix(data_dir)?) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn check_bytes(path: &str, bytes: Vec<u8>) -> FileCheck {
+    let result = decode_xml(bytes, Path::new(path))
+        .and_then(|xml| convert_xml_to_json(&xml))
+        .map(|json| filter_activities(&json, &FilterOptions::default(), Path::new(path)).len());
+
+    match result {
+        Ok(activities) => FileCheck {
+            path: path.to_owned(),
+            activities,
+            error: None,
+        },
+        Err(e) => FileCheck {
+            path: path.to_owned(),
+            activities: 0,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+/// Reads and converts every activity file in a file, folder or zip without
+/// keeping the output, reporting per-file failures.
+pub fn validate_path(path: &str, settings: &Settings) -> eyre::Result<Vec<FileCheck>> {
+    let p = Path::new(path);
+    let filter = settings.path_filter()?;
+
+    if p.is_file() && p.extension().is_some_and(|ext| ext == "zip") {
+        let mut entries = zip_activity_entries(p, &filter)?;
+        if settings.deterministic {
+            sort_entries(&mut entries);
+        }
+        let checks = entries
+            .par_iter()
+            .map_init(
+                || {
+                    File::open(p)
+                        .map_err(eyre::Report::from)
+                        .and_then(|f| Ok(ZipArchive::new(f)?))
+                },
+                |archive, (index, name)| {
+                    let mut bytes = Vec::new();
+                    let read = match archive {
+                        Ok(a) => a
+                            .by_index(*index)
+                            .map_err(eyre::Report::from)
+                            .and_then(|mut f| Ok(f.read_to_end(&mut bytes)?)),
+                        Err(e) => Err(eyre::eyre!("Error opening {:?}: {}", p, e)),
+                    };
+                    match read {
+                        Ok(_) => check_bytes(name, bytes),
+                        Err(e) => FileCheck {
+                            path: name.clone(),
+                            activities: 0,
+                            error: Some(e.to_string()),
+                        },
+                    }
+                },
+            )
+            .collect();
+        return Ok(checks);
+    }
+
+    let files = if p.is_dir() {
+        activity_files(&detect_source(p)?.data_dir(), &filter)?
+    } else if p.is_file() && is_activity_file(p) {
+        vec![p.to_path_buf()]
+    } else {
+        return Err(eyre::eyre!("Invalid path provided. Ensure it's either an XML file, a zip archive or a directory containing XML files."));
+    };
+
+    Ok(files
+        .par_iter()
+        .map(|file| {
+            let name = file.to_string_lossy();
+            match fs::read(file) {
+                Ok(bytes) => check_bytes(&name, bytes),
+                Err(e) => FileCheck {
+                    path: name.into_owned(),
+                    activities: 0,
+                    error: Some(e.to_string()),
+                },
+            }
+        })
+        .collect())
+}
+
+/// One activity file read while folding over a dump.
+pub struct DumpFile<'a> {
+    /// Path on disk, or the entry name inside a zip.
+    pub path: &'a Path,
+    pub publisher: Option<String>,
+    pub bytes: eyre::Result<Vec<u8>>,
+}
+
+/// One activity read while folding over a dump: as published, and as the
+/// record `convert` writes for it.
+pub struct DumpActivity {
+    pub activity: Activity,
+    pub filtered: FilteredActivity,
+}
+
+/// The activities `convert` keeps from one file's `bytes`, filtered with
+/// `options`. Activities that don't fit the model are reported and left out,
+/// as `filter_activities` does; a file that fails to convert is an error.
+pub fn file_activities(
+    path: &Path,
+    bytes: eyre::Result<Vec<u8>>,
+    options: &FilterOptions,
+) -> eyre::Result<Vec<DumpActivity>> {
+    let json = bytes
+        .and_then(|b| decode_xml(b, path))
+        .and_then(|xml| convert_xml_to_json(&xml))?;
+
+    let mut activities = Vec::new();
+    for element in activity_elements(&json) {
+        match Activity::from_value(element) {
+            Ok(activity) => {
+                let filtered = filter_typed(&activity, options);
+                activities.push(DumpActivity { activity, filtered });
+            }
+            Err(e) => eprintln!("Skipping an activity in {}: {}", path.display(), e),
+        }
+    }
+    Ok(activities)
+}
+
+/// Folds over every activity `convert` keeps from a dump folder, zip, single
+/// XML file or snapshot, filtered with the same `settings`. `add` gets each
+/// activity with its file's publisher. Files that fail to convert are
+/// reported and skipped.
+pub fn for_each_activity<T, I, A, M>(
+    path: &str,
+    settings: &Settings,
+    init: I,
+    add: A,
+    merge: M,
+) -> eyre::Result<T>
+where
+    T: Send,
+    I: Fn() -> T + Sync + Send,
+    A: Fn(T, DumpActivity, Option<&str>) -> T + Sync + Send,
+    M: Fn(T, T) -> T + Sync + Send,
+{
+    let options = settings.filter_options()?;
+    fold_dump(
+        path,
+        settings,
+        init,
+        |acc, file| match file_activities(file.path, file.bytes, &options) {
+            Ok(activities) => activities
+                .into_iter()
+                .fold(acc, |acc, activity| add(acc, activity, file.publisher.as_deref())),
+            Err(e) => {
+                eprintln!("Skipping {}: {}", file.path.display(), e);
+                acc
+            }
+        },
+        merge,
+    )
+}
+
+/// Folds over every activity file of a dump folder, zip, single XML file or
+/// snapshot in parallel, without keeping the files. `merge` combines results
+/// in file order, which is path order for folders and, in deterministic mode,
+/// for zips too. Each file's publisher comes from the detected layout, read
+/// from the archive for zips.
+pub fn fold_dump<T, I, A, M>(
+    path: &str,
+    settings: &Settings,
+    init: I,
+    add: A,
+    merge: M,
+) -> eyre::Result<T>
+where
+    T: Send,
+    I: Fn() -> T + Sync + Send,
+    A: Fn(T, DumpFile) -> T + Sync + Send,
+    M: Fn(T, T) -> T + Sync + Send,
+{
+    let p = Path::new(path);
+    let filter = settings.path_filter()?;
+
+    let zip_path = if p.is_file() && p.extension().is_some_and(|ext| ext == "zip") {
+        Some(p.to_path_buf())
+    } else if p.is_dir() || (p.is_file() && is_activity_file(p)) {
+        None
+    } else if let Some(snapshot) = settings.snapshot_store().get(path) {
+        Some(PathBuf::from(snapshot.zip_path))
+    } else {
+        return Err(eyre::eyre!("Invalid path provided. Ensure it's either an XML file, a zip archive, a directory containing XML files or a snapshot name."));
+    };
+
+    if let Some(zip_path) = zip_path {
+        let source = detect_zip_source(&zip_path)?;
+        let mut entries = zip_activity_entries(&zip_path, &filter)?;
+        if settings.deterministic {
+            sort_entries(&mut entries);
+        }
+        return Ok(entries
+            .par_iter()
+            .fold(
+                || {
+                    let archive = File::open(&zip_path)
+                        .map_err(eyre::Report::from)
+                        .and_then(|f| Ok(ZipArchive::new(f)?));
+                    (archive, init())
+                },
+                |(mut archive, acc), (index, name)| {
+                    let bytes = match &mut archive {
+                        Ok(a) => {
+                            a.by_index(*index)
+                                .map_err(eyre::Report::from)
+                                .and_then(|mut f| {
+                                    let mut bytes = Vec::new();
+                                    f.read_to_end(&mut bytes)?;
+                                    Ok(bytes)
+                                })
+                        }
+                        Err(e) => Err(eyre::eyre!("Error opening {:?}: {}", zip_path, e)),
+                    };
+                    let entry = Path::new(name);
+                    let file = DumpFile {
+                        path: entry,
+                        publisher: source.dataset_info(entry).publisher,
+                        bytes,
+                    };
+                    let acc = add(acc, file);
+                    (archive, acc)
+                },
+            )
+            .map(|(_, acc)| acc)
+            .reduce(&init, &merge));
+    }
+
+    let (files, source) = if p.is_dir() {
+        let source = detect_source(p)?;
+        (activity_files(&source.data_dir(), &filter)?, Some(source))
+    } else {
+        (vec![p.to_path_buf()], None)
+    };
+    Ok(files
+        .par_iter()
+        .fold(&init, |acc, file| {
+            let publisher = match &source {
+                Some(source) => source.dataset_info(file).publisher,
+                None => publisher_of(file),
+            };
+            let file = DumpFile {
+                path: file,
+                publisher,
+                bytes: fs::read(file).map_err(Into::into),
+            };
+            add(acc, file)
+        })
+        .reduce(&init, &merge))
+}
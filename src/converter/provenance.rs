@@ -0,0 +1,115 @@
+use chrono::{DateTime, Utc};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::converter::filter::{data_relative, publisher_of};
+use crate::converter::layout::{DatasetInfo, DetectedSource};
+use crate::converter::settings::Settings;
+
+/// RFC 3339 timestamp of a file's last modification, if the platform has one.
+pub fn modified_at(path: &Path) -> Option<String> {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    Some(format_time(modified))
+}
+
+pub fn format_time(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).to_rfc3339()
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Dataset source URL from registry metadata (CKAN package or flat export).
+fn source_url(metadata: &Value) -> Option<Value> {
+    metadata
+        .get("resources")
+        .and_then(|r| r.get(0))
+        .and_then(|r| r.get("url"))
+        .or_else(|| metadata.get("source_url"))
+        .or_else(|| metadata.get("url"))
+        .cloned()
+}
+
+/// Download time of the snapshot a dump was extracted from, when `root` lies
+/// in `<cache_dir>/<snapshot name>`.
+pub fn snapshot_downloaded_at(root: &Path, settings: &Settings) -> Option<String> {
+    let cache = settings.cache_path();
+    let name = root.strip_prefix(&cache).ok()?.components().next()?;
+    let name = name.as_os_str().to_string_lossy();
+    settings.snapshot_store().get(&name)?.downloaded_at
+}
+
+/// Builds the `_source` object attached to every record read from one file.
+///
+/// `path` is the file on disk, or the entry name when reading from a zip.
+/// Either way the recorded `path` is relative to the dump's data folder.
+/// `downloaded_at` is the time the dump was fetched; files that weren't part
+/// of a download fall back to their own modification time.
+pub fn source_record(
+    source: Option<&DetectedSource>,
+    path: &Path,
+    bytes: &[u8],
+    downloaded_at: Option<&str>,
+) -> Value {
+    let info = match source {
+        Some(s) => s.dataset_info(path),
+        None => DatasetInfo {
+            publisher: publisher_of(path),
+            dataset: path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+            metadata: None,
+        },
+    };
+
+    let relative = data_relative(
+        source
+            .and_then(|s| path.strip_prefix(&s.root).ok())
+            .unwrap_or(path),
+    );
+
+    let downloaded_at = downloaded_at
+        .map(str::to_owned)
+        .or_else(|| modified_at(path));
+
+    let mut out = Map::new();
+    out.insert("publisher".into(), json!(info.publisher));
+    out.insert("dataset".into(), json!(info.dataset));
+    out.insert(
+        "file_name".into(),
+        json!(path.file_name().map(|n| n.to_string_lossy())),
+    );
+    out.insert(
+        "path".into(),
+        json!(relative.to_string_lossy().replace('\\', "/")),
+    );
+    out.insert("sha256".into(), json!(sha256_hex(bytes)));
+    out.insert("downloaded_at".into(), json!(downloaded_at));
+    if let Some(metadata) = info.metadata {
+        if let Some(url) = source_url(&metadata) {
+            out.insert("url".into(), url);
+        }
+        out.insert("registry".into(), metadata);
+    }
+
+    Value::Object(out)
+}
+
+/// Adds `_source` to each filtered record.
+pub fn attach_source(records: &mut [Value], source: &Value) {
+    for record in records.iter_mut() {
+        if let Value::Object(o) = record {
+            o.insert("_source".into(), source.clone());
+        }
+    }
+}
@@ -0,0 +1,254 @@
+//! Financial totals derived from an activity's flattened transactions and
+//! budgets, so every consumer adds them up the same way.
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::converter::model::{
+    Budget, Code, DateElement, Element, FilteredBudget, FilteredTransaction,
+};
+
+/// A sum overall and by year.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Rollup {
+    pub total: f64,
+    /// By the year of the transaction date or budget period start; `unknown` when undated.
+    pub by_year: BTreeMap<String, f64>,
+}
+
+impl Rollup {
+    fn add(&mut self, year: String, value: f64) {
+        self.total += value;
+        *self.by_year.entry(year).or_default() += value;
+    }
+
+    /// Rounds to cents, so float noise from adding and converting doesn't show.
+    fn round(&mut self) {
+        let cents = |v: f64| (v * 100.0).round() / 100.0;
+        self.total = cents(self.total);
+        self.by_year.values_mut().for_each(|v| *v = cents(*v));
+    }
+}
+
+/// An activity's transaction and budget totals in one currency.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Totals {
+    pub currency: String,
+    /// Outgoing commitments (transaction type 2).
+    pub commitment: Rollup,
+    /// Disbursements (type 3).
+    pub disbursement: Rollup,
+    /// Expenditure (type 4).
+    pub expenditure: Rollup,
+    /// Incoming funds (type 1).
+    pub incoming_funds: Rollup,
+    /// Budgets, counting revised budgets instead of the originals they replace.
+    pub budget: Rollup,
+    /// Values left out because they are in another currency without an exchange rate.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub unconverted: usize,
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+/// Flat exchange rates for converting values between currencies.
+#[derive(Clone, Debug, Default)]
+pub struct ExchangeRates {
+    /// The currency rates are quoted in and converted totals are reported in.
+    pub base: Option<String>,
+    /// Value of one unit of each currency in `base`, e.g. `{"EUR": 1.08}` for USD.
+    pub rates: BTreeMap<String, f64>,
+}
+
+impl ExchangeRates {
+    fn rate(&self, currency: &str) -> Option<f64> {
+        if self.base.as_deref() == Some(currency) {
+            return Some(1.0);
+        }
+        self.rates.get(currency).copied()
+    }
+
+    /// `value` in `from` expressed in `to`, or `None` without rates for both.
+    pub fn convert(&self, value: f64, from: &str, to: &str) -> Option<f64> {
+        if from == to {
+            return Some(value);
+        }
+        Some(value * self.rate(from)? / self.rate(to)?)
+    }
+
+    /// Rejects rates that are zero, negative or not numbers.
+    pub fn check(&self) -> eyre::Result<()> {
+        for (currency, rate) in &self.rates {
+            if !rate.is_finite() || *rate <= 0.0 {
+                return Err(eyre::eyre!(
+                    "Exchange rate for {} must be a positive number, got {}.",
+                    currency,
+                    rate
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn currency_code(code: &Option<Code>) -> Option<String> {
+    code.as_ref()?
+        .as_string()
+        .map(|c| c.to_ascii_uppercase())
+        .filter(|c| !c.is_empty())
+}
+
+fn year(date: Option<String>) -> String {
+    date.as_deref()
+        .and_then(|d| d.get(..4))
+        .filter(|y| y.chars().all(|c| c.is_ascii_digit()))
+        .map(str::to_owned)
+        .unwrap_or_else(|| "unknown".to_owned())
+}
+
+/// Flattens a budget to its type, status, period and value, as written to a
+/// record's `budget` array, which the budget totals are added up from.
+/// `None` when it has no value.
+pub fn flatten_budget(b: &Budget) -> Option<FilteredBudget> {
+    let amount = b.amount()?;
+    let date = |d: &Option<Element<DateElement>>| d.as_ref()?.parsed()?.iso_date.clone();
+    Some(FilteredBudget {
+        budget_type: b.kind.clone(),
+        budget_status: b.status.clone(),
+        period_start: date(&b.period_start),
+        period_end: date(&b.period_end),
+        budget_value: amount.amount()?.clone(),
+        budget_currency: amount.currency.clone(),
+    })
+}
+
+/// Budgets to add up: for each period, the revised budgets (type 2) when
+/// there are any, otherwise the originals.
+fn budgets_to_sum(budgets: &[FilteredBudget]) -> Vec<&FilteredBudget> {
+    let period = |b: &FilteredBudget| {
+        (
+            b.period_start.as_ref().and_then(Code::as_string),
+            b.period_end.as_ref().and_then(Code::as_string),
+        )
+    };
+    let is_revised = |b: &FilteredBudget| {
+        b.budget_type.as_ref().and_then(Code::as_string).as_deref() == Some("2")
+    };
+    let revised: BTreeSet<_> = budgets
+        .iter()
+        .filter(|b| is_revised(b))
+        .map(period)
+        .collect();
+    budgets
+        .iter()
+        .filter(|b| is_revised(b) || !revised.contains(&period(b)))
+        .collect()
+}
+
+/// Totals in `currency`. Values without a currency are taken to be in
+/// `default_currency`; values in other currencies are converted with `rates`
+/// or counted in `unconverted`.
+pub fn totals_in(
+    currency: &str,
+    default_currency: Option<&str>,
+    transactions: &[FilteredTransaction],
+    budgets: &[FilteredBudget],
+    rates: &ExchangeRates,
+) -> Totals {
+    let mut totals = Totals {
+        currency: currency.to_owned(),
+        ..Default::default()
+    };
+
+    let convert = |value: Option<f64>, own: &Option<Code>, unconverted: &mut usize| {
+        let value = value?;
+        let from = currency_code(own).or_else(|| default_currency.map(str::to_owned));
+        let converted = match from {
+            Some(from) => rates.convert(value, &from, currency),
+            None => Some(value),
+        };
+        if converted.is_none() {
+            *unconverted += 1;
+        }
+        converted
+    };
+
+    for t in transactions {
+        let rollup = match t.type_code() {
+            Some(1) => &mut totals.incoming_funds,
+            Some(2) => &mut totals.commitment,
+            Some(3) => &mut totals.disbursement,
+            Some(4) => &mut totals.expenditure,
+            _ => continue,
+        };
+        let value = convert(
+            t.transaction_value.as_f64(),
+            &t.transaction_currency,
+            &mut totals.unconverted,
+        );
+        if let Some(value) = value {
+            rollup.add(year(t.transaction_date.as_string()), value);
+        }
+    }
+    for b in budgets_to_sum(budgets) {
+        let value = convert(
+            b.budget_value.as_f64(),
+            &b.budget_currency,
+            &mut totals.unconverted,
+        );
+        if let Some(value) = value {
+            let start = b.period_start.as_ref().and_then(Code::as_string);
+            totals.budget.add(year(start), value);
+        }
+    }
+
+    for rollup in [
+        &mut totals.commitment,
+        &mut totals.disbursement,
+        &mut totals.expenditure,
+        &mut totals.incoming_funds,
+        &mut totals.budget,
+    ] {
+        rollup.round();
+    }
+    totals
+}
+
+/// The activity's totals in its own currency: `default-currency`, or failing
+/// that the currency of its first transaction or budget that has one.
+/// `None` when it has neither transactions nor budgets.
+pub fn activity_totals(
+    default_currency: &Option<Code>,
+    transactions: &[FilteredTransaction],
+    budgets: &[FilteredBudget],
+    rates: &ExchangeRates,
+) -> Option<Totals> {
+    if transactions.is_empty() && budgets.is_empty() {
+        return None;
+    }
+    let default_currency = currency_code(default_currency);
+    let currency = default_currency
+        .clone()
+        .or_else(|| {
+            transactions
+                .iter()
+                .find_map(|t| currency_code(&t.transaction_currency))
+        })
+        .or_else(|| {
+            budgets
+                .iter()
+                .find_map(|b| currency_code(&b.budget_currency))
+        })
+        .unwrap_or_else(|| "unknown".to_owned());
+
+    Some(totals_in(
+        &currency,
+        default_currency.as_deref(),
+        transactions,
+        budgets,
+        rates,
+    ))
+}
@@ -0,0 +1,158 @@
+//! Search documents: an activity's text split into weighted, language-tagged
+//! fields, ready to feed a full-text engine.
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::{BTreeMap, HashSet};
+
+use crate::converter::model::{texts_by_lang, Activity, Code, Element, Organisation};
+
+/// One field of an activity's text in one language.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SearchEntry {
+    pub field: String,
+    pub lang: String,
+    pub weight: f64,
+    pub text: String,
+}
+
+/// Every search field with its default weight, in the order entries are written.
+pub const DEFAULT_WEIGHTS: &[(&str, f64)] = &[
+    ("title", 3.0),
+    ("description-general", 2.0),
+    ("description-objectives", 1.5),
+    ("description-target-groups", 1.0),
+    ("description-other", 1.0),
+    ("result", 1.0),
+    ("indicator", 1.0),
+    ("sector", 1.0),
+    ("reporting-org", 1.5),
+    ("participating-org", 1.0),
+];
+
+pub fn default_weights() -> BTreeMap<String, f64> {
+    DEFAULT_WEIGHTS
+        .iter()
+        .map(|(field, weight)| (field.to_string(), *weight))
+        .collect()
+}
+
+/// Rejects unknown field names and negative or non-finite weights.
+pub fn check_weights(weights: &BTreeMap<String, f64>) -> eyre::Result<()> {
+    for (field, weight) in weights {
+        if !DEFAULT_WEIGHTS.iter().any(|(known, _)| known == field) {
+            let known: Vec<&str> = DEFAULT_WEIGHTS.iter().map(|(f, _)| *f).collect();
+            return Err(eyre::eyre!(
+                "Unknown search field {:?}; expected one of {}.",
+                field,
+                known.join(", ")
+            ));
+        }
+        if !weight.is_finite() || *weight < 0.0 {
+            return Err(eyre::eyre!(
+                "Search weight for {:?} must be zero or more, got {}.",
+                field,
+                weight
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Field of a description by its DescriptionType code; untyped descriptions
+/// are general, as the standard says.
+fn description_field(kind: Option<&Code>) -> &'static str {
+    match kind.and_then(Code::as_string).as_deref() {
+        None | Some("1") => "description-general",
+        Some("2") => "description-objectives",
+        Some("3") => "description-target-groups",
+        Some(_) => "description-other",
+    }
+}
+
+fn org_texts(org: &Element<Organisation>, lang: &str) -> BTreeMap<String, String> {
+    match org {
+        Element::Parsed(o) => texts_by_lang(o.narrative.as_ref(), o.extra.get("#text"), lang),
+        Element::Raw(v) => texts_by_lang(None, Some(v), lang),
+    }
+}
+
+/// Builds an activity's search document. `lang` is the language of narratives
+/// without `xml:lang`, and only fields with a positive weight are included.
+///
+/// Entries are ordered by field as in [`DEFAULT_WEIGHTS`], then by document
+/// order, then by language, so the same activity always gives the same
+/// document. Text repeated within a field and language is kept once.
+pub fn search_document(
+    activity: &Activity,
+    lang: &str,
+    weights: &BTreeMap<String, f64>,
+) -> Vec<SearchEntry> {
+    let mut found: Vec<(&str, BTreeMap<String, String>)> = Vec::new();
+
+    if let Some(title) = &activity.title {
+        found.push(("title", title.texts(lang)));
+    }
+    for description in activity.description.iter().flat_map(|d| d.iter()) {
+        let (field, texts) = match description {
+            Element::Parsed(d) => (
+                description_field(d.kind.as_ref()),
+                texts_by_lang(d.narrative.as_ref(), d.extra.get("#text"), lang),
+            ),
+            Element::Raw(v) => (description_field(None), texts_by_lang(None, Some(v), lang)),
+        };
+        found.push((field, texts));
+    }
+    for result in activity.results() {
+        for text in [&result.title, &result.description].into_iter().flatten() {
+            found.push(("result", text.texts(lang)));
+        }
+        for indicator in result.indicator.iter().flat_map(|i| i.iter()) {
+            let indicator = match indicator {
+                Element::Parsed(i) => i,
+                Element::Raw(_) => continue,
+            };
+            for text in [&indicator.title, &indicator.description]
+                .into_iter()
+                .flatten()
+            {
+                found.push(("indicator", text.texts(lang)));
+            }
+        }
+    }
+    for sector in activity.sectors() {
+        found.push((
+            "sector",
+            texts_by_lang(sector.narrative.as_ref(), None, lang),
+        ));
+    }
+    if let Some(org) = &activity.reporting_org {
+        found.push(("reporting-org", org_texts(org, lang)));
+    }
+    for org in activity.participating_org.iter().flat_map(|o| o.iter()) {
+        found.push(("participating-org", org_texts(org, lang)));
+    }
+
+    let order = |field: &str| DEFAULT_WEIGHTS.iter().position(|(f, _)| *f == field);
+    found.sort_by_key(|(field, _)| order(field));
+
+    let mut seen = HashSet::new();
+    let mut entries = Vec::new();
+    for (field, texts) in found {
+        let weight = match weights.get(field) {
+            Some(w) if *w > 0.0 => *w,
+            _ => continue,
+        };
+        for (lang, text) in texts {
+            if seen.insert((field, lang.clone(), text.clone())) {
+                entries.push(SearchEntry {
+                    field: field.to_owned(),
+                    lang,
+                    weight,
+                    text,
+                });
+            }
+        }
+    }
+    entries
+}
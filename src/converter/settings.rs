@@ -0,0 +1,230 @@
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::converter::filter::PathFilter;
+use crate::converter::orgid::AgencyList;
+use crate::converter::rollup::ExchangeRates;
+use crate::converter::search::{check_weights, default_weights};
+use crate::converter::snapshot::SnapshotStore;
+use crate::converter::utils::FilterOptions;
+
+pub const DEFAULT_SOURCE_URL: &str = "https://data.iati-data-dump.opendataservices.coop/data.zip";
+// "https://gitlab.com/codeforIATI/iati-data/-/archive/main/iati-data-main.zip",
+
+py_struct! {
+    /// Where source data comes from and where it is kept on disk.
+    ///
+    /// Passed from Python as `Settings(...)`; every field is optional and falls
+    /// back to the previous hardcoded behaviour.
+    #[derive(Clone, Debug)]
+    pub struct Settings {
+        /// URL of the zipped registry dump.
+        #[pyo3(get, set)]
+        pub source_url: String,
+        /// Directory dumps are extracted into.
+        #[pyo3(get, set)]
+        pub cache_dir: String,
+        /// Directory holding an earlier extracted dump used to back-fill broken files.
+        #[pyo3(get, set)]
+        pub archive_dir: String,
+        /// Directory of dated downloads; defaults to `<cache_dir>/snapshots`.
+        #[pyo3(get, set)]
+        pub snapshot_dir: Option<String>,
+        /// Extra directory of dataset files to back-fill from after `archive_dir`.
+        #[pyo3(get, set)]
+        pub mirror_dir: Option<String>,
+        /// As a last resort, download broken datasets again from their registry URL.
+        #[pyo3(get, set)]
+        pub refetch: bool,
+        /// Skip the download when today's snapshot already exists.
+        #[pyo3(get, set)]
+        pub reuse_download: bool,
+        /// Never touch the network; uses the most recent snapshot.
+        #[pyo3(get, set)]
+        pub offline: bool,
+        /// Convert straight from the downloaded zip instead of extracting it.
+        /// Files listed in errors.txt are not back-filled in this mode.
+        #[pyo3(get, set)]
+        pub stream_zip: bool,
+        /// Publisher folders or globs to keep; empty keeps everything.
+        #[pyo3(get, set)]
+        pub include: Vec<String>,
+        /// Publisher folders or globs to drop.
+        #[pyo3(get, set)]
+        pub exclude: Vec<String>,
+        /// Language of narratives when neither they nor their activity declare one.
+        #[pyo3(get, set)]
+        pub default_lang: String,
+        /// Write narratives as one string in this language instead of a map by language.
+        #[pyo3(get, set)]
+        pub preferred_lang: Option<String>,
+        /// Weight of each `meta.search` field; fields left out are not indexed and
+        /// an empty map leaves the search document out.
+        #[pyo3(get, set)]
+        pub search_weights: BTreeMap<String, f64>,
+        /// Convert files in path order, so repeated runs over the same input give
+        /// byte-identical output.
+        #[pyo3(get, set)]
+        pub deterministic: bool,
+        /// Currency `exchange_rates` are quoted in; `meta.converted_totals` is
+        /// written in it when set.
+        #[pyo3(get, set)]
+        pub report_currency: Option<String>,
+        /// Value of one unit of each currency in `report_currency`, used to add up
+        /// transactions and budgets in other currencies.
+        #[pyo3(get, set)]
+        pub exchange_rates: BTreeMap<String, f64>,
+        /// Local copy of org-id.guide's list of registration agencies (its
+        /// `download.json`), used to split and check organisation refs.
+        #[pyo3(get, set)]
+        pub org_id_list: Option<String>,
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            source_url: DEFAULT_SOURCE_URL.to_owned(),
+            cache_dir: "raw_data".to_owned(),
+            archive_dir: "archive".to_owned(),
+            snapshot_dir: None,
+            mirror_dir: None,
+            refetch: false,
+            reuse_download: false,
+            offline: false,
+            stream_zip: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            default_lang: "en".to_owned(),
+            preferred_lang: None,
+            search_weights: default_weights(),
+            deterministic: false,
+            report_currency: None,
+            exchange_rates: BTreeMap::new(),
+            org_id_list: None,
+        }
+    }
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl Settings {
+    /// New parameters go last so positional calls from older code keep working.
+    #[new]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        source_url: Option<String>,
+        cache_dir: Option<String>,
+        archive_dir: Option<String>,
+        reuse_download: Option<bool>,
+        offline: Option<bool>,
+        include: Option<Vec<String>>,
+        exclude: Option<Vec<String>>,
+        stream_zip: Option<bool>,
+        mirror_dir: Option<String>,
+        refetch: Option<bool>,
+        snapshot_dir: Option<String>,
+        default_lang: Option<String>,
+        preferred_lang: Option<String>,
+        search_weights: Option<BTreeMap<String, f64>>,
+        deterministic: Option<bool>,
+        report_currency: Option<String>,
+        exchange_rates: Option<BTreeMap<String, f64>>,
+        org_id_list: Option<String>,
+    ) -> Self {
+        let defaults = Settings::default();
+        Settings {
+            source_url: source_url.unwrap_or(defaults.source_url),
+            cache_dir: cache_dir.unwrap_or(defaults.cache_dir),
+            archive_dir: archive_dir.unwrap_or(defaults.archive_dir),
+            snapshot_dir: snapshot_dir.or(defaults.snapshot_dir),
+            mirror_dir: mirror_dir.or(defaults.mirror_dir),
+            refetch: refetch.unwrap_or(defaults.refetch),
+            reuse_download: reuse_download.unwrap_or(defaults.reuse_download),
+            offline: offline.unwrap_or(defaults.offline),
+            stream_zip: stream_zip.unwrap_or(defaults.stream_zip),
+            include: include.unwrap_or(defaults.include),
+            exclude: exclude.unwrap_or(defaults.exclude),
+            default_lang: default_lang.unwrap_or(defaults.default_lang),
+            preferred_lang: preferred_lang.or(defaults.preferred_lang),
+            search_weights: search_weights.unwrap_or(defaults.search_weights),
+            deterministic: deterministic.unwrap_or(defaults.deterministic),
+            report_currency: report_currency.or(defaults.report_currency),
+            exchange_rates: exchange_rates.unwrap_or(defaults.exchange_rates),
+            org_id_list: org_id_list.or(defaults.org_id_list),
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+impl Settings {
+    pub fn cache_path(&self) -> PathBuf {
+        PathBuf::from(&self.cache_dir)
+    }
+
+    pub fn archive_path(&self) -> PathBuf {
+        PathBuf::from(&self.archive_dir)
+    }
+
+    pub fn path_filter(&self) -> eyre::Result<PathFilter> {
+        PathFilter::new(&self.include, &self.exclude)
+    }
+
+    pub fn filter_options(&self) -> eyre::Result<FilterOptions> {
+        check_weights(&self.search_weights)?;
+        let currency = |c: &str| c.trim().to_ascii_uppercase();
+        let exchange_rates = ExchangeRates {
+            base: self.report_currency.as_deref().map(currency),
+            rates: self
+                .exchange_rates
+                .iter()
+                .map(|(c, rate)| (currency(c), *rate))
+                .collect(),
+        };
+        exchange_rates.check()?;
+        let org_agencies = self.org_agencies()?;
+        Ok(FilterOptions {
+            default_lang: self.default_lang.trim().to_ascii_lowercase(),
+            preferred_lang: self.preferred_lang.clone(),
+            search_weights: self.search_weights.clone(),
+            exchange_rates,
+            org_agencies,
+        })
+    }
+
+    /// Registration agencies from `org_id_list`, empty when it isn't set.
+    pub fn org_agencies(&self) -> eyre::Result<AgencyList> {
+        match &self.org_id_list {
+            Some(path) => AgencyList::load(Path::new(path)),
+            None => Ok(AgencyList::default()),
+        }
+    }
+
+    /// Snapshot store directory, `<cache_dir>/snapshots` unless overridden.
+    pub fn snapshot_path(&self) -> PathBuf {
+        match &self.snapshot_dir {
+            Some(dir) => PathBuf::from(dir),
+            None => self.cache_path().join("snapshots"),
+        }
+    }
+
+    pub fn snapshot_store(&self) -> SnapshotStore {
+        SnapshotStore::new(self.snapshot_path())
+    }
+
+    /// Where a zipped dump is extracted: `<cache_dir>/<snapshot name>` for a
+    /// zip in the snapshot store, `cache_dir` otherwise.
+    pub fn extraction_path(&self, zip_path: &Path) -> PathBuf {
+        let snapshot_dir = zip_path.parent();
+        match snapshot_dir.and_then(|d| Some((d.parent()?, d.file_name()?))) {
+            Some((store, name)) if store == self.snapshot_path() => self.cache_path().join(name),
+            _ => self.cache_path(),
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use chrono::{NaiveDate, Utc};
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use serde_json::Value;
+use zip::ZipArchive;
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::converter::provenance::modified_at;
+
+/// File name of the dump inside each snapshot folder.
+pub const SNAPSHOT_ZIP: &str = "data.zip";
+
+py_struct! {
+    /// A dated download kept in the snapshot store.
+    #[derive(Clone, Debug)]
+    pub struct Snapshot {
+        /// Folder name, the UTC date of the download (`YYYY-MM-DD`).
+        #[pyo3(get)]
+        pub name: String,
+        #[pyo3(get)]
+        pub path: String,
+        #[pyo3(get)]
+        pub zip_path: String,
+        /// Size of the zipped dump in bytes.
+        #[pyo3(get)]
+        pub size: u64,
+        #[pyo3(get)]
+        pub downloaded_at: Option<String>,
+        /// The dump's own metadata.json, when it had one.
+        pub metadata: Option<Value>,
+    }
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl Snapshot {
+    /// The dump's metadata.json as a JSON string.
+    #[getter(metadata)]
+    fn py_metadata(&self) -> Option<String> {
+        self.metadata.as_ref().map(Value::to_string)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Snapshot({}, {} bytes)", self.name, self.size)
+    }
+}
+
+/// Whether `name` is a snapshot folder name, a `YYYY-MM-DD` date.
+pub fn is_snapshot_name(name: &str) -> bool {
+    name.len() == 10 && NaiveDate::parse_from_str(name, "%Y-%m-%d").is_ok()
+}
+
+/// Directory of dated dumps: `<dir>/<YYYY-MM-DD>/data.zip` plus the dump's
+/// `metadata.json` alongside it.
+pub struct SnapshotStore {
+    pub dir: PathBuf,
+}
+
+impl SnapshotStore {
+    pub fn new(dir: PathBuf) -> Self {
+        SnapshotStore { dir }
+    }
+
+    /// Name a download made now would be stored under.
+    pub fn today() -> String {
+        Utc::now().format("%Y-%m-%d").to_string()
+    }
+
+    /// The snapshot called `name`; `None` unless `name` is a `YYYY-MM-DD` date.
+    pub fn get(&self, name: &str) -> Option<Snapshot> {
+        if !is_snapshot_name(name) {
+            return None;
+        }
+        let path = self.dir.join(name);
+        let zip_path = path.join(SNAPSHOT_ZIP);
+        let size = fs::metadata(&zip_path).ok().filter(|m| m.is_file())?.len();
+        let metadata = fs::read_to_string(path.join("metadata.json"))
+            .ok()
+            .and_then(|c| serde_json::from_str(&c).ok());
+
+        Some(Snapshot {
+            name: name.to_owned(),
+            path: path.to_string_lossy().into_owned(),
+            zip_path: zip_path.to_string_lossy().into_owned(),
+            size,
+            downloaded_at: modified_at(&zip_path),
+            metadata,
+        })
+    }
+
+    /// All snapshots, oldest first.
+    pub fn list(&self) -> eyre::Result<Vec<Snapshot>> {
+        if !self.dir.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut names: Vec<String> = fs::read_dir(&self.dir)?
+            .flatten()
+            .filter(|e| e.path().is_dir())
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+
+        Ok(names.iter().filter_map(|n| self.get(n)).collect())
+    }
+
+    pub fn latest(&self) -> eyre::Result<Option<Snapshot>> {
+        Ok(self.list()?.pop())
+    }
+
+    /// Stores a freshly downloaded dump under `name`, replacing any snapshot
+    /// already there.
+    pub fn save(&self, name: &str, bytes: &[u8]) -> eyre::Result<Snapshot> {
+        if !is_snapshot_name(name) {
+            return Err(eyre::eyre!("Snapshot names are YYYY-MM-DD dates, not '{}'", name));
+        }
+        let path = self.dir.join(name);
+        fs::create_dir_all(&path)?;
+
+        let zip_path = path.join(SNAPSHOT_ZIP);
+        fs::write(&zip_path, bytes)?;
+
+        if let Some(metadata) = read_zip_metadata(&zip_path)? {
+            fs::write(path.join("metadata.json"), metadata)?;
+        }
+
+        self.get(name)
+            .ok_or_else(|| eyre::eyre!("Failed to store snapshot {}", name))
+    }
+
+    /// Deletes all but the `keep` most recent snapshots, returning the names removed.
+    pub fn prune(&self, keep: usize) -> eyre::Result<Vec<String>> {
+        let snapshots = self.list()?;
+        let excess = snapshots.len().saturating_sub(keep);
+
+        let mut removed = Vec::new();
+        for snapshot in snapshots.into_iter().take(excess) {
+            fs::remove_dir_all(&snapshot.path)?;
+            removed.push(snapshot.name);
+        }
+        Ok(removed)
+    }
+}
+
+/// The dump-level metadata.json from a zipped dump, if it has one at the
+/// root or inside a single top-level folder.
+fn read_zip_metadata(zip_path: &Path) -> eyre::Result<Option<String>> {
+    let mut archive = ZipArchive::new(File::open(zip_path)?)?;
+
+    let name = archive
+        .file_names()
+        .find(|n| {
+            let path = Path::new(n);
+            path.file_name().is_some_and(|name| name == "metadata.json")
+                && path.components().count() <= 2
+        })
+        .map(str::to_owned);
+
+    match name {
+        Some(name) => {
+            let mut content = String::new();
+            archive.by_name(&name)?.read_to_string(&mut content)?;
+            Ok(Some(content))
+        }
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,224 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use crate::converter::model::{Code, FilteredActivity};
+use crate::converter::pipeline::{file_activities, fold_dump, load_records};
+use crate::converter::settings::Settings;
+use crate::converter::utils::FilterOptions;
+
+/// Transaction sums in one currency.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct CurrencyTotals {
+    pub total: f64,
+    /// Totals by the year of the transaction date; `unknown` when undated.
+    pub by_year: BTreeMap<String, f64>,
+}
+
+/// Count and sums of one transaction type.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct TransactionTotals {
+    pub count: usize,
+    /// Keyed by the transaction currency, falling back to the activity's default.
+    pub by_currency: BTreeMap<String, CurrencyTotals>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FileFailure {
+    pub path: String,
+    pub error: String,
+}
+
+/// Aggregates over a whole dump. Every map is keyed by code and sorted.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct DumpStats {
+    pub files: usize,
+    pub files_failed: usize,
+    pub activities: usize,
+    pub publishers: BTreeMap<String, usize>,
+    pub recipient_countries: BTreeMap<String, usize>,
+    /// DAC 5-digit sector codes.
+    pub sectors: BTreeMap<String, usize>,
+    pub statuses: BTreeMap<String, usize>,
+    /// Keyed by IATI 2.x transaction type code.
+    pub transactions: BTreeMap<String, TransactionTotals>,
+    pub failures: Vec<FileFailure>,
+}
+
+const UNKNOWN: &str = "unknown";
+
+fn bump(map: &mut BTreeMap<String, usize>, key: Option<String>) {
+    *map.entry(key.unwrap_or_else(|| UNKNOWN.to_owned()))
+        .or_default() += 1;
+}
+
+fn merge_counts(into: &mut BTreeMap<String, usize>, from: BTreeMap<String, usize>) {
+    for (k, v) in from {
+        *into.entry(k).or_default() += v;
+    }
+}
+
+impl DumpStats {
+    /// Adds one filtered record published by `publisher`.
+    pub fn add_activity(&mut self, activity: &FilteredActivity, publisher: Option<String>) {
+        self.activities += 1;
+        bump(&mut self.publishers, publisher);
+
+        for country in activity.recipient_countries() {
+            bump(
+                &mut self.recipient_countries,
+                country.code.as_ref().and_then(|c| c.as_string()),
+            );
+        }
+        for sector in activity.dac_sectors() {
+            bump(&mut self.sectors, sector.dac_code.as_ref().and_then(Code::as_string));
+        }
+        let status = activity
+            .activity_status
+            .as_ref()
+            .and_then(|s| s.parsed())
+            .and_then(|s| s.code.as_ref()?.as_string());
+        bump(&mut self.statuses, status);
+
+        let default_currency = activity
+            .default_currency
+            .as_ref()
+            .and_then(|c| c.as_string());
+        for t in activity.transactions() {
+            let kind = t
+                .type_code()
+                .map(|c| c.to_string())
+                .or_else(|| t.transaction_type.as_string());
+            let totals = self
+                .transactions
+                .entry(kind.unwrap_or_else(|| UNKNOWN.to_owned()))
+                .or_default();
+            totals.count += 1;
+
+            let value = match t.transaction_value.as_f64() {
+                Some(v) => v,
+                None => continue,
+            };
+            let currency = t
+                .transaction_currency
+                .as_ref()
+                .and_then(|c| c.as_string())
+                .or_else(|| default_currency.clone())
+                .unwrap_or_else(|| UNKNOWN.to_owned());
+            let year = t
+                .transaction_date
+                .as_string()
+                .and_then(|d| {
+                    d.get(..4)
+                        .filter(|y| y.chars().all(|c| c.is_ascii_digit()))
+                        .map(str::to_owned)
+                })
+                .unwrap_or_else(|| UNKNOWN.to_owned());
+
+            let by_currency = totals.by_currency.entry(currency).or_default();
+            by_currency.total += value;
+            *by_currency.by_year.entry(year).or_default() += value;
+        }
+    }
+
+    fn add_failure(&mut self, path: String, error: eyre::Report) {
+        self.files_failed += 1;
+        self.failures.push(FileFailure {
+            path,
+            error: error.to_string(),
+        });
+    }
+
+    /// Combines the stats of two disjoint sets of files.
+    pub fn merge(mut self, other: DumpStats) -> DumpStats {
+        self.files += other.files;
+        self.files_failed += other.files_failed;
+        self.activities += other.activities;
+        merge_counts(&mut self.publishers, other.publishers);
+        merge_counts(&mut self.recipient_countries, other.recipient_countries);
+        merge_counts(&mut self.sectors, other.sectors);
+        merge_counts(&mut self.statuses, other.statuses);
+        for (kind, totals) in other.transactions {
+            let into = self.transactions.entry(kind).or_default();
+            into.count += totals.count;
+            for (currency, sums) in totals.by_currency {
+                let into = into.by_currency.entry(currency).or_default();
+                into.total += sums.total;
+                for (year, v) in sums.by_year {
+                    *into.by_year.entry(year).or_default() += v;
+                }
+            }
+        }
+        self.failures.extend(other.failures);
+        self
+    }
+
+    /// Converts one file's bytes and adds its activities, or records the failure.
+    fn add_file(
+        &mut self,
+        path: &Path,
+        bytes: eyre::Result<Vec<u8>>,
+        publisher: Option<String>,
+        options: &FilterOptions,
+    ) {
+        self.files += 1;
+        match file_activities(path, bytes, options) {
+            Ok(activities) => {
+                for dump in &activities {
+                    self.add_activity(&dump.filtered, publisher.clone());
+                }
+            }
+            Err(e) => self.add_failure(path.to_string_lossy().into_owned(), e),
+        }
+    }
+}
+
+/// Counts and sums over a dump folder, zip, single file, snapshot name or a
+/// JSON file written by `convert`, computed per file in parallel without
+/// keeping the converted records. Dump files are filtered with the same
+/// `settings` as `convert`.
+pub fn dump_stats(path: &str, settings: &Settings) -> eyre::Result<DumpStats> {
+    let p = Path::new(path);
+
+    let mut stats = if p.is_file() && p.extension().is_some_and(|ext| ext == "json") {
+        let mut stats = DumpStats::default();
+        let mut files = BTreeSet::new();
+        for record in load_records(path, settings)? {
+            if let Some(file) = record.pointer("/_source/path").and_then(Value::as_str) {
+                files.insert(file.to_owned());
+            }
+            match FilteredActivity::from_value(&record) {
+                Ok(activity) => {
+                    let publisher = record
+                        .pointer("/_source/publisher")
+                        .and_then(Value::as_str)
+                        .map(str::to_owned);
+                    stats.add_activity(&activity, publisher);
+                }
+                Err(e) => {
+                    let file = record.pointer("/_source/path").and_then(Value::as_str);
+                    stats.add_failure(file.unwrap_or(path).to_owned(), e.into());
+                }
+            }
+        }
+        stats.files = files.len();
+        stats
+    } else {
+        let options = settings.filter_options()?;
+        fold_dump(
+            path,
+            settings,
+            DumpStats::default,
+            |mut stats, file| {
+                stats.add_file(file.path, file.bytes, file.publisher, &options);
+                stats
+            },
+            DumpStats::merge,
+        )?
+    };
+
+    stats.failures.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(stats)
+}
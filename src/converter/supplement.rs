@@ -0,0 +1,225 @@
+use zip::ZipArchive;
+
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::converter::health::{file_status, ErrorEntry, FileStatus};
+use crate::converter::settings::Settings;
+use crate::converter::snapshot::{is_snapshot_name, Snapshot};
+
+/// A place a dataset listed in errors.txt can be back-filled from.
+pub trait SupplementSource: Sync {
+    /// Short name recorded against each file this source supplies.
+    fn name(&self) -> &'static str;
+
+    /// Writes a replacement for `entry` to `destination`, returning a note
+    /// describing where it came from.
+    fn supply(&self, entry: &ErrorEntry, destination: &Path) -> eyre::Result<String>;
+}
+
+/// The most recent dated download in the snapshot store older than the one
+/// being converted, or the newest when the dump didn't come from the store.
+/// Datasets are read straight from its zip.
+pub struct PreviousSnapshot {
+    pub snapshot: Snapshot,
+}
+
+/// An earlier extracted dump under `archive_dir`, mirroring the current dump's
+/// folder name (e.g. `archive/iati-data-main/data`).
+pub struct ArchiveDir {
+    pub data_dir: PathBuf,
+}
+
+/// A directory of dataset files kept in sync by other means, laid out as
+/// `<mirror>/<publisher>/<dataset>.xml` or `<mirror>/data/<publisher>/<dataset>.xml`.
+pub struct MirrorDir {
+    pub dir: PathBuf,
+}
+
+/// Downloads the dataset again from the URL recorded in errors.txt.
+pub struct RefetchUrl {
+    pub timeout: Duration,
+}
+
+impl SupplementSource for PreviousSnapshot {
+    fn name(&self) -> &'static str {
+        "snapshot"
+    }
+
+    fn supply(&self, entry: &ErrorEntry, destination: &Path) -> eyre::Result<String> {
+        let wanted = entry.path_in(Path::new("data"));
+        let mut archive = ZipArchive::new(File::open(&self.snapshot.zip_path)?)?;
+        let index = (0..archive.len())
+            .find(|&i| {
+                archive.by_index_raw(i).is_ok_and(|file| {
+                    file.size() > 0 && file.enclosed_name().is_some_and(|n| n.ends_with(&wanted))
+                })
+            })
+            .ok_or_else(|| eyre::eyre!("not in snapshot {}", self.snapshot.name))?;
+
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = archive.by_index(index)?;
+        let written = io::copy(&mut file, &mut File::create(destination)?)?;
+        if written != file.size() {
+            return Err(eyre::eyre!(
+                "size mismatch extracting {} from snapshot {}",
+                file.name(),
+                self.snapshot.name
+            ));
+        }
+        Ok(format!("extracted from snapshot {}", self.snapshot.name))
+    }
+}
+
+impl SupplementSource for ArchiveDir {
+    fn name(&self) -> &'static str {
+        "archive"
+    }
+
+    fn supply(&self, entry: &ErrorEntry, destination: &Path) -> eyre::Result<String> {
+        let source = entry.path_in(&self.data_dir);
+        copy_dataset(&source, destination)?;
+        Ok(format!("copied from {}", source.display()))
+    }
+}
+
+impl SupplementSource for MirrorDir {
+    fn name(&self) -> &'static str {
+        "mirror"
+    }
+
+    fn supply(&self, entry: &ErrorEntry, destination: &Path) -> eyre::Result<String> {
+        let candidates = [entry.path_in(&self.dir), entry.path_in(&self.dir.join("data"))];
+        let source = candidates
+            .iter()
+            .find(|c| file_status(c) == FileStatus::Present)
+            .ok_or_else(|| eyre::eyre!("not in mirror {}", self.dir.display()))?;
+        copy_dataset(source, destination)?;
+        Ok(format!("copied from {}", source.display()))
+    }
+}
+
+impl SupplementSource for RefetchUrl {
+    fn name(&self) -> &'static str {
+        "refetch"
+    }
+
+    fn supply(&self, entry: &ErrorEntry, destination: &Path) -> eyre::Result<String> {
+        let url = entry
+            .url
+            .as_deref()
+            .ok_or_else(|| eyre::eyre!("no URL recorded"))?;
+
+        let client = reqwest::blocking::Client::builder()
+            .timeout(self.timeout)
+            .build()?;
+        let response = client.get(url).send()?;
+        if !response.status().is_success() {
+            return Err(eyre::eyre!("{} returned {}", url, response.status()));
+        }
+
+        let bytes = response.bytes()?;
+        let head = String::from_utf8_lossy(&bytes[..bytes.len().min(1024)]);
+        let html = head.to_ascii_lowercase().contains("<!doctype html");
+        if bytes.is_empty() || html || !head.contains('<') {
+            return Err(eyre::eyre!("{} did not return XML", url));
+        }
+
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(destination, &bytes)?;
+        Ok(format!("fetched from {}", url))
+    }
+}
+
+/// Copies a non-empty replacement over `destination`, checking the size afterwards.
+pub fn copy_dataset(source: &Path, destination: &Path) -> eyre::Result<()> {
+    if file_status(source) != FileStatus::Present {
+        return Err(eyre::eyre!("no replacement at {}", source.display()));
+    }
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let bytes = fs::copy(source, destination)?;
+    let written = fs::metadata(destination)?.len();
+    if written != bytes {
+        return Err(eyre::eyre!(
+            "size mismatch copying {}: source {} bytes, destination {} bytes",
+            source.display(),
+            bytes,
+            written
+        ));
+    }
+
+    Ok(())
+}
+
+/// Sources to try, in order, for a dump whose `data` folder is `data_dir`.
+pub fn supplement_chain(
+    data_dir: &Path,
+    settings: &Settings,
+) -> eyre::Result<Vec<Box<dyn SupplementSource>>> {
+    let root_dir = data_dir.parent().unwrap_or(Path::new("."));
+    let root_name = root_dir.file_name().unwrap_or_default();
+
+    // A dump extracted from a snapshot sits under `<cache_dir>/<name>`; only
+    // snapshots older than it count as previous.
+    let current = data_dir
+        .strip_prefix(settings.cache_path())
+        .ok()
+        .and_then(|rest| rest.components().next())
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .filter(|name| is_snapshot_name(name));
+    let previous = settings
+        .snapshot_store()
+        .list()?
+        .into_iter()
+        .rev()
+        .find(|s| current.as_ref().is_none_or(|name| s.name < *name));
+
+    let mut chain: Vec<Box<dyn SupplementSource>> = Vec::new();
+    if let Some(snapshot) = previous {
+        chain.push(Box::new(PreviousSnapshot { snapshot }));
+    }
+    chain.push(Box::new(ArchiveDir {
+        data_dir: settings.archive_path().join(root_name).join("data"),
+    }));
+    if let Some(mirror) = &settings.mirror_dir {
+        chain.push(Box::new(MirrorDir {
+            dir: PathBuf::from(mirror),
+        }));
+    }
+    if settings.refetch && !settings.offline {
+        chain.push(Box::new(RefetchUrl {
+            timeout: Duration::from_secs(120),
+        }));
+    }
+    Ok(chain)
+}
+
+/// Runs `entry` through `chain` until one source supplies it, recording the
+/// outcome of every attempt on the entry.
+pub fn supplement_entry(entry: &mut ErrorEntry, data_dir: &Path, chain: &[Box<dyn SupplementSource>]) {
+    let destination = entry.path_in(data_dir);
+
+    for source in chain {
+        match source.supply(entry, &destination) {
+            Ok(note) => {
+                entry.status = FileStatus::Supplemented;
+                entry.supplied_by = Some(source.name().to_owned());
+                entry.attempts.push(format!("{}: {}", source.name(), note));
+                entry.note = Some(note);
+                return;
+            }
+            Err(e) => entry.attempts.push(format!("{}: {}", source.name(), e)),
+        }
+    }
+
+    entry.note = entry.attempts.last().cloned();
+}
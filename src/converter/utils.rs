@@ -3,16 +3,34 @@ use eyre::Result;
 use quickxml_to_serde::{xml_string_to_json, Config};
 use regex::Regex;
 use reqwest;
-use serde_json::{json, Map, Value};
+use serde_json::{Map, Value};
 use zip::ZipArchive;
 
 // Standard Library Imports
-use std::collections::{HashMap, HashSet};
+use std::collections::BTreeMap;
 use std::fs::{self, File};
-use std::io::{self, BufRead, BufReader, Read};
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 
+use crate::converter::filter::{data_relative, is_safe_relative};
+use crate::converter::health::{read_health, FileStatus, HealthReport};
+use crate::converter::layout::{detect_source, FlatFolder, SourceLayout};
+use crate::converter::model::{
+    parsed, Activity, Code, CodeElement, CodeRef, Element, Elements,
+    FilteredActivity, FilteredBudget, FilteredDescription, FilteredLocation, FilteredRecipient,
+    FilteredTransaction, LangText, LatLon, Location, Meta, Narratives, OneOrMany, Organisation,
+    ParticipatingOrg, PolicyMarkerSummary, Recipient, RelatedRef, ReportingOrg, SectorSummary,
+    TextElement, Transaction, VocabularyCode, org_role, sector_vocabulary, texts_by_lang,
+};
+use crate::converter::orgid::{parse_org_ref, AgencyList};
+use crate::converter::rollup::{activity_totals, flatten_budget, totals_in, ExchangeRates};
+use crate::converter::search::{default_weights, search_document};
+use crate::converter::settings::Settings;
+use crate::converter::snapshot::SnapshotStore;
+use crate::converter::supplement::{supplement_chain, supplement_entry};
+
+#[allow(clippy::unnecessary_map_or)]
 pub fn count_xml_files(dir: &Path) -> eyre::Result<usize> {
     let entries = fs::read_dir(dir)?;
     let mut count = 0;
@@ -39,6 +57,7 @@ pub fn try_convert_xml_to_json(xml: &str, config: &Config) -> Result<String, eyr
     Ok(serde_json::to_string(&json)?)
 }
 
+#[allow(clippy::needless_return)]
 pub fn preprocess_xml(input: &str) -> String {
     let mut processed_xml = input.to_string();
 
@@ -49,294 +68,498 @@ pub fn preprocess_xml(input: &str) -> String {
     return processed_xml;
 }
 
-fn is_human_text(s: &str) -> bool {
-    let t = s.trim();
-    if t.len() < 4 || !t.contains(' ') {
-        return false;
+/// How `filter_activity` writes its records.
+#[derive(Clone, Debug)]
+pub struct FilterOptions {
+    /// Language of narratives without `xml:lang` when the activity doesn't
+    /// declare one either.
+    pub default_lang: String,
+    /// Reduce narratives to one string in this language, falling back to the
+    /// activity's language and then to whichever language comes first.
+    pub preferred_lang: Option<String>,
+    /// Weight of each field of `meta.search`; fields left out are not indexed.
+    pub search_weights: BTreeMap<String, f64>,
+    /// Rates for adding up values in other currencies in `meta.totals`, and
+    /// for `meta.converted_totals` when they have a base currency.
+    pub exchange_rates: ExchangeRates,
+    /// Registration agencies participating org refs are checked against.
+    pub org_agencies: AgencyList,
+}
+
+impl Default for FilterOptions {
+    fn default() -> Self {
+        FilterOptions {
+            default_lang: "en".to_owned(),
+            preferred_lang: None,
+            search_weights: default_weights(),
+            exchange_rates: ExchangeRates::default(),
+            org_agencies: AgencyList::default(),
+        }
     }
-    t.chars().any(|c| c.is_alphabetic())
 }
 
-fn collect_narratives(v: &Value, bag: &mut HashSet<String>) {
-    match v {
-        Value::Object(o) => {
-            for (k, v2) in o {
-                if k == "narrative" {
-                    if let Value::String(s) = v2 {
-                        if is_human_text(s) {
-                            bag.insert(s.trim().to_owned());
-                        }
-                    }
-                }
-                collect_narratives(v2, bag); // descend
+impl FilterOptions {
+    /// Normalises narratives, or the plain text of elements written without
+    /// them as in v1.0x, to text by language. `lang` is the activity's language.
+    fn lang_text(
+        &self,
+        narratives: Option<&Narratives>,
+        plain: Option<&Value>,
+        lang: &str,
+    ) -> Option<LangText> {
+        let texts = texts_by_lang(narratives, plain, lang);
+        if texts.is_empty() {
+            return None;
+        }
+
+        match &self.preferred_lang {
+            Some(preferred) => {
+                let preferred = preferred.to_ascii_lowercase();
+                let text = texts
+                    .get(&preferred)
+                    .or_else(|| texts.get(lang))
+                    .or_else(|| texts.values().next())?;
+                Some(LangText::One(text.clone()))
             }
+            None => Some(LangText::ByLang(texts)),
         }
-        Value::Array(a) => a.iter().for_each(|v2| collect_narratives(v2, bag)),
-        _ => {}
     }
 }
 
-pub fn filter_activity(activity: &Value) -> Value {
-    const KEEP: [&str; 12] = [
-        "default-currency",
-        "last-updated-datetime",
-        "iati-identifier",
-        "reporting-org",
-        "title",
-        "description",
-        "participating-org",
-        "activity-status",
-        "recipient-country",
-        "recipient-region",
-        "location",
-        "transaction",
-    ];
-    let wanted: HashSet<_> = KEEP.iter().cloned().collect();
-    let obj = match activity.as_object() {
-        Some(o) => o,
-        None => return Value::Object(Map::new()),
+/// Code of the first of `key` / `default-key` present, as long as it's a single element.
+fn single_code(own: &Elements<CodeElement>, default: &Elements<CodeElement>) -> Option<CodeRef> {
+    let element = own.as_ref().or(default.as_ref())?;
+    match element {
+        OneOrMany::One(Element::Parsed(c)) => Some(CodeRef {
+            code: c.code.clone()?,
+        }),
+        _ => None,
+    }
+}
+
+fn filter_transaction(t: &Transaction) -> Option<FilteredTransaction> {
+    let amount = t.amount()?;
+    Some(FilteredTransaction {
+        transaction_type: t.type_code()?.clone(),
+        transaction_date: t.date()?.clone(),
+        transaction_value: amount.amount()?.clone(),
+        transaction_currency: amount.currency.clone(),
+    })
+}
+
+fn filter_recipients(
+    recipients: &Elements<Recipient>,
+    options: &FilterOptions,
+    lang: &str,
+) -> Option<Vec<FilteredRecipient>> {
+    let recipients = recipients.as_ref()?;
+    let filtered = recipients
+        .iter()
+        .map(|r| match r {
+            Element::Parsed(r) => FilteredRecipient {
+                code: r.code.clone(),
+                percentage: r.percentage.clone(),
+                vocabulary: r.vocabulary.clone(),
+                vocabulary_uri: r.vocabulary_uri.clone(),
+                name: options.lang_text(r.narrative.as_ref(), r.extra.get("#text"), lang),
+            },
+            Element::Raw(v) => FilteredRecipient {
+                name: options.lang_text(None, Some(v), lang),
+                ..Default::default()
+            },
+        })
+        .collect();
+    Some(filtered)
+}
+
+/// Reads `point/pos`, or the v1.0x `coordinates` element, into a position.
+/// A position that can't be read or is out of range comes back as the text
+/// published, so it can still be inspected.
+fn location_point(l: &Location) -> (Option<LatLon>, Option<Code>) {
+    if let Some(pos) = l.point.as_ref().and_then(|p| p.parsed()?.pos.as_ref()) {
+        let point = pos.as_string().as_deref().and_then(LatLon::parse);
+        let invalid = point.is_none().then(|| pos.clone());
+        return (point, invalid);
+    }
+    let coordinates = match l.extra.get("coordinates") {
+        Some(c) => c,
+        None => return (None, None),
+    };
+    let degrees = |key: &str| {
+        coordinates
+            .get(key)
+            .map(|v| Code(v.clone()))
+            .and_then(|c| c.as_f64())
+    };
+    match (degrees("latitude"), degrees("longitude")) {
+        (Some(lat), Some(lon)) => match LatLon::new(lat, lon) {
+            Some(point) => (Some(point), None),
+            None => (None, Some(Code(Value::String(format!("{} {}", lat, lon))))),
+        },
+        _ => (None, None),
+    }
+}
+
+fn filter_location(l: &Location, options: &FilterOptions, lang: &str) -> FilteredLocation {
+    let text = |e: &Option<Element<TextElement>>| match e.as_ref()? {
+        Element::Parsed(t) => options.lang_text(t.narrative.as_ref(), t.extra.get("#text"), lang),
+        Element::Raw(v) => options.lang_text(None, Some(v), lang),
+    };
+    let code = |e: &Option<Element<CodeElement>>| e.as_ref()?.parsed()?.code.clone();
+    let extra_code = |key: &str| {
+        let code = l.extra.get(key)?.get("code")?;
+        Some(Code(code.clone()))
+    };
+    let codes = |elements: &Elements<CodeElement>| -> Vec<VocabularyCode> {
+        parsed(elements)
+            .filter_map(|c| {
+                Some(VocabularyCode {
+                    vocabulary: c.vocabulary.clone(),
+                    level: c.extra.get("level").map(|v| Code(v.clone())),
+                    code: c.code.clone()?,
+                })
+            })
+            .collect()
+    };
+    let (point, invalid_pos) = location_point(l);
+
+    FilteredLocation {
+        reference: l.reference.clone(),
+        name: text(&l.name),
+        description: text(&l.description),
+        location_reach: code(&l.location_reach),
+        location_id: codes(&l.location_id),
+        administrative: codes(&l.administrative),
+        point,
+        invalid_pos,
+        exactness: code(&l.exactness),
+        location_class: extra_code("location-class"),
+        feature_designation: extra_code("feature-designation"),
+    }
+}
+
+fn filter_participant(
+    org: &Element<Organisation>,
+    options: &FilterOptions,
+    lang: &str,
+) -> ParticipatingOrg {
+    let o = match org {
+        Element::Parsed(o) => o,
+        Element::Raw(v) => {
+            return ParticipatingOrg {
+                name: options.lang_text(None, Some(v), lang),
+                ..Default::default()
+            }
+        }
     };
+    let role = o.role.as_ref().and_then(Code::as_string);
+    let role = role.as_deref().and_then(org_role);
+    ParticipatingOrg {
+        reference: o.reference.clone(),
+        kind: o.kind.clone(),
+        role: match role {
+            Some((number, _)) => Some(Code(Value::from(number))),
+            None => o.role.clone(),
+        },
+        role_name: role.map(|(_, name)| name.to_owned()),
+        activity_id: o.activity_id.clone(),
+        crs_channel_code: o.crs_channel_code.clone(),
+        name: options.lang_text(o.narrative.as_ref(), o.extra.get("#text"), lang),
+        org_id: o
+            .reference
+            .as_ref()
+            .map(|r| parse_org_ref(&r.as_string().unwrap_or_default(), &options.org_agencies)),
+    }
+}
+
+/// Builds the record `convert` returns for one parsed activity.
+pub fn filter_typed(activity: &Activity, options: &FilterOptions) -> FilteredActivity {
+    let lang = activity
+        .lang
+        .as_deref()
+        .map(|l| l.trim().to_ascii_lowercase())
+        .filter(|l| !l.is_empty())
+        .unwrap_or_else(|| options.default_lang.clone());
+    let text =
+        |narratives: &Option<Narratives>| options.lang_text(narratives.as_ref(), None, &lang);
 
     //---------------- meta -----------------
-    let mut meta = Map::new();
-
-    // sectors (all) + main purpose
-    if let Some(sec_val) = obj.get("sector") {
-        let src = match sec_val {
-            Value::Array(a) => a.clone(),
-            Value::Object(o) => vec![Value::Object(o.clone())],
-            _ => vec![],
+    let mut meta = Meta::default();
+
+    // sectors by vocabulary + main DAC purpose
+    let mut sectors: BTreeMap<String, Vec<SectorSummary>> = BTreeMap::new();
+    for s in activity.sectors() {
+        let code = match &s.code {
+            Some(code) => code.clone(),
+            None => continue,
         };
-
-        // build sector list
-        let mut sectors: Vec<Map<String, Value>> = src
-            .into_iter()
-            .filter_map(|s| {
-                let o = s.as_object()?;
-                let mut item = Map::new();
-                item.insert("dac_code".into(), o.get("code")?.clone());
-                if let Some(n) = o.get("narrative") {
-                    item.insert("name".into(), n.clone());
-                }
-                if let Some(p) = o.get("percentage") {
-                    item.insert("percentage".into(), p.clone());
-                }
-                Some(item)
-            })
-            .collect();
-
-        if !sectors.is_empty() {
-            // select purpose = highest percentage or first
-            sectors.sort_by(|a, b| {
-                let pa = a.get("percentage").and_then(|v| v.as_f64()).unwrap_or(0.0);
-                let pb = b.get("percentage").and_then(|v| v.as_f64()).unwrap_or(0.0);
-                pb.partial_cmp(&pa).unwrap() // descending
+        let vocabulary = sector_vocabulary(s.vocabulary.as_ref());
+        let digits = code.as_string().filter(|c| c.chars().all(|c| c.is_ascii_digit()));
+        let (dac_code, dac_category) = match (vocabulary.as_str(), digits) {
+            ("1", Some(c)) if c.len() == 5 => (Some(code.clone()), Some(c[..3].to_owned())),
+            ("2", Some(c)) if c.len() == 3 => (None, Some(c)),
+            _ => (None, None),
+        };
+        sectors
+            .entry(vocabulary.clone())
+            .or_default()
+            .push(SectorSummary {
+                code,
+                vocabulary,
+                vocabulary_uri: s.vocabulary_uri.clone(),
+                dac_code,
+                dac_category,
+                name: text(&s.narrative),
+                percentage: s.percentage.clone(),
             });
-            meta.insert(
-                "sectors".into(),
-                Value::Array(sectors.iter().cloned().map(Value::Object).collect()),
-            );
-            meta.insert("purpose".into(), Value::Object(sectors[0].clone()));
-        }
+    }
+    let pct = |s: &SectorSummary| s.percentage.as_ref().and_then(Code::as_f64).unwrap_or(0.0);
+    for group in sectors.values_mut() {
+        group.sort_by(|a, b| pct(b).total_cmp(&pct(a))); // descending, stable
+    }
+    meta.purpose = ["1", "2"]
+        .iter()
+        .filter_map(|v| sectors.get(*v)?.iter().find(|s| s.dac_category.is_some()))
+        .next()
+        .cloned();
+    if !sectors.is_empty() {
+        meta.sectors = Some(sectors);
     }
 
     // policy‑markers
-    if let Some(pm) = obj.get("policy-marker") {
-        let src = match pm {
-            Value::Array(a) => a.clone(),
-            Value::Object(o) => vec![Value::Object(o.clone())],
-            _ => vec![],
-        };
-        let mks: Vec<Value> = src
-            .into_iter()
-            .filter_map(|v| {
-                let m = v.as_object()?;
-                let mut out = Map::new();
-                if let Some(c) = m.get("code") {
-                    out.insert("code".into(), c.clone());
-                }
-                if let Some(sig) = m.get("significance") {
-                    out.insert("significance".into(), sig.clone());
-                }
-                if let Some(n) = m.get("narrative") {
-                    out.insert("name".into(), n.clone());
-                }
-                (!out.is_empty()).then(|| Value::Object(out))
-            })
-            .collect();
-        if !mks.is_empty() {
-            meta.insert("policy_markers".into(), Value::Array(mks));
-        }
+    let markers: Vec<PolicyMarkerSummary> = parsed(&activity.policy_marker)
+        .map(|m| PolicyMarkerSummary {
+            code: m.code.clone(),
+            significance: m.significance.clone(),
+            name: text(&m.narrative),
+        })
+        .filter(|m| *m != PolicyMarkerSummary::default())
+        .collect();
+    if !markers.is_empty() {
+        meta.policy_markers = Some(markers);
     }
 
     // single‑code helpers
-    let grab = |key: &str| -> Option<Value> {
-        obj.get(key)
-            .or_else(|| obj.get(&format!("default-{}", key)))
-            .and_then(|v| v.get("code"))
-            .cloned()
-    };
-    if let Some(v) = grab("aid-type") {
-        meta.insert("aid_type".into(), json!({"code":v}));
-    }
-    if let Some(v) = grab("finance-type") {
-        meta.insert("finance_type".into(), json!({"code":v}));
-    }
+    meta.aid_type = single_code(&activity.aid_type, &activity.default_aid_type);
+    meta.finance_type = single_code(&activity.finance_type, &activity.default_finance_type);
 
     // related activities
-    if let Some(rel) = obj.get("related-activity") {
-        let src = match rel {
-            Value::Array(a) => a.clone(),
-            Value::Object(o) => vec![Value::Object(o.clone())],
-            _ => vec![],
-        };
-        let rels: Vec<Value> = src
-            .into_iter()
-            .filter_map(|v| {
-                let o = v.as_object()?;
-                let mut m = Map::new();
-                if let Some(r) = o.get("ref") {
-                    m.insert("ref".into(), r.clone());
-                }
-                if let Some(t) = o.get("type") {
-                    m.insert("type".into(), t.clone());
-                }
-                (!m.is_empty()).then(|| Value::Object(m))
-            })
-            .collect();
-        if !rels.is_empty() {
-            meta.insert("related_activities".into(), Value::Array(rels));
-        }
+    let related: Vec<RelatedRef> = parsed(&activity.related_activity)
+        .map(|r| RelatedRef {
+            reference: r.reference.clone(),
+            kind: r.kind.clone(),
+        })
+        .filter(|r| *r != RelatedRef::default())
+        .collect();
+    if !related.is_empty() {
+        meta.related_activities = Some(related);
+    }
+
+    // search document
+    let search = search_document(activity, &lang, &options.search_weights);
+    if !search.is_empty() {
+        meta.search = Some(search);
     }
 
     //---------------- core fields ----------------
-    let mut out = Map::new();
-    for (k, v) in obj {
-        if !wanted.contains(k.as_str()) {
-            continue;
-        }
-        match k.as_str() {
-            "description" => {
-                let arr = match v {
-                    Value::Array(a) => a.clone(),
-                    Value::Object(o) => vec![Value::Object(o.clone())],
-                    _ => vec![],
-                };
-                out.insert(k.clone(), Value::Array(arr));
-            }
-            "transaction" => {
-                let src = match v {
-                    Value::Array(a) => a.clone(),
-                    Value::Object(o) => vec![Value::Object(o.clone())],
-                    _ => vec![],
-                };
-                let txs: Vec<Value> = src
-                    .into_iter()
-                    .filter_map(|t| {
-                        let m = t.as_object()?;
-                        let tt = m.get("transaction-type")?.get("code")?;
-                        let td = m.get("transaction-date")?.get("iso-date")?;
-                        let val = m.get("value")?;
-                        let tv = val.get("#text").or_else(|| val.get("value"))?;
-                        let mut n = Map::new();
-                        n.insert("transaction-type".into(), tt.clone());
-                        n.insert("transaction-date".into(), td.clone());
-                        n.insert("transaction-value".into(), tv.clone());
-                        if let Some(c) = val.get("currency") {
-                            n.insert("transaction-currency".into(), c.clone());
-                        }
-                        Some(Value::Object(n))
-                    })
-                    .collect();
-                out.insert(k.clone(), Value::Array(txs));
-            }
-            "title" => {
-                if let Some(narr) = v.get("narrative") {
-                    out.insert(k.clone(), narr.clone());
-                }
-            }
-            "reporting-org" => {
-                let mut ro = Map::new();
-                if let Some(id) = v.get("ref") {
-                    ro.insert("id".into(), id.clone());
-                }
-                if let Some(t) = v.get("type") {
-                    ro.insert("type".into(), t.clone());
-                }
-                if let Some(n) = v.get("narrative") {
-                    ro.insert("name".into(), n.clone());
-                }
-                out.insert(k.clone(), Value::Object(ro));
-            }
-            _ => {
-                out.insert(k.clone(), v.clone());
-            }
-        }
+    let transactions: Option<Vec<FilteredTransaction>> = activity
+        .transaction
+        .as_ref()
+        .map(|_| {
+            activity
+                .transactions()
+                .filter_map(filter_transaction)
+                .collect()
+        });
+    let budgets: Option<Vec<FilteredBudget>> = activity
+        .budget
+        .as_ref()
+        .map(|_| activity.budgets().filter_map(flatten_budget).collect());
+
+    // financial totals
+    let transaction_list = transactions.as_deref().unwrap_or_default();
+    let budget_list = budgets.as_deref().unwrap_or_default();
+    let rates = &options.exchange_rates;
+    meta.totals = activity_totals(&activity.default_currency, transaction_list, budget_list, rates);
+    if let (Some(totals), Some(base)) = (&meta.totals, &rates.base) {
+        let default_currency = activity
+            .default_currency
+            .as_ref()
+            .and_then(Code::as_string)
+            .map(|c| c.to_ascii_uppercase());
+        meta.converted_totals = Some(totals_in(
+            base,
+            default_currency.as_deref().or(Some(totals.currency.as_str())),
+            transaction_list,
+            budget_list,
+            rates,
+        ));
     }
-    if !meta.is_empty() {
-        out.insert("meta".into(), Value::Object(meta));
+
+    let description = activity.description.as_ref().map(|d| {
+        d.iter()
+            .filter_map(|d| match d {
+                Element::Parsed(d) => Some(FilteredDescription {
+                    kind: d.kind.clone(),
+                    narrative: options.lang_text(
+                        d.narrative.as_ref(),
+                        d.extra.get("#text"),
+                        &lang,
+                    )?,
+                }),
+                Element::Raw(v) => Some(FilteredDescription {
+                    kind: None,
+                    narrative: options.lang_text(None, Some(v), &lang)?,
+                }),
+            })
+            .collect()
+    });
+
+    FilteredActivity {
+        default_currency: activity.default_currency.clone(),
+        last_updated_datetime: activity.last_updated_datetime.clone(),
+        iati_identifier: activity.iati_identifier.clone(),
+        reporting_org: activity.reporting_org.as_ref().map(|ro| match ro {
+            Element::Parsed(o) => ReportingOrg {
+                id: o.reference.clone(),
+                kind: o.kind.clone(),
+                name: options.lang_text(o.narrative.as_ref(), o.extra.get("#text"), &lang),
+            },
+            Element::Raw(v) => ReportingOrg {
+                name: options.lang_text(None, Some(v), &lang),
+                ..Default::default()
+            },
+        }),
+        title: activity.title.as_ref().and_then(|t| match t {
+            Element::Parsed(t) => {
+                options.lang_text(t.narrative.as_ref(), t.extra.get("#text"), &lang)
+            }
+            Element::Raw(v) => options.lang_text(None, Some(v), &lang),
+        }),
+        description,
+        participating_org: activity.participating_org.as_ref().map(|orgs| {
+            orgs.iter()
+                .map(|o| filter_participant(o, options, &lang))
+                .collect()
+        }),
+        activity_status: activity.activity_status.clone(),
+        recipient_country: filter_recipients(&activity.recipient_country, options, &lang),
+        recipient_region: filter_recipients(&activity.recipient_region, options, &lang),
+        location: activity.location.is_some().then(|| {
+            activity
+                .locations()
+                .map(|l| filter_location(l, options, &lang))
+                .collect()
+        }),
+        transaction: transactions,
+        budget: budgets,
+        meta: (!meta.is_empty()).then_some(meta),
+        extra: Map::new(),
     }
+}
 
-    let mut bag = HashSet::new();
-    collect_narratives(activity, &mut bag); // use *original* activity
-    let free_text = bag.into_iter().collect::<Vec<_>>().join(" ");
+/// Filters one activity with the default options.
+pub fn filter_activity(activity: &Value) -> Result<Value, serde_json::Error> {
+    filter_activity_with(activity, &FilterOptions::default())
+}
 
-    if !free_text.is_empty() {
-        let meta_val = out
-            .entry("meta".to_string())
-            .or_insert_with(|| Value::Object(Map::new()));
-        if let Value::Object(m) = meta_val {
-            m.insert("results".to_string(), Value::String(free_text));
-        }
+/// Filters one activity, failing if it doesn't fit the typed [`Activity`].
+pub fn filter_activity_with(
+    activity: &Value,
+    options: &FilterOptions,
+) -> Result<Value, serde_json::Error> {
+    let parsed = Activity::from_value(activity)?;
+    let filtered = filter_typed(&parsed, options);
+    serde_json::to_value(&filtered)
+}
+
+/// Downloads today's dump into the snapshot store and returns the path of
+/// the stored zip. With `reuse_download` an existing snapshot from today is
+/// used as is; in `offline` mode the most recent snapshot is used.
+pub fn download_zip(settings: &Settings) -> eyre::Result<PathBuf> {
+    let store = settings.snapshot_store();
+    let today = SnapshotStore::today();
+
+    if settings.offline {
+        let latest = store.latest()?.ok_or_else(|| {
+            eyre::eyre!(
+                "Offline mode is enabled but no snapshot was found in {}.",
+                store.dir.display()
+            )
+        })?;
+        eprintln!("Offline: using snapshot {}", latest.name);
+        return Ok(PathBuf::from(latest.zip_path));
     }
 
-    Value::Object(out)
-}
+    if settings.reuse_download {
+        if let Some(existing) = store.get(&today) {
+            eprintln!("Reusing existing download at {}", existing.zip_path);
+            return Ok(PathBuf::from(existing.zip_path));
+        }
+    }
 
-pub fn download_zip(url: &str) -> eyre::Result<PathBuf> {
     let client = reqwest::blocking::Client::builder()
         .timeout(Duration::from_secs(900))
         .build()?;
 
     // Fetch the content from the URL
-    let response = client.get(url).send()?;
+    let response = client.get(&settings.source_url).send()?;
 
     if !response.status().is_success() {
-        return Err(eyre::eyre!("Failed to fetch the ZIP file from the URL."));
+        return Err(eyre::eyre!(
+            "Failed to fetch the ZIP file from {}.",
+            settings.source_url
+        ));
     }
 
     let bytes = response.bytes()?;
+    let snapshot = store.save(&today, &bytes)?;
 
-    // Create the 'raw_data' directory if it doesn't exist
-    let dir_path = PathBuf::from("raw_data");
-    if !dir_path.exists() {
-        fs::create_dir(&dir_path)?;
-    }
-
-    let zip_path = dir_path.join("mock_iati_data.zip");
-    fs::write(&zip_path, bytes)?;
-
-    Ok(zip_path)
+    Ok(PathBuf::from(snapshot.zip_path))
 }
 
-pub fn extract_zip(zip_path: &Path) -> eyre::Result<String> {
-    let file = File::open(&zip_path)?;
+pub fn extract_zip(zip_path: &Path, settings: &Settings) -> eyre::Result<String> {
+    let file = File::open(zip_path)?;
     let mut archive = ZipArchive::new(file)?;
+    let filter = settings.path_filter()?;
 
-    let extraction_path = PathBuf::from("raw_data");
+    let extraction_path = settings.extraction_path(zip_path);
+    let mut skipped = 0;
     for i in 0..archive.len() {
-        let mut file = archive.by_index(i).unwrap();
-        let outpath = extraction_path.join(file.name());
-
-        if file.name().ends_with('/') {
-            std::fs::create_dir_all(&outpath)?;
-        } else {
-            if let Some(parent) = outpath.parent() {
-                std::fs::create_dir_all(parent)?;
+        let mut file = archive.by_index(i)?;
+
+        // Only accept names that stay inside the extraction directory
+        let relative = match file.enclosed_name() {
+            Some(name) if is_safe_relative(name) => name.to_path_buf(),
+            _ => {
+                return Err(eyre::eyre!(
+                    "Refusing to extract unsafe path '{}' from {}",
+                    file.name(),
+                    zip_path.display()
+                ))
             }
-            let mut outfile = File::create(&outpath)?;
-            std::io::copy(&mut file, &mut outfile)?;
+        };
+
+        if file.is_dir() {
+            std::fs::create_dir_all(extraction_path.join(&relative))?;
+            continue;
+        }
+
+        if !filter.allows(&data_relative(&relative)) {
+            skipped += 1;
+            continue;
         }
+
+        let outpath = extraction_path.join(&relative);
+        if let Some(parent) = outpath.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut outfile = File::create(&outpath)?;
+        std::io::copy(&mut file, &mut outfile)?;
+    }
+
+    if skipped > 0 {
+        eprintln!("Skipped {} archive entries excluded by filters.", skipped);
     }
     Ok(extraction_path.to_string_lossy().into_owned())
 }
@@ -359,289 +582,59 @@ pub fn find_data_folder(base_path: &Path) -> eyre::Result<Option<PathBuf>> {
     Ok(None)
 }
 
-pub fn process_root(zip_path: &Path) -> eyre::Result<String> {
-    let extraction_base_path = extract_zip(&zip_path)?;
-
-    let mut found_root_path = None;
+pub fn process_root(zip_path: &Path, settings: &Settings) -> eyre::Result<String> {
+    let extraction_base_path = extract_zip(zip_path, settings)?;
 
-    // Iterate over directories inside extraction_base_path
-    for entry in std::fs::read_dir(&extraction_base_path)? {
-        let entry = entry?;
-        let path = entry.path();
-
-        if path.is_dir() && path.join("metadata.json").exists() && path.join("data").exists() {
-            found_root_path = Some(path);
-            break;
-        }
+    let source = detect_source(Path::new(&extraction_base_path))?;
+    if source.layout.name() == FlatFolder.name() {
+        return Err(eyre::eyre!(
+            "Couldn't recognise the layout of the extracted data in {}.",
+            extraction_base_path
+        ));
     }
-
-    let root_path = match found_root_path {
-        Some(p) => p,
-        None => {
-            return Err(eyre::eyre!(
-                "Couldn't locate the root directory containing metadata.json and data folder."
-            ))
-        }
-    };
-
-    // Read and pretty print metadata.json content
-    let metadata_path = root_path.join("metadata.json");
-    let mut file = File::open(metadata_path)?;
-    let mut content = String::new();
-    file.read_to_string(&mut content)?;
-
-    // Pretty print the JSON content
-    let json_content: Value = serde_json::from_str(&content)?;
-    let pretty_json = serde_json::to_string_pretty(&json_content)?;
-
-    println!(
-        "\n============== Metadata for Content ==============\n\n{}\n\n===================================================\n",
-        pretty_json
+    eprintln!(
+        "Detected '{}' layout at {}",
+        source.layout.name(),
+        source.root.display()
     );
+    let root_path = source.root.clone();
 
-    // Read and process errors.txt
-    let errors_txt_path = root_path.join("errors.txt");
-    if errors_txt_path.exists() {
-        let errors_file = File::open(&errors_txt_path)
-            .map_err(|e| eyre::eyre!("Failed to open {}: {}", errors_txt_path.display(), e))?;
-        let reader = BufReader::new(errors_file);
-
-        // Collect all lines into a vector
-        let lines: Vec<String> = reader
-            .lines()
-            .collect::<Result<_, io::Error>>()
-            .map_err(|e| {
-                eyre::eyre!(
-                    "Failed to read lines from {}: {}",
-                    errors_txt_path.display(),
-                    e
-                )
-            })?;
-
-        // Take the top 5 errors
-        let top_five = lines.into_iter().take(5).collect::<Vec<String>>();
-
-        // Print the top 5 errors
-        println!("\n================ Top 5 Errors =================\n");
-        for (i, error) in top_five.iter().enumerate() {
-            println!("{}. {}", i + 1, error);
-        }
-        println!("\n===================================================\n");
+    // Read and pretty print metadata.json content, if the layout has one
+    let metadata_path = root_path.join("metadata.json");
+    if metadata_path.is_file() {
+        let mut file = File::open(metadata_path)?;
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+
+        // Pretty print the JSON content
+        let json_content: Value = serde_json::from_str(&content)?;
+        let pretty_json = serde_json::to_string_pretty(&json_content)?;
+
+        eprintln!(
+            "\n============== Metadata for Content ==============\n\n{}\n\n===================================================\n",
+            pretty_json
+        );
     }
-    // Return the path to the `data` folder
-    Ok(root_path.join("data").to_string_lossy().into_owned())
-}
 
-/// Represents an entry in errors.txt
-struct ErrorEntry {
-    folder_title: String,
-    file_name: String,
-    url: String, // Include if needed
+    // Return the path to the `data` folder
+    Ok(source.data_dir().to_string_lossy().into_owned())
 }
 
-pub fn supplement_root_files() -> Result<String> {
-    // Define the path to errors.txt
-    let errors_txt_path = Path::new("./raw_data/iati-data-main/errors.txt");
-
-    if !errors_txt_path.exists() {
-        println!("\n================ No Errors Found =================\n");
-        return Ok("No errors.txt file found.".to_owned());
-    }
-
-    // Open errors.txt
-    let errors_file = File::open(&errors_txt_path)
-        .map_err(|e| eyre::eyre!("Failed to open {}: {}", errors_txt_path.display(), e))?;
-    let reader = BufReader::new(errors_file);
-
-    // Collect all lines into a vector
-    let lines: Vec<String> = reader
-        .lines()
-        .collect::<Result<_, io::Error>>()
-        .map_err(|e| {
-            eyre::eyre!(
-                "Failed to read lines from {}: {}",
-                errors_txt_path.display(),
-                e
-            )
-        })?;
-
-    // Check if there are any lines
-    if lines.is_empty() {
-        println!("\n================ No Errors Found =================\n");
-        return Ok("errors.txt is empty.".to_owned());
-    }
-
-    // Parse lines into ErrorEntry structs
-    let mut error_entries = Vec::new();
-    let mut malformed_lines = 0;
-
-    for (i, line) in lines.iter().enumerate() {
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() < 4 {
-            eprintln!("Line {} is malformed: {}", i + 1, line);
-            malformed_lines += 1;
-            continue;
-        }
-
-        let folder_title = parts[1].to_string();
-        let file_name = parts[2].to_string();
-        let url = parts[3].to_string(); // Use if needed
-
-        error_entries.push(ErrorEntry {
-            folder_title,
-            file_name,
-            url,
-        });
-    }
-
-    let total_lines = lines.len();
-
-    // Define source and destination base paths
-    // let source_base = Path::new("./raw_data/iati-data-main/data"); // Raw data directory: ./raw_data/iati-data-main/data
-    let archive_source_base = Path::new("./archive/iati-data-main/data"); // Archive directory: ./archive/iati-data-main/data
-    let destination_base = Path::new("./raw_data/iati-data-main/data"); // Destination is the raw data directory
-
-    // Initialize storage for files to copy
-    let mut files_to_copy: HashMap<String, Vec<String>> = HashMap::new();
-
-    // Iterate through each ErrorEntry to identify missing or empty files
-    for entry in &error_entries {
-        // Ensure the file name ends with .xml
-        let file_name = if entry.file_name.ends_with(".xml") {
-            entry.file_name.clone()
-        } else {
-            format!("{}.xml", entry.file_name)
-        };
-
-        // Construct the expected file path in the raw data directory
-        let destination_file_path = destination_base.join(&entry.folder_title).join(&file_name);
-
-        let mut needs_copy = false;
-
-        if !destination_file_path.exists() || !destination_file_path.is_file() {
-            // File does not exist or is not a regular file
-            needs_copy = true;
-        } else {
-            // File exists; check if it's empty
-            match fs::metadata(&destination_file_path) {
-                Ok(metadata) => {
-                    if metadata.len() == 0 {
-                        println!(
-                            "⚠️ File '{}' is empty. It will be overwritten.",
-                            destination_file_path.display()
-                        );
-                        needs_copy = true;
-                    }
-                }
-                Err(e) => {
-                    eprintln!(
-                        "❌ Failed to retrieve metadata for '{}': {}",
-                        destination_file_path.display(),
-                        e
-                    );
-                    // Decide whether to treat this as needing copy or skip
-                    needs_copy = true; // Assuming we want to attempt to copy
-                }
-            }
-        }
-
-        if needs_copy {
-            files_to_copy
-                .entry(entry.folder_title.clone())
-                .or_insert_with(Vec::new)
-                .push(file_name.clone());
-        }
-    }
-
-    // If there are no files to copy, inform the user and exit
-    if files_to_copy.is_empty() {
-        println!("\n================ No Missing or Empty Files to Copy =================\n");
-        return Ok("All files are present and non-empty. No copies needed.".to_owned());
-    }
-
-    // Perform copying operations
-    println!("\n===================== Copy Operations =====================\n");
-
-    for (folder_title, file_names) in files_to_copy.iter() {
-        let destination_folder = destination_base.join(folder_title);
-        let archive_folder = archive_source_base.join(folder_title); // Using archive as the source
-
-        // Ensure the destination folder exists
-        if !destination_folder.exists() {
-            println!(
-                "📁 Destination folder '{}' does not exist. Creating...",
-                destination_folder.display()
-            );
-
-            match fs::create_dir_all(&destination_folder) {
-                Ok(_) => {
-                    println!(
-                        "✅ Created destination directory '{}'",
-                        destination_folder.display()
-                    );
-                }
-                Err(e) => {
-                    eprintln!(
-                        "❌ Failed to create destination directory '{}': {}",
-                        destination_folder.display(),
-                        e
-                    );
-                    continue; // Skip copying files in this folder
-                }
-            }
-        }
-
-        // Iterate through each file and perform the copy from archive
-        for file_name in file_names {
-            let source_file_path = archive_folder.join(&file_name); // Source is archive
-            let destination_file_path = destination_folder.join(&file_name);
-
-            // Attempt to copy the file
-            match fs::copy(&source_file_path, &destination_file_path) {
-                Ok(bytes) => {
-                    println!(
-                        "📄 Copied file '{}' ({} bytes) to '{}'",
-                        file_name,
-                        bytes,
-                        destination_file_path.display()
-                    );
-
-                    // Optional: Verify that the copied file matches the source
-                    let source_metadata = fs::metadata(&source_file_path)?;
-                    let destination_metadata = fs::metadata(&destination_file_path)?;
-
-                    if source_metadata.len() == destination_metadata.len() {
-                        println!("✅ Verified that '{}' was copied correctly.", file_name);
-                    } else {
-                        eprintln!(
-                            "⚠️ Size mismatch for '{}'. Source: {} bytes, Destination: {} bytes.",
-                            file_name,
-                            source_metadata.len(),
-                            destination_metadata.len()
-                        );
-                    }
-                }
-                Err(e) => {
-                    eprintln!(
-                        "❌ Failed to copy file '{}' to '{}': {}",
-                        source_file_path.display(),
-                        destination_file_path.display(),
-                        e
-                    );
-                }
-            }
+/// Back-fills files listed in the dump's errors.txt. Each missing or empty
+/// file is tried against the latest earlier snapshot, the dump under
+/// `settings.archive_dir`, then `settings.mirror_dir`, then (with
+/// `settings.refetch`) its original URL.
+/// `data_dir` is the `data` folder returned by `process_root`; errors.txt
+/// sits next to it.
+pub fn supplement_root_files(data_dir: &Path, settings: &Settings) -> Result<HealthReport> {
+    let mut report = read_health(data_dir)?;
+    let chain = supplement_chain(data_dir, settings)?;
+
+    for entry in report.entries.iter_mut() {
+        if entry.status != FileStatus::Present {
+            supplement_entry(entry, data_dir, &chain);
         }
     }
 
-    println!("\n==============================================================\n");
-
-    // Create a summary message
-    let summary = format!(
-        "Checked {} files with {} malformed lines.\nFiles to copy: {}.\nCopy operations completed.",
-        total_lines,
-        malformed_lines,
-        files_to_copy.values().map(|v| v.len()).sum::<usize>()
-    );
-
-    Ok(summary)
+    Ok(report)
 }
@@ -1,92 +1,41 @@
-use pyo3::prelude::*;
-use zip::result;
-
-use std::path::Path;
-
-use indicatif::ProgressBar;
-
-use converter::{
-    convert_xml_to_json, read_and_decode_xml,
-    utils::{count_xml_files, download_zip, process_root, supplement_root_files},
-    walk_and_convert,
-};
-
-use pyo3::{pymodule, types::PyModule, wrap_pyfunction, PyResult, Python};
+/// Declares a struct that is also a pyo3 class with the `python` feature,
+/// exposing the fields marked `#[pyo3(get)]` or `#[pyo3(get, set)]` to Python.
+/// pyo3 0.17 doesn't see field attributes written behind `cfg_attr`, so the
+/// markers are kept or dropped here instead.
+macro_rules! py_struct {
+    (
+        $(#[$meta:meta])*
+        pub struct $name:ident {
+            $(
+                $(#[doc = $doc:literal])*
+                $(#[pyo3($($py:ident),*)])?
+                pub $field:ident: $ty:ty,
+            )*
+        }
+    ) => {
+        #[cfg(feature = "python")]
+        $(#[$meta])*
+        #[pyo3::pyclass]
+        pub struct $name {
+            $(
+                $(#[doc = $doc])*
+                $(#[pyo3($($py),*)])?
+                pub $field: $ty,
+            )*
+        }
+
+        #[cfg(not(feature = "python"))]
+        $(#[$meta])*
+        pub struct $name {
+            $(
+                $(#[doc = $doc])*
+                pub $field: $ty,
+            )*
+        }
+    };
+}
 
 pub mod converter;
 
-
-#[pyfunction]
-pub fn convert(path: Option<String>, pretty: Option<bool>) -> eyre::Result<Option<String>> {
-    let extraction_path: String;
-
-    if let Some(provided_path) = path {
-        extraction_path = provided_path;
-    } else {
-        println!(
-            "Beginning download of source data. This is a large file, timeout set to 15 minutes."
-        );
-        // Download the zip file if no path is provided
-        let zip_path = download_zip("https://data.iati-data-dump.opendataservices.coop/data.zip")?;
-        // "https://gitlab.com/codeforIATI/iati-data/-/archive/main/iati-data-main.zip",
-
-        extraction_path = process_root(&zip_path)?;
-    }
-
-    let p = Path::new(&extraction_path);
-
-    println!("root path is {}", &p.display());
-
-    let supplemented_root_status = supplement_root_files()?;
-    println!("Supplemented root status: {}", supplemented_root_status);
-
-    let total_files = count_xml_files(&p)?;
-
-    println!("total size is: {}", total_files);
-
-    // let result_text = "this is a blank";
-
-    // if result_text.is_empty() {
-    //     Err(eyre::eyre!("Error: String is empty"))
-    // } else {
-    //     Ok(Some(result_text.to_string()))
-    // }
-
-    let mut current_count = 0;
-    let pb = ProgressBar::new(total_files as u64);
-
-    if p.is_dir() {
-        let unified_json = walk_and_convert(&p, &mut current_count, &pb)?;
-
-        let result = if pretty.unwrap_or(false) {
-            serde_json::to_string_pretty(&unified_json)?
-        } else {
-            serde_json::to_string(&unified_json)?
-        };
-
-        Ok(Some(result))
-    } else if p.is_file()
-        && p.extension().map_or(true, |ext| ext != "xml")
-        && !extraction_path.ends_with("-org.xml")
-    {
-        let xml_content = read_and_decode_xml(&p)?;
-
-        let json = convert_xml_to_json(&xml_content)?;
-
-        let result = if pretty.unwrap_or(false) {
-            serde_json::to_string_pretty(&json)?
-        } else {
-            serde_json::to_string(&json)?
-        };
-
-        Ok(Some(result))
-    } else {
-        Err(eyre::eyre!("Invalid path provided. Ensure it's either an XML file or a directory containing XML files."))
-    }
-}
-
-#[pymodule]
-fn iati_json_parser(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
-    m.add_function(wrap_pyfunction!(convert, m)?)?;
-    Ok(())
-}
+#[cfg(feature = "python")]
+mod python;
@@ -0,0 +1,371 @@
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::converter::model::{
+    Code, FilteredActivity, FilteredBudget, FilteredTransaction, LangText, ParticipatingOrg,
+    ReportingOrg,
+};
+use crate::converter::rollup::Totals;
+
+fn text(code: &Option<Code>) -> Option<String> {
+    code.as_ref()?.as_string()
+}
+
+/// The preferred-language string, or the first language's text.
+fn lang_text(text: &Option<LangText>) -> Option<String> {
+    Some(text.as_ref()?.text()?.to_owned())
+}
+
+/// Python-style repr of an optional value.
+fn repr<T: std::fmt::Display>(value: &Option<T>) -> String {
+    match value {
+        Some(v) => v.to_string(),
+        None => "None".to_owned(),
+    }
+}
+
+fn json_to_py(py: Python<'_>, json: &str) -> PyResult<PyObject> {
+    Ok(py.import("json")?.call_method1("loads", (json,))?.into())
+}
+
+/// An organisation named on an activity: the reporting org or a participant.
+#[pyclass(name = "Organisation")]
+#[derive(Clone)]
+pub struct PyOrganisation {
+    pub reference: Option<String>,
+    #[pyo3(get)]
+    pub name: Option<String>,
+    pub kind: Option<String>,
+    /// Role code for participating orgs, e.g. `"1"` for funding; `None` for the reporting org.
+    #[pyo3(get)]
+    pub role: Option<String>,
+    /// Role name, e.g. `"Funding"`.
+    #[pyo3(get)]
+    pub role_name: Option<String>,
+    #[pyo3(get)]
+    pub activity_id: Option<String>,
+    /// Registration agency prefix of `ref`, e.g. `"GB-COH"`.
+    #[pyo3(get)]
+    pub prefix: Option<String>,
+    /// The part of `ref` after the agency prefix.
+    #[pyo3(get)]
+    pub local_id: Option<String>,
+    /// Problems with `ref`, e.g. `["placeholder"]`; empty when it looks right.
+    #[pyo3(get)]
+    pub ref_issues: Vec<String>,
+}
+
+#[pymethods]
+impl PyOrganisation {
+    /// Organisation identifier, e.g. `GB-GOV-1`.
+    #[getter]
+    fn r#ref(&self) -> Option<String> {
+        self.reference.clone()
+    }
+
+    /// Organisation type code, e.g. `"10"` for government.
+    #[getter]
+    fn r#type(&self) -> Option<String> {
+        self.kind.clone()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Organisation({}, {})",
+            self.reference.as_deref().unwrap_or("-"),
+            self.name.as_deref().unwrap_or("-")
+        )
+    }
+}
+
+impl From<&ReportingOrg> for PyOrganisation {
+    fn from(org: &ReportingOrg) -> Self {
+        PyOrganisation {
+            reference: text(&org.id),
+            name: lang_text(&org.name),
+            kind: text(&org.kind),
+            role: None,
+            role_name: None,
+            activity_id: None,
+            prefix: None,
+            local_id: None,
+            ref_issues: Vec::new(),
+        }
+    }
+}
+
+impl From<&ParticipatingOrg> for PyOrganisation {
+    fn from(org: &ParticipatingOrg) -> Self {
+        let org_id = org.org_id.clone().unwrap_or_default();
+        PyOrganisation {
+            reference: text(&org.reference),
+            name: lang_text(&org.name),
+            kind: text(&org.kind),
+            role: text(&org.role),
+            role_name: org.role_name.clone(),
+            activity_id: text(&org.activity_id),
+            prefix: org_id.prefix,
+            local_id: org_id.local_id,
+            ref_issues: org_id.issues.iter().map(|i| i.name().to_owned()).collect(),
+        }
+    }
+}
+
+#[pyclass(name = "Transaction")]
+#[derive(Clone)]
+pub struct PyTransaction {
+    /// IATI 2.x transaction type code, e.g. `3` for a disbursement.
+    #[pyo3(get)]
+    pub transaction_type: Option<u8>,
+    #[pyo3(get)]
+    pub date: Option<String>,
+    #[pyo3(get)]
+    pub value: Option<f64>,
+    /// The transaction's own currency; `None` means the activity's default.
+    #[pyo3(get)]
+    pub currency: Option<String>,
+}
+
+#[pymethods]
+impl PyTransaction {
+    fn __repr__(&self) -> String {
+        format!(
+            "Transaction(type={}, date={}, value={})",
+            repr(&self.transaction_type),
+            repr(&self.date),
+            repr(&self.value)
+        )
+    }
+}
+
+impl From<&FilteredTransaction> for PyTransaction {
+    fn from(t: &FilteredTransaction) -> Self {
+        PyTransaction {
+            transaction_type: t.type_code(),
+            date: t.transaction_date.as_string(),
+            value: t.transaction_value.as_f64(),
+            currency: text(&t.transaction_currency),
+        }
+    }
+}
+
+#[pyclass(name = "Budget")]
+#[derive(Clone)]
+pub struct PyBudget {
+    /// `"1"` original or `"2"` revised.
+    #[pyo3(get)]
+    pub budget_type: Option<String>,
+    /// `"1"` indicative or `"2"` committed.
+    #[pyo3(get)]
+    pub status: Option<String>,
+    #[pyo3(get)]
+    pub period_start: Option<String>,
+    #[pyo3(get)]
+    pub period_end: Option<String>,
+    #[pyo3(get)]
+    pub value: Option<f64>,
+    #[pyo3(get)]
+    pub currency: Option<String>,
+}
+
+#[pymethods]
+impl PyBudget {
+    fn __repr__(&self) -> String {
+        format!(
+            "Budget({} to {}, value={})",
+            repr(&self.period_start),
+            repr(&self.period_end),
+            repr(&self.value)
+        )
+    }
+}
+
+impl From<&FilteredBudget> for PyBudget {
+    fn from(b: &FilteredBudget) -> Self {
+        PyBudget {
+            budget_type: text(&b.budget_type),
+            status: text(&b.budget_status),
+            period_start: text(&b.period_start),
+            period_end: text(&b.period_end),
+            value: b.budget_value.as_f64(),
+            currency: text(&b.budget_currency),
+        }
+    }
+}
+
+/// One converted activity, as returned by `activities`.
+#[pyclass(name = "Activity")]
+#[derive(Clone)]
+pub struct PyActivity {
+    pub record: FilteredActivity,
+}
+
+impl PyActivity {
+    /// A total from `meta.totals`, or `fallback` for records converted before
+    /// it existed.
+    fn total(&self, pick: impl Fn(&Totals) -> f64, fallback: impl Fn() -> f64) -> f64 {
+        self.record.totals().map(pick).unwrap_or_else(fallback)
+    }
+}
+
+#[pymethods]
+impl PyActivity {
+    #[getter]
+    fn iati_identifier(&self) -> Option<String> {
+        self.record.identifier()
+    }
+
+    /// The title in the preferred language when converted with one,
+    /// otherwise in the first language alphabetically.
+    #[getter]
+    fn title(&self) -> Option<String> {
+        lang_text(&self.record.title)
+    }
+
+    /// Descriptions, picked by language as `title` is, in document order.
+    #[getter]
+    fn descriptions(&self) -> Vec<String> {
+        self.record
+            .description
+            .iter()
+            .flatten()
+            .filter_map(|d| Some(d.narrative.text()?.to_owned()))
+            .collect()
+    }
+
+    #[getter]
+    fn default_currency(&self) -> Option<String> {
+        text(&self.record.default_currency)
+    }
+
+    #[getter]
+    fn last_updated_datetime(&self) -> Option<String> {
+        text(&self.record.last_updated_datetime)
+    }
+
+    #[getter]
+    fn activity_status(&self) -> Option<String> {
+        let status = self.record.activity_status.as_ref()?.parsed()?;
+        text(&status.code)
+    }
+
+    #[getter]
+    fn reporting_org(&self) -> Option<PyOrganisation> {
+        self.record.reporting_org.as_ref().map(PyOrganisation::from)
+    }
+
+    #[getter]
+    fn participating_orgs(&self) -> Vec<PyOrganisation> {
+        self.record
+            .participating_orgs()
+            .map(PyOrganisation::from)
+            .collect()
+    }
+
+    #[getter]
+    fn transactions(&self) -> Vec<PyTransaction> {
+        self.record
+            .transactions()
+            .map(PyTransaction::from)
+            .collect()
+    }
+
+    #[getter]
+    fn budgets(&self) -> Vec<PyBudget> {
+        self.record.budgets().map(PyBudget::from).collect()
+    }
+
+    #[getter]
+    fn recipient_countries(&self) -> Vec<String> {
+        self.record
+            .recipient_countries()
+            .filter_map(|c| text(&c.code))
+            .collect()
+    }
+
+    /// DAC 5-digit sector codes, highest percentage first.
+    #[getter]
+    fn sectors(&self) -> Vec<String> {
+        self.record
+            .dac_sectors()
+            .filter_map(|s| text(&s.dac_code))
+            .collect()
+    }
+
+    /// Where the activity was read from (`_source`), as a dict.
+    #[getter]
+    fn source(&self, py: Python<'_>) -> PyResult<Option<PyObject>> {
+        self.record
+            .extra
+            .get("_source")
+            .map(|s| json_to_py(py, &s.to_string()))
+            .transpose()
+    }
+
+    /// Transaction and budget totals in the activity currency
+    /// (`meta.totals`), as a dict.
+    #[getter]
+    fn totals(&self, py: Python<'_>) -> PyResult<Option<PyObject>> {
+        self.record
+            .totals()
+            .map(|t| json_to_py(py, &serde_json::to_string(t).unwrap_or_default()))
+            .transpose()
+    }
+
+    /// Total commitments (type 2) in the activity currency.
+    fn total_committed(&self) -> f64 {
+        self.total(|t| t.commitment.total, || self.record.transaction_total(2))
+    }
+
+    /// Total disbursements (type 3) in the activity currency.
+    fn total_disbursed(&self) -> f64 {
+        self.total(|t| t.disbursement.total, || self.record.transaction_total(3))
+    }
+
+    /// Total expenditure (type 4) in the activity currency.
+    fn total_expenditure(&self) -> f64 {
+        self.total(|t| t.expenditure.total, || self.record.transaction_total(4))
+    }
+
+    /// Total incoming funds (type 1) in the activity currency.
+    fn total_incoming_funds(&self) -> f64 {
+        self.total(|t| t.incoming_funds.total, || self.record.transaction_total(1))
+    }
+
+    /// Total budget in the activity currency, counting revised budgets over originals.
+    fn total_budget(&self) -> f64 {
+        self.total(|t| t.budget.total, || self.record.budget_total())
+    }
+
+    /// The activity as the dict `convert` would have returned for it.
+    fn to_dict(&self, py: Python<'_>) -> PyResult<PyObject> {
+        json_to_py(py, &self.to_json()?)
+    }
+
+    fn to_json(&self) -> PyResult<String> {
+        serde_json::to_string(&self.record)
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))
+    }
+
+    /// Builds an activity from one record returned by `convert`.
+    #[staticmethod]
+    fn from_dict(py: Python<'_>, record: &PyDict) -> PyResult<PyActivity> {
+        let json: String = py
+            .import("json")?
+            .call_method1("dumps", (record,))?
+            .extract()?;
+        let value: serde_json::Value = serde_json::from_str(&json)
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))?;
+        FilteredActivity::from_value(&value)
+            .map(|record| PyActivity { record })
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Activity({}, {:?})",
+            self.iati_identifier().as_deref().unwrap_or("-"),
+            self.title().unwrap_or_default()
+        )
+    }
+}
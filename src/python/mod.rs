@@ -0,0 +1,318 @@
+//! Python bindings, built with the `python` feature.
+
+use pyo3::prelude::*;
+
+#[cfg(feature = "index")]
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use self::activity::{PyActivity, PyBudget, PyOrganisation, PyTransaction};
+
+#[cfg(feature = "index")]
+use crate::converter::index;
+use crate::converter::{
+    allocation::{dump_allocations, AllocationOptions},
+    diff::diff_activities,
+    feed::ChangeFeed,
+    geojson::feature_collection,
+    health::{read_health, ErrorEntry, HealthReport},
+    layout::detect_source,
+    pipeline::{convert_to_value, download_source, load_records, prepare_zip, to_output},
+    settings::Settings,
+    model::FilteredActivity,
+    organisations::dump_organisations,
+    snapshot::Snapshot,
+    stats::dump_stats,
+    utils::supplement_root_files,
+};
+
+use pyo3::{pymodule, types::PyModule, wrap_pyfunction, PyResult, Python};
+
+mod activity;
+
+/// A change feed manifest returned by `convert`, written by `commit()` once
+/// the converted JSON has been stored.
+#[pyclass]
+pub struct PendingManifest {
+    feed: Option<ChangeFeed>,
+}
+
+#[pymethods]
+impl PendingManifest {
+    /// Writes the manifest. Later calls do nothing.
+    fn commit(&mut self) -> eyre::Result<()> {
+        match self.feed.take() {
+            Some(feed) => feed.commit(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Converts a file, folder or zip, downloading the dump when `path` is
+/// `None`. With a `manifest`, the manifest comes back uncommitted.
+#[pyfunction]
+pub fn convert(
+    path: Option<String>,
+    pretty: Option<bool>,
+    settings: Option<Settings>,
+    since: Option<String>,
+    manifest: Option<String>,
+) -> eyre::Result<(Option<String>, Option<PendingManifest>)> {
+    let settings = settings.unwrap_or_default();
+    let feed = match (&since, &manifest) {
+        (None, None) => None,
+        _ => Some(ChangeFeed::new(since.as_deref(), manifest.as_deref())?),
+    };
+
+    let extraction_path = match path {
+        Some(provided_path) => provided_path,
+        None => {
+            // Download the zip file if no path is provided
+            download_source(&settings)?
+        }
+    };
+
+    let (value, feed) = convert_to_value(&extraction_path, &settings, feed)?;
+    let pending = feed
+        .filter(|f| f.manifest.is_some())
+        .map(|feed| PendingManifest { feed: Some(feed) });
+    Ok((Some(to_output(&value, pretty)?), pending))
+}
+
+/// Converts a snapshot from the store by name (see `list_snapshots`).
+#[pyfunction]
+pub fn convert_snapshot(
+    name: String,
+    pretty: Option<bool>,
+    settings: Option<Settings>,
+) -> eyre::Result<Option<String>> {
+    let settings = settings.unwrap_or_default();
+    let snapshot = settings
+        .snapshot_store()
+        .get(&name)
+        .ok_or_else(|| eyre::eyre!("No snapshot named '{}'.", name))?;
+
+    let extraction_path = prepare_zip(Path::new(&snapshot.zip_path), &settings)?;
+    let (value, _) = convert_to_value(&extraction_path, &settings, None)?;
+    Ok(Some(to_output(&value, pretty)?))
+}
+
+/// Snapshots in the store, oldest first.
+#[pyfunction]
+pub fn list_snapshots(settings: Option<Settings>) -> eyre::Result<Vec<Snapshot>> {
+    settings.unwrap_or_default().snapshot_store().list()
+}
+
+/// Deletes all but the `keep` most recent snapshots, along with their
+/// extracted copies, and returns the names removed.
+#[pyfunction]
+pub fn prune_snapshots(keep: usize, settings: Option<Settings>) -> eyre::Result<Vec<String>> {
+    let settings = settings.unwrap_or_default();
+    let removed = settings.snapshot_store().prune(keep)?;
+
+    for name in &removed {
+        let extracted = settings.cache_path().join(name);
+        if extracted.is_dir() {
+            std::fs::remove_dir_all(extracted)?;
+        }
+    }
+    Ok(removed)
+}
+
+/// Compares two conversions and reports activities added, removed and
+/// changed, keyed by `iati-identifier`. Each side may be a JSON file written
+/// by `convert`, a snapshot name, or any path `convert` accepts.
+#[pyfunction]
+pub fn diff(
+    old: String,
+    new: String,
+    pretty: Option<bool>,
+    settings: Option<Settings>,
+) -> eyre::Result<String> {
+    let settings = settings.unwrap_or_default();
+    let old_records = load_records(&old, &settings)?;
+    let new_records = load_records(&new, &settings)?;
+
+    let report = diff_activities(&old_records, &new_records);
+    eprintln!(
+        "Diff: {} added, {} removed, {} changed, {} unchanged",
+        report.summary.added,
+        report.summary.removed,
+        report.summary.changed,
+        report.summary.unchanged
+    );
+
+    to_output(&serde_json::to_value(&report)?, pretty)
+}
+
+/// Counts and transaction sums over a dump, computed file by file in parallel.
+/// `path` accepts the same inputs as `activities`.
+#[pyfunction]
+pub fn stats(
+    path: String,
+    pretty: Option<bool>,
+    settings: Option<Settings>,
+) -> eyre::Result<String> {
+    let settings = settings.unwrap_or_default();
+    let stats = dump_stats(&path, &settings)?;
+    eprintln!(
+        "Stats: {} files, {} failed, {} activities",
+        stats.files, stats.files_failed, stats.activities
+    );
+    to_output(&serde_json::to_value(&stats)?, pretty)
+}
+
+/// Transaction values split by sector and recipient country or region, one
+/// row per share. `path` accepts a dump folder, zip, XML file or snapshot name.
+#[pyfunction]
+pub fn allocations(
+    path: String,
+    sector_vocabulary: Option<String>,
+    region_vocabulary: Option<String>,
+    pretty: Option<bool>,
+    settings: Option<Settings>,
+) -> eyre::Result<String> {
+    let settings = settings.unwrap_or_default();
+    let defaults = AllocationOptions::default();
+    let options = AllocationOptions {
+        sector_vocabulary: sector_vocabulary.unwrap_or(defaults.sector_vocabulary),
+        region_vocabulary: region_vocabulary.unwrap_or(defaults.region_vocabulary),
+    };
+    let rows = dump_allocations(&path, &settings, &options)?;
+    to_output(&serde_json::to_value(&rows)?, pretty)
+}
+
+/// Organisations named across a dump as reporting, participating, provider
+/// or receiver orgs, one row per organisation merged by ref. `path` accepts a
+/// dump folder, zip, XML file or snapshot name.
+#[pyfunction]
+pub fn organisations(
+    path: String,
+    pretty: Option<bool>,
+    settings: Option<Settings>,
+) -> eyre::Result<String> {
+    let settings = settings.unwrap_or_default();
+    let orgs = dump_organisations(&path, &settings)?;
+    eprintln!("Organisations: {}", orgs.len());
+    to_output(&serde_json::to_value(&orgs)?, pretty)
+}
+
+/// Activity locations as a GeoJSON FeatureCollection. `path` may be a JSON
+/// file from `convert`, a snapshot name, or anything `convert` accepts.
+#[pyfunction]
+pub fn geojson(
+    path: String,
+    pretty: Option<bool>,
+    settings: Option<Settings>,
+) -> eyre::Result<String> {
+    let settings = settings.unwrap_or_default();
+    let records = load_records(&path, &settings)?;
+    to_output(&feature_collection(&records), pretty)
+}
+
+/// Converted activities as `Activity` objects. `path` may be a JSON file
+/// written by `convert`, a snapshot name, or any path `convert` accepts.
+#[pyfunction]
+pub fn activities(path: String, settings: Option<Settings>) -> eyre::Result<Vec<PyActivity>> {
+    let settings = settings.unwrap_or_default();
+    load_records(&path, &settings)?
+        .iter()
+        .enumerate()
+        .map(|(i, record)| {
+            FilteredActivity::from_value(record)
+                .map(|record| PyActivity { record })
+                .map_err(|e| eyre::eyre!("Record {} isn't an activity: {}", i, e))
+        })
+        .collect()
+}
+
+/// Builds a full-text index of the activities at `path` in `index_dir`,
+/// replacing any earlier index there. `path` accepts the same inputs as
+/// `activities`. Returns the number of activities indexed.
+#[cfg(feature = "index")]
+#[pyfunction]
+pub fn build_index(
+    path: String,
+    index_dir: String,
+    settings: Option<Settings>,
+) -> eyre::Result<usize> {
+    let settings = settings.unwrap_or_default();
+    let count = index::build_index(&path, &settings, Path::new(&index_dir))?;
+    eprintln!("Indexed {} activities in {}", count, index_dir);
+    Ok(count)
+}
+
+/// Searches an index built by `build_index`, returning the best `limit`
+/// matches as JSON. `filters` maps `country`, `sector`, `publisher` or
+/// `status` to the codes to keep.
+#[cfg(feature = "index")]
+#[pyfunction]
+pub fn search(
+    index_dir: String,
+    query: String,
+    filters: Option<BTreeMap<String, Vec<String>>>,
+    limit: Option<usize>,
+    pretty: Option<bool>,
+    settings: Option<Settings>,
+) -> eyre::Result<String> {
+    let settings = settings.unwrap_or_default();
+    let filters = index::SearchFilters::from_map(filters.unwrap_or_default())?;
+    let hits = index::search_index(
+        Path::new(&index_dir),
+        &query,
+        &filters,
+        &settings.search_weights,
+        limit.unwrap_or(20),
+    )?;
+    to_output(&serde_json::to_value(&hits)?, pretty)
+}
+
+/// Reads errors.txt for a dump and reports which listed datasets are present,
+/// supplemented or missing. With `supplement`, missing files are back-filled
+/// from the archive first, as `convert` does.
+#[pyfunction]
+pub fn dump_health(
+    path: Option<String>,
+    settings: Option<Settings>,
+    supplement: Option<bool>,
+) -> eyre::Result<HealthReport> {
+    let settings = settings.unwrap_or_default();
+    let root = path.map(PathBuf::from).unwrap_or_else(|| settings.cache_path());
+    let data_dir = detect_source(&root)?.data_dir();
+
+    if supplement.unwrap_or(false) {
+        supplement_root_files(&data_dir, &settings)
+    } else {
+        read_health(&data_dir)
+    }
+}
+
+#[pymodule]
+fn iati_json_parser(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(convert, m)?)?;
+    m.add_function(wrap_pyfunction!(convert_snapshot, m)?)?;
+    m.add_function(wrap_pyfunction!(list_snapshots, m)?)?;
+    m.add_function(wrap_pyfunction!(prune_snapshots, m)?)?;
+    m.add_function(wrap_pyfunction!(diff, m)?)?;
+    m.add_function(wrap_pyfunction!(dump_health, m)?)?;
+    m.add_function(wrap_pyfunction!(activities, m)?)?;
+    m.add_function(wrap_pyfunction!(stats, m)?)?;
+    m.add_function(wrap_pyfunction!(allocations, m)?)?;
+    m.add_function(wrap_pyfunction!(organisations, m)?)?;
+    m.add_function(wrap_pyfunction!(geojson, m)?)?;
+    #[cfg(feature = "index")]
+    {
+        m.add_function(wrap_pyfunction!(build_index, m)?)?;
+        m.add_function(wrap_pyfunction!(search, m)?)?;
+    }
+    m.add_class::<Settings>()?;
+    m.add_class::<Snapshot>()?;
+    m.add_class::<PendingManifest>()?;
+    m.add_class::<HealthReport>()?;
+    m.add_class::<ErrorEntry>()?;
+    m.add_class::<PyActivity>()?;
+    m.add_class::<PyTransaction>()?;
+    m.add_class::<PyBudget>()?;
+    m.add_class::<PyOrganisation>()?;
+    Ok(())
+}
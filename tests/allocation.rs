@@ -0,0 +1,149 @@
+use iati_json_parser::converter::allocation::{allocate, dump_allocations, AllocationOptions};
+use iati_json_parser::converter::convert_xml_to_json;
+use iati_json_parser::converter::model::Activity;
+use iati_json_parser::converter::settings::Settings;
+
+const DUMP: &str = "tests/fixtures/ods-dump";
+
+fn activity(xml: &str) -> Activity {
+    let json = convert_xml_to_json(&xml.to_owned()).unwrap();
+    Activity::from_value(&json["iati-activities"]["iati-activity"]).unwrap()
+}
+
+#[test]
+fn activity_percentages_split_each_transaction() {
+    let rows = dump_allocations(DUMP, &Settings::default(), &AllocationOptions::default()).unwrap();
+
+    let commitment: Vec<_> = rows
+        .iter()
+        .filter(|r| r.iati_identifier.as_deref() == Some("XM-1-A1"))
+        .filter(|r| r.transaction_type == Some(2))
+        .map(|r| {
+            (
+                r.sector.as_deref().unwrap(),
+                r.recipient.as_deref().unwrap(),
+                r.value,
+            )
+        })
+        .collect();
+    // The SDG (vocabulary 7) sector is left out of the DAC split.
+    assert_eq!(
+        commitment,
+        [
+            ("14030", "KE", 420.0),
+            ("14030", "UG", 280.0),
+            ("12220", "KE", 180.0),
+            ("12220", "UG", 120.0),
+        ]
+    );
+    let a1 = rows
+        .iter()
+        .find(|r| r.iati_identifier.as_deref() == Some("XM-1-A1"))
+        .unwrap();
+    assert_eq!(a1.publisher.as_deref(), Some("pubA"));
+    assert_eq!(a1.currency.as_deref(), Some("USD"));
+    assert_eq!(a1.sector_from, Some("activity"));
+
+    // No sectors: one row keeps the whole value against the region.
+    let b1: Vec<_> = rows
+        .iter()
+        .filter(|r| r.iati_identifier.as_deref() == Some("GB-2-B1"))
+        .collect();
+    assert_eq!(b1.len(), 1);
+    assert_eq!(b1[0].sector, None);
+    assert_eq!(b1[0].recipient_type, Some("region"));
+    assert_eq!(b1[0].recipient.as_deref(), Some("298"));
+    assert_eq!(b1[0].value, 200.0);
+}
+
+#[test]
+fn transaction_sector_and_country_override_the_activity() {
+    let activity = activity(
+        r#"<iati-activities version="2.03"><iati-activity default-currency="EUR">
+<iati-identifier>XM-ALLOC-1</iati-identifier>
+<recipient-country code="KE" percentage="50"/>
+<recipient-region code="298" vocabulary="1" percentage="50"/>
+<recipient-region code="145" vocabulary="2" percentage="100"/>
+<sector code="11110" percentage="100"/>
+<transaction><transaction-type code="3"/><transaction-date iso-date="2024-01-01"/>
+<value value-date="2024-01-01">90</value>
+<sector code="12220"/><sector code="12230"/><sector code="1.1" vocabulary="7"/>
+<recipient-country code="UG"/></transaction>
+<transaction><transaction-type code="3"/><transaction-date iso-date="2024-02-01"/>
+<value value-date="2024-02-01">100</value></transaction>
+</iati-activity></iati-activities>"#,
+    );
+    let rows = allocate(&activity, &AllocationOptions::default());
+
+    let split: Vec<_> = rows
+        .iter()
+        .map(|r| {
+            (
+                r.transaction_date.as_deref().unwrap(),
+                r.sector.as_deref().unwrap(),
+                r.recipient.as_deref().unwrap(),
+                r.value,
+            )
+        })
+        .collect();
+    // Vocabulary 2 regions don't share the activity's 100% with countries.
+    assert_eq!(
+        split,
+        [
+            ("2024-01-01", "12220", "UG", 45.0),
+            ("2024-01-01", "12230", "UG", 45.0),
+            ("2024-02-01", "11110", "KE", 50.0),
+            ("2024-02-01", "11110", "298", 50.0),
+        ]
+    );
+    assert_eq!(rows[0].sector_from, Some("transaction"));
+    assert_eq!(rows[0].recipient_from, Some("transaction"));
+    assert_eq!(rows[2].sector_from, Some("activity"));
+    assert_eq!(rows[3].recipient_type, Some("region"));
+    assert!(rows.iter().all(|r| r.currency.as_deref() == Some("EUR")));
+
+    let regions = AllocationOptions {
+        region_vocabulary: "2".to_owned(),
+        ..Default::default()
+    };
+    let shares: Vec<_> = allocate(&activity, &regions)
+        .iter()
+        .filter(|r| r.transaction_date.as_deref() == Some("2024-02-01"))
+        .map(|r| (r.recipient.clone().unwrap(), r.share))
+        .collect();
+    // KE's 50 against region 145's 100: scaled to add up to the whole value.
+    assert_eq!(
+        shares,
+        [("KE".to_owned(), 0.333333), ("145".to_owned(), 0.666667)]
+    );
+}
+
+#[test]
+fn sector_vocabulary_names_match_their_codes() {
+    let activity = activity(
+        r#"<iati-activities version="1.05"><iati-activity default-currency="EUR">
+<iati-identifier>XM-ALLOC-2</iati-identifier>
+<recipient-country code="KE"/>
+<sector code="11110" vocabulary="DAC" percentage="100"/>
+<sector code="111" vocabulary="DAC-3" percentage="100"/>
+<transaction><transaction-type code="D"/><transaction-date iso-date="2012-01-01"/>
+<value value-date="2012-01-01">10</value></transaction>
+</iati-activity></iati-activities>"#,
+    );
+
+    for (option, sector, vocabulary) in [
+        ("DAC", "11110", "1"),
+        ("1", "11110", "1"),
+        ("DAC-3", "111", "2"),
+        ("2", "111", "2"),
+    ] {
+        let options = AllocationOptions {
+            sector_vocabulary: option.to_owned(),
+            ..Default::default()
+        };
+        let rows = allocate(&activity, &options);
+        assert_eq!(rows.len(), 1, "{}", option);
+        assert_eq!(rows[0].sector.as_deref(), Some(sector), "{}", option);
+        assert_eq!(rows[0].sector_vocabulary.as_deref(), Some(vocabulary), "{}", option);
+    }
+}
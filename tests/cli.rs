@@ -0,0 +1,98 @@
+use serde_json::Value;
+
+use std::fs;
+use std::process::{Command, Output};
+
+mod common;
+
+use common::DUMP;
+
+fn iati_json(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_iati-json"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout_json(output: &Output) -> Value {
+    assert!(output.status.success(), "{:?}", output);
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn convert_writes_selected_fields_as_json_or_lines() {
+    let records = stdout_json(&iati_json(&[
+        "--deterministic",
+        "convert",
+        DUMP,
+        "--fields",
+        "iati-identifier",
+    ]));
+    assert_eq!(
+        records,
+        serde_json::json!([
+            {"iati-identifier": "XM-1-A1"},
+            {"iati-identifier": "XM-1-A2"},
+            {"iati-identifier": "GB-2-B1"}
+        ])
+    );
+
+    let output = iati_json(&["convert", DUMP, "--format", "jsonl"]);
+    assert!(output.status.success());
+    let lines = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(lines.lines().count(), 3);
+    for line in lines.lines() {
+        serde_json::from_str::<Value>(line).unwrap();
+    }
+}
+
+#[test]
+fn validate_fails_on_broken_files() {
+    assert!(iati_json(&["validate", DUMP]).status.success());
+
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("pub")).unwrap();
+    fs::write(
+        dir.path().join("pub/broken.xml"),
+        "<!DOCTYPE html><html></html>",
+    )
+    .unwrap();
+    let output = iati_json(&["validate", dir.path().to_str().unwrap()]);
+    assert!(!output.status.success());
+    let failure: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(failure["path"].as_str().unwrap().ends_with("broken.xml"));
+}
+
+#[test]
+fn find_prints_matching_activities() {
+    let found = stdout_json(&iati_json(&["find", DUMP, "GB-2-B1"]));
+    assert_eq!(found.as_array().unwrap().len(), 1);
+    assert_eq!(found[0]["_source"]["publisher"], "pubB");
+}
+
+#[test]
+fn find_matches_numeric_identifiers() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("pub")).unwrap();
+    fs::write(
+        dir.path().join("pub/numeric.xml"),
+        "<iati-activities><iati-activity><iati-identifier> 12345 </iati-identifier></iati-activity></iati-activities>",
+    )
+    .unwrap();
+
+    let found = stdout_json(&iati_json(&["find", dir.path().to_str().unwrap(), "12345"]));
+    assert_eq!(found.as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn convert_gives_filtered_records_for_a_single_file() {
+    let file = format!("{}/data/pubA/a.xml", DUMP);
+    let records = stdout_json(&iati_json(&["convert", &file]));
+    let records = records.as_array().unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["_source"]["publisher"], "pubA");
+    assert!(records[0]["meta"].is_object());
+
+    let found = stdout_json(&iati_json(&["find", &file, "XM-1-A2"]));
+    assert_eq!(found[0]["iati-identifier"], "XM-1-A2");
+}
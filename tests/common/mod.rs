@@ -0,0 +1,40 @@
+//! Fixtures shared by the integration tests.
+#![allow(dead_code)]
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub const DUMP: &str = "tests/fixtures/ods-dump";
+
+/// Writes a zip of `entries`, each a member name and its contents, in order.
+pub fn write_zip(zip_path: &Path, entries: &[(&str, &[u8])]) {
+    let mut zip = zip::ZipWriter::new(fs::File::create(zip_path).unwrap());
+    let options = zip::write::FileOptions::default();
+    for (name, contents) in entries {
+        zip.start_file(*name, options).unwrap();
+        zip.write_all(contents).unwrap();
+    }
+    zip.finish().unwrap();
+}
+
+/// The fixture dump's files `names` (such as `pubA/a.xml`) zipped as
+/// codeforIATI lays them out, under `iati-data-main/data/`, as `dir/dump.zip`.
+pub fn dump_zip(dir: &Path, names: &[&str]) -> PathBuf {
+    let zip_path = dir.join("dump.zip");
+    let files: Vec<(String, Vec<u8>)> = names
+        .iter()
+        .map(|name| {
+            (
+                format!("iati-data-main/data/{}", name),
+                fs::read(Path::new(DUMP).join("data").join(name)).unwrap(),
+            )
+        })
+        .collect();
+    let entries: Vec<(&str, &[u8])> = files
+        .iter()
+        .map(|(name, contents)| (name.as_str(), contents.as_slice()))
+        .collect();
+    write_zip(&zip_path, &entries);
+    zip_path
+}
@@ -0,0 +1,147 @@
+use iati_json_parser::converter::settings::Settings;
+use iati_json_parser::converter::{convert_dir, convert_file, Converter};
+use serde_json::Value;
+
+use std::fs;
+use std::path::Path;
+
+mod common;
+
+use common::{write_zip, DUMP};
+
+fn identifiers(records: &[Value]) -> Vec<String> {
+    let mut ids: Vec<String> = records
+        .iter()
+        .filter_map(|r| r["iati-identifier"].as_str().map(str::to_owned))
+        .collect();
+    ids.sort();
+    ids
+}
+
+#[test]
+fn convert_file_tags_activities_with_source() {
+    let records = convert_file(Path::new(DUMP).join("data/pubA/a.xml")).unwrap();
+
+    assert_eq!(identifiers(&records), ["XM-1-A1", "XM-1-A2"]);
+    assert_eq!(records[0]["_source"]["publisher"], "pubA");
+    assert_eq!(records[0]["_source"]["dataset"], "a");
+}
+
+#[test]
+fn convert_dir_reads_every_publisher() {
+    let records = convert_dir(DUMP).unwrap();
+
+    assert_eq!(identifiers(&records), ["GB-2-B1", "XM-1-A1", "XM-1-A2"]);
+    let b1 = records
+        .iter()
+        .find(|r| r["iati-identifier"] == "GB-2-B1")
+        .unwrap();
+    assert_eq!(b1["_source"]["path"], "pubB/b.xml");
+}
+
+#[test]
+fn builder_applies_filters_and_since() {
+    let converter = Converter::builder().include("pubA").build().unwrap();
+    assert_eq!(
+        identifiers(&converter.convert_dir(DUMP).unwrap()),
+        ["XM-1-A1", "XM-1-A2"]
+    );
+
+    let converter = Converter::builder().since("2024-01-01").build().unwrap();
+    assert_eq!(
+        identifiers(&converter.convert_dir(DUMP).unwrap()),
+        ["GB-2-B1", "XM-1-A1"]
+    );
+}
+
+#[test]
+fn builder_rejects_bad_timestamp() {
+    assert!(Converter::builder().since("last tuesday").build().is_err());
+}
+
+#[test]
+fn activities_iterator_matches_convert_dir() {
+    let converter = Converter::default();
+    let streamed: Vec<Value> = converter
+        .activities(DUMP)
+        .unwrap()
+        .collect::<eyre::Result<_>>()
+        .unwrap();
+
+    assert_eq!(
+        identifiers(&streamed),
+        identifiers(&converter.convert_dir(DUMP).unwrap())
+    );
+}
+
+#[test]
+fn activities_iterator_reads_zip_and_reports_bad_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let zip_path = dir.path().join("dump.zip");
+    let b = fs::read(Path::new(DUMP).join("data/pubB/b.xml")).unwrap();
+    write_zip(
+        &zip_path,
+        &[
+            ("iati-data-main/data/pubB/b.xml", &b),
+            ("iati-data-main/data/pubC/broken.xml", b"<!DOCTYPE html><html></html>"),
+        ],
+    );
+
+    let results: Vec<eyre::Result<Value>> = Converter::default()
+        .activities(&zip_path)
+        .unwrap()
+        .collect();
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].as_ref().unwrap()["iati-identifier"], "GB-2-B1");
+    assert!(results[1].is_err());
+}
+
+#[test]
+fn manifest_skips_unchanged_files_on_second_run() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifest = dir.path().join("manifest.json");
+    let converter = Converter::builder()
+        .manifest(manifest.to_string_lossy())
+        .build()
+        .unwrap();
+
+    // Nothing is written until the feed is committed.
+    assert_eq!(converter.convert_dir(DUMP).unwrap().len(), 3);
+    assert!(!manifest.is_file());
+
+    let (records, feed) = converter.convert_changes(DUMP).unwrap();
+    assert_eq!(records.len(), 3);
+    feed.unwrap().commit().unwrap();
+    assert!(manifest.is_file());
+    assert!(converter.convert_dir(DUMP).unwrap().is_empty());
+}
+
+#[test]
+fn converters_only_back_fill_the_dump_when_asked() {
+    let dir = tempfile::tempdir().unwrap();
+    let dump = dir.path().join("dump");
+    fs::create_dir_all(dump.join("data/pubA")).unwrap();
+    fs::create_dir_all(dump.join("data/pubB")).unwrap();
+    fs::copy(Path::new(DUMP).join("data/pubB/b.xml"), dump.join("data/pubB/b.xml")).unwrap();
+    fs::copy(Path::new(DUMP).join("metadata.json"), dump.join("metadata.json")).unwrap();
+    fs::write(dump.join("errors.txt"), "404 pubA a.xml\n").unwrap();
+    let mirror = dir.path().join("mirror");
+    fs::create_dir_all(mirror.join("pubA")).unwrap();
+    fs::copy(Path::new(DUMP).join("data/pubA/a.xml"), mirror.join("pubA/a.xml")).unwrap();
+
+    let settings = Settings {
+        cache_dir: dir.path().join("cache").to_string_lossy().into_owned(),
+        archive_dir: dir.path().join("archive").to_string_lossy().into_owned(),
+        mirror_dir: Some(mirror.to_string_lossy().into_owned()),
+        offline: true,
+        ..Default::default()
+    };
+    let builder = || Converter::builder().settings(settings.clone());
+    let records = builder().build().unwrap().convert_dir(&dump).unwrap();
+    assert_eq!(identifiers(&records), ["GB-2-B1"]);
+    assert!(!dump.join("data/pubA/a.xml").exists());
+
+    let records = builder().supplement(true).build().unwrap().convert_dir(&dump).unwrap();
+    assert_eq!(identifiers(&records), ["GB-2-B1", "XM-1-A1", "XM-1-A2"]);
+}
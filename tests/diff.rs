@@ -0,0 +1,72 @@
+use iati_json_parser::converter::diff::{diff_activities, ChangeKind};
+use serde_json::{json, Value};
+
+fn activity(id: &str, status: u64, transactions: Value) -> Value {
+    json!({
+        "iati-identifier": id,
+        "activity-status": {"code": status},
+        "transaction": transactions,
+        "_source": {"sha256": format!("hash-{}-{}", id, status)}
+    })
+}
+
+#[test]
+fn activities_are_matched_by_identifier() {
+    let old = [
+        activity("XM-1", 2, json!([])),
+        activity("XM-2", 2, json!([])),
+        json!({"title": "no identifier"}),
+    ];
+    let new = [
+        activity("XM-2", 3, json!([])),
+        activity("XM-3", 2, json!([])),
+    ];
+
+    let report = diff_activities(&old, &new);
+    assert_eq!(report.added, ["XM-3"]);
+    assert_eq!(report.removed, ["XM-1"]);
+    assert_eq!(report.summary.unidentified, 1);
+
+    // `_source` changes on every download and isn't a change.
+    let changes = &report.changed[0].changes;
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].path, "activity-status/code");
+    assert_eq!(changes[0].kind, ChangeKind::Changed);
+    assert_eq!(changes[0].old, Some(json!(2)));
+    assert_eq!(changes[0].new, Some(json!(3)));
+}
+
+#[test]
+fn arrays_are_compared_as_multisets() {
+    let t = |value: u64| json!({"value": value});
+    let old = [activity("XM-1", 2, json!([t(10), t(20), t(20)]))];
+
+    // Reordered: nothing changed.
+    let reordered = [activity("XM-1", 2, json!([t(20), t(10), t(20)]))];
+    let report = diff_activities(&old, &reordered);
+    assert!(report.changed.is_empty());
+    assert_eq!(report.summary.unchanged, 1);
+
+    // One duplicate gone and a new value inserted first: one of each, not
+    // every later element shifted.
+    let new = [activity("XM-1", 2, json!([t(30), t(10), t(20)]))];
+    let changes = &diff_activities(&old, &new).changed[0].changes;
+    let kinds: Vec<_> = changes
+        .iter()
+        .map(|c| {
+            (
+                c.path.as_str(),
+                c.kind.clone(),
+                c.old.clone(),
+                c.new.clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            ("transaction", ChangeKind::Removed, Some(t(20)), None),
+            ("transaction", ChangeKind::Added, None, Some(t(30))),
+        ]
+    );
+}
@@ -0,0 +1,132 @@
+use iati_json_parser::converter::feed::{parse_iati_datetime, ChangeFeed};
+use iati_json_parser::converter::pipeline::convert_to_value;
+use iati_json_parser::converter::settings::Settings;
+use iati_json_parser::converter::Converter;
+use serde_json::{json, Value};
+
+use std::fs;
+use std::path::Path;
+
+mod common;
+
+use common::{write_zip, DUMP};
+
+fn source(path: &str, hash: &str) -> Value {
+    json!({"path": path, "sha256": hash})
+}
+
+#[test]
+fn unchanged_files_are_skipped_once_committed() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifest = dir.path().join("state/manifest.json");
+    let manifest = manifest.to_str().unwrap();
+
+    let first = ChangeFeed::new(None, Some(manifest)).unwrap();
+    for file in [source("data/a.xml", "1"), source("data/b.xml", "2")] {
+        assert!(!first.file_unchanged(&file));
+        first.record_file(&file);
+    }
+    first.commit().unwrap();
+
+    let second = ChangeFeed::new(None, Some(manifest)).unwrap();
+    assert!(second.file_unchanged(&source("data/a.xml", "1")));
+    assert!(!second.file_unchanged(&source("data/b.xml", "3")));
+    assert!(!second.file_unchanged(&source("data/c.xml", "4")));
+
+    // Files not converted this run keep their previous hash.
+    second.record_file(&source("data/b.xml", "3"));
+    second.commit().unwrap();
+    let third = ChangeFeed::new(None, Some(manifest)).unwrap();
+    assert!(third.file_unchanged(&source("data/a.xml", "1")));
+    assert!(third.file_unchanged(&source("data/b.xml", "3")));
+
+    // Without a manifest nothing is skipped.
+    let plain = ChangeFeed::new(None, None).unwrap();
+    assert!(!plain.file_unchanged(&source("data/a.xml", "1")));
+}
+
+#[test]
+fn since_keeps_activities_updated_after_it() {
+    assert_eq!(
+        parse_iati_datetime("2024-06-01"),
+        parse_iati_datetime("2024-06-01T00:00:00Z")
+    );
+    assert!(ChangeFeed::new(Some("last week"), None).is_err());
+
+    let feed = ChangeFeed::new(Some("2024-06-01"), None).unwrap();
+    assert!(feed.keep(&json!({"last-updated-datetime": "2024-06-02T10:00:00"})));
+    assert!(!feed.keep(&json!({"last-updated-datetime": "2024-05-31"})));
+    // Undated activities can't be shown unchanged.
+    assert!(feed.keep(&json!({"last-updated-datetime": "soon"})));
+    assert!(feed.keep(&json!({})));
+}
+
+#[test]
+fn the_manifest_waits_for_the_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifest = dir.path().join("manifest.json");
+    let settings = Settings::default();
+    let feed = || ChangeFeed::new(None, manifest.to_str()).unwrap();
+
+    let (records, pending) = convert_to_value(DUMP, &settings, Some(feed())).unwrap();
+    assert_eq!(records.as_array().unwrap().len(), 3);
+    assert!(!manifest.exists());
+    pending.unwrap().commit().unwrap();
+
+    let (records, _) = convert_to_value(DUMP, &settings, Some(feed())).unwrap();
+    assert_eq!(records, json!([]));
+
+    // A single file has no change feed.
+    let file = Path::new(DUMP).join("data/pubA/a.xml");
+    assert!(convert_to_value(file.to_str().unwrap(), &settings, Some(feed())).is_err());
+}
+
+#[test]
+fn streamed_activities_commit_explicitly() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifest = dir.path().join("manifest.json");
+    let converter = Converter::builder()
+        .manifest(manifest.to_string_lossy())
+        .build()
+        .unwrap();
+
+    let mut activities = converter.activities(DUMP).unwrap();
+    assert_eq!(activities.by_ref().count(), 3);
+    assert!(!manifest.exists());
+    activities.commit().unwrap();
+
+    let hashes: Value = serde_json::from_str(&fs::read_to_string(&manifest).unwrap()).unwrap();
+    assert_eq!(hashes.as_object().unwrap().len(), 2);
+    assert_eq!(converter.activities(DUMP).unwrap().count(), 0);
+}
+
+#[test]
+fn files_that_fail_to_convert_are_not_committed() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifest = dir.path().join("manifest.json");
+    let zip_path = dir.path().join("dump.zip");
+    let good = fs::read(Path::new(DUMP).join("data/pubA/a.xml")).unwrap();
+    write_zip(
+        &zip_path,
+        &[
+            ("iati-data-main/data/pubA/a.xml", &good),
+            (
+                "iati-data-main/data/pubA/broken.xml",
+                b"<!DOCTYPE html><html>Not found</html>",
+            ),
+        ],
+    );
+    let converter = Converter::builder()
+        .manifest(manifest.to_string_lossy())
+        .build()
+        .unwrap();
+
+    let mut activities = converter.activities(&zip_path).unwrap();
+    assert!(activities.by_ref().count() > 0);
+    activities.commit().unwrap();
+
+    let hashes: Value = serde_json::from_str(&fs::read_to_string(&manifest).unwrap()).unwrap();
+    let paths: Vec<_> = hashes.as_object().unwrap().keys().collect();
+    assert_eq!(paths.len(), 1);
+    assert!(paths[0].ends_with("pubA/a.xml"), "{:?}", paths);
+}
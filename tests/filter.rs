@@ -0,0 +1,122 @@
+use iati_json_parser::converter::filter::{
+    data_relative, is_safe_relative, publisher_of, PathFilter,
+};
+use iati_json_parser::converter::settings::Settings;
+use iati_json_parser::converter::utils::extract_zip;
+use iati_json_parser::converter::{zip_activity_entries, Converter};
+use serde_json::Value;
+
+use std::path::Path;
+
+mod common;
+
+use common::{dump_zip, write_zip, DUMP};
+
+fn identifiers(records: &[Value]) -> Vec<&str> {
+    let mut ids: Vec<&str> = records
+        .iter()
+        .filter_map(|r| r["iati-identifier"].as_str())
+        .collect();
+    ids.sort();
+    ids
+}
+
+fn filter(include: &[&str], exclude: &[&str]) -> PathFilter {
+    let owned = |rules: &[&str]| rules.iter().map(|r| r.to_string()).collect::<Vec<_>>();
+    PathFilter::new(&owned(include), &owned(exclude)).unwrap()
+}
+
+#[test]
+fn rules_match_publishers_and_globs() {
+    assert_eq!(
+        publisher_of(Path::new("iati-data-main/data/dfid/a.xml")).as_deref(),
+        Some("dfid")
+    );
+    assert_eq!(publisher_of(Path::new("data/a.xml")), None);
+    assert_eq!(
+        data_relative(Path::new("iati-data-main/data/dfid/a.xml")),
+        Path::new("dfid/a.xml")
+    );
+
+    let dfid = filter(&["dfid"], &[]);
+    assert!(dfid.allows(Path::new("dfid/a.xml")));
+    assert!(!dfid.allows(Path::new("usaid/a.xml")));
+
+    let globbed = filter(&["gb-*"], &["gb-2/*"]);
+    assert!(globbed.allows(Path::new("gb-1/a.xml")));
+    assert!(!globbed.allows(Path::new("gb-2/a.xml")));
+    assert!(!globbed.allows(Path::new("xm-1/a.xml")));
+}
+
+#[test]
+fn globs_select_the_same_files_from_a_folder_and_a_zip() {
+    let dir = tempfile::tempdir().unwrap();
+    let zip_path = dump_zip(dir.path(), &["pubA/a.xml", "pubB/b.xml"]);
+
+    let converter = Converter::builder().include("pub*").build().unwrap();
+    let expected = ["GB-2-B1", "XM-1-A1", "XM-1-A2"];
+    assert_eq!(identifiers(&converter.convert_dir(DUMP).unwrap()), expected);
+    assert_eq!(
+        identifiers(&converter.convert_zip(&zip_path).unwrap()),
+        expected
+    );
+
+    let converter = Converter::builder()
+        .include("pub*")
+        .exclude("*B/*.xml")
+        .build()
+        .unwrap();
+    let expected = ["XM-1-A1", "XM-1-A2"];
+    assert_eq!(identifiers(&converter.convert_dir(DUMP).unwrap()), expected);
+    assert_eq!(
+        identifiers(&converter.convert_zip(&zip_path).unwrap()),
+        expected
+    );
+    let streamed: Vec<Value> = converter
+        .activities(&zip_path)
+        .unwrap()
+        .collect::<eyre::Result<_>>()
+        .unwrap();
+    assert_eq!(identifiers(&streamed), expected);
+}
+
+#[test]
+fn files_outside_publisher_folders_only_match_globs() {
+    let loose = Path::new("loose.xml");
+
+    // Metadata is never a dataset, whatever the rules.
+    assert!(filter(&["dfid"], &[]).allows(Path::new("metadata.json")));
+    assert!(filter(&["dfid"], &[]).allows(Path::new("errors.txt")));
+
+    // Include rules drop stray datasets unless a glob picks them.
+    assert!(!filter(&["dfid"], &[]).allows(loose));
+    assert!(filter(&["loose*"], &[]).allows(loose));
+
+    // Exclude-only rules let them through unless a glob excludes them.
+    assert!(filter(&[], &["dfid"]).allows(loose));
+    assert!(!filter(&[], &["loose*"]).allows(loose));
+}
+
+#[test]
+fn zip_entries_escaping_the_extraction_dir_are_rejected() {
+    assert!(is_safe_relative(Path::new("data/pub/a.xml")));
+    assert!(!is_safe_relative(Path::new("../evil.xml")));
+    assert!(!is_safe_relative(Path::new("/etc/evil.xml")));
+
+    let dir = tempfile::tempdir().unwrap();
+    let zip_path = dir.path().join("dump.zip");
+    let xml: &[u8] = b"<iati-activities/>";
+    write_zip(&zip_path, &[("data/pub/a.xml", xml), ("../evil.xml", xml)]);
+
+    // Streaming skips the entry; extracting refuses the archive.
+    let entries = zip_activity_entries(&zip_path, &PathFilter::default()).unwrap();
+    let names: Vec<_> = entries.iter().map(|(_, name)| name.as_str()).collect();
+    assert_eq!(names, ["data/pub/a.xml"]);
+
+    let settings = Settings {
+        cache_dir: dir.path().join("cache").to_string_lossy().into_owned(),
+        ..Default::default()
+    };
+    assert!(extract_zip(&zip_path, &settings).is_err());
+    assert!(!dir.path().join("evil.xml").exists());
+}
@@ -194,6 +194,7 @@ fn find_iati_id_with_simple_search() {
 }
 
 #[test]
+#[allow(clippy::needless_borrows_for_generic_args)]
 fn find_iati_id_with_sed_approach() {
     let json_file_path = "iati_repository_2025-07-28.json";
 
@@ -316,6 +317,7 @@ fn find_iati_id_with_sed_approach() {
 }
 
 #[test]
+#[allow(clippy::needless_borrows_for_generic_args, clippy::needless_range_loop)]
 fn find_iati_id_complete_extraction() {
     let json_file_path = "iati_repository_2025-07-28.json";
 
@@ -484,6 +486,7 @@ fn find_iati_id_complete_extraction() {
 }
 
 #[test]
+#[allow(clippy::needless_borrows_for_generic_args, clippy::needless_range_loop)]
 fn find_iati_id_final_extraction() {
     let json_file_path = "iati_repository_2025-07-28.json";
 
@@ -584,6 +587,7 @@ fn find_iati_id_final_extraction() {
 }
 
 #[test]
+#[allow(clippy::needless_borrows_for_generic_args)]
 fn find_iati_id_with_jq() {
     let json_file_path = "iati_repository_2025-07-28.json";
 
@@ -0,0 +1,97 @@
+use iati_json_parser::converter::convert_xml_to_json;
+use iati_json_parser::converter::geojson::feature_collection;
+use iati_json_parser::converter::model::LatLon;
+use iati_json_parser::converter::pipeline::load_records;
+use iati_json_parser::converter::settings::Settings;
+use iati_json_parser::converter::utils::filter_activity;
+use serde_json::{json, Value};
+
+use std::fs;
+
+fn filtered(path: &str) -> Value {
+    let json = convert_xml_to_json(&fs::read_to_string(path).unwrap()).unwrap();
+    filter_activity(&json["iati-activities"]["iati-activity"]).unwrap()
+}
+
+fn location(pos: &str) -> Value {
+    let xml = format!(
+        r#"<iati-activities version="2.03"><iati-activity>
+<iati-identifier>XM-GEO-1</iati-identifier>
+<location><point srsName="x"><pos>{}</pos></point></location>
+</iati-activity></iati-activities>"#,
+        pos
+    );
+    let json = convert_xml_to_json(&xml).unwrap();
+    filter_activity(&json["iati-activities"]["iati-activity"]).unwrap()["location"][0].clone()
+}
+
+#[test]
+fn locations_are_normalised_with_numeric_positions() {
+    let activity = filtered("tests/fixtures/activities/rich.xml");
+
+    assert_eq!(
+        activity["location"],
+        json!([
+            {
+                "ref": "L1",
+                "name": {"en": "Kampala"},
+                "location-reach": 1,
+                "location-id": [{"vocabulary": "G1", "code": 1453782}],
+                "administrative": [{"vocabulary": "G1", "level": 1, "code": 1}],
+                "point": {"lat": 0.31, "lon": 32.58},
+                "exactness": 1
+            },
+            {"name": {"en": "Gulu"}}
+        ])
+    );
+}
+
+#[test]
+fn bad_positions_are_kept_as_published() {
+    assert_eq!(
+        LatLon::parse("-1.28, 36.82"),
+        Some(LatLon {
+            lat: -1.28,
+            lon: 36.82
+        })
+    );
+    assert_eq!(
+        location("-1.28 36.82")["point"],
+        json!({"lat": -1.28, "lon": 36.82})
+    );
+
+    // Out of range, the 0 0 placeholder, a third value and text.
+    for pos in ["91 10", "10 181", "0 0", "1 2 3", "Nairobi"] {
+        let location = location(pos);
+        assert!(location.get("point").is_none(), "{}", pos);
+        assert_eq!(location["invalid-pos"], pos);
+    }
+}
+
+#[test]
+fn geojson_has_a_point_per_located_place() {
+    let records = load_records("tests/fixtures/ods-dump", &Settings::default()).unwrap();
+    let collection = feature_collection(&records);
+
+    assert_eq!(collection["type"], "FeatureCollection");
+    let features = collection["features"].as_array().unwrap();
+    assert_eq!(features.len(), 1);
+    assert_eq!(
+        features[0]["geometry"],
+        json!({"type": "Point", "coordinates": [36.82, -1.28]})
+    );
+    let properties = &features[0]["properties"];
+    assert_eq!(properties["iati_identifier"], "XM-1-A1");
+    assert_eq!(properties["publisher"], "pubA");
+    assert_eq!(properties["location_name"], "Nairobi");
+    assert_eq!(properties["recipient_countries"], "KE,UG");
+    assert_eq!(properties["sectors"], "14030,12220");
+    assert_eq!(properties["commitment"], 1000.0);
+
+    // Gulu has no position, so only Kampala is mapped.
+    let rich = feature_collection(&[filtered("tests/fixtures/activities/rich.xml")]);
+    let features = rich["features"].as_array().unwrap();
+    assert_eq!(features.len(), 1);
+    assert_eq!(features[0]["properties"]["location_ref"], "L1");
+    assert_eq!(features[0]["properties"]["exactness"], "1");
+}
@@ -0,0 +1,69 @@
+use iati_json_parser::converter::health::{parse_error_line, read_health, FileStatus};
+
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn error_lines_are_parsed() {
+    let entry = parse_error_line(
+        3,
+        "404 gb-1 gb-1-acts.xml https://example.org/acts.xml Not Found",
+    )
+    .unwrap();
+    assert_eq!(entry.line, 3);
+    assert_eq!(entry.status_code, Some(404));
+    assert_eq!(entry.kind, "http");
+    assert_eq!(entry.publisher, "gb-1");
+    assert_eq!(entry.dataset, "gb-1-acts");
+    assert_eq!(entry.url.as_deref(), Some("https://example.org/acts.xml"));
+    assert_eq!(entry.detail.as_deref(), Some("Not Found"));
+    assert_eq!(
+        entry.path_in(Path::new("data")),
+        Path::new("data/gb-1/gb-1-acts.xml")
+    );
+
+    let entry = parse_error_line(1, "ConnectionError: xm-2 xm-2-acts").unwrap();
+    assert_eq!(entry.status_code, None);
+    assert_eq!(entry.kind, "ConnectionError");
+    assert_eq!(entry.url, None);
+
+    assert!(parse_error_line(1, "404 gb-1").is_none());
+}
+
+#[test]
+fn names_that_leave_the_data_folder_are_rejected() {
+    for line in [
+        "404 ../../x acts.xml",
+        "404 .. acts.xml",
+        "404 gb-1 ../acts.xml",
+        "404 /etc acts.xml",
+        "404 gb-1/nested acts.xml",
+        "404 . acts.xml",
+    ] {
+        assert!(parse_error_line(1, line).is_none(), "{}", line);
+    }
+}
+
+#[test]
+fn health_cross_references_the_data_folder() {
+    let dir = tempfile::tempdir().unwrap();
+    let data = dir.path().join("data");
+    fs::create_dir_all(data.join("pub")).unwrap();
+    fs::write(data.join("pub/present.xml"), "<iati-activities/>").unwrap();
+    fs::write(data.join("pub/empty.xml"), "").unwrap();
+    fs::write(
+        dir.path().join("errors.txt"),
+        "500 pub present.xml\n\n404 pub empty.xml\n404 pub gone.xml\n404 ../escape x.xml\nnonsense\n",
+    )
+    .unwrap();
+
+    let report = read_health(&data).unwrap();
+    let statuses: Vec<_> = report.entries.iter().map(|e| e.status).collect();
+    assert_eq!(
+        statuses,
+        [FileStatus::Present, FileStatus::Empty, FileStatus::Missing]
+    );
+    assert_eq!(report.malformed_lines, ["404 ../escape x.xml", "nonsense"]);
+    assert_eq!(report.present(), 1);
+    assert_eq!(report.missing(), 2);
+}
@@ -0,0 +1,79 @@
+use iati_json_parser::converter::layout::detect_source;
+use serde_json::json;
+
+use std::fs;
+use std::path::Path;
+
+mod common;
+
+use common::DUMP;
+
+fn write(root: &Path, name: &str, contents: &str) {
+    let path = root.join(name);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+}
+
+#[test]
+fn ods_dump_is_found_from_its_root_or_data_folder() {
+    for path in [DUMP.to_owned(), format!("{}/data", DUMP)] {
+        let source = detect_source(Path::new(&path)).unwrap();
+        assert_eq!(source.layout.name(), "ods-dump");
+        assert_eq!(source.data_dir(), Path::new(DUMP).join("data"));
+
+        let info = source.dataset_info(&source.data_dir().join("pubA/a.xml"));
+        assert_eq!(info.publisher.as_deref(), Some("pubA"));
+        assert_eq!(info.dataset, "a");
+    }
+}
+
+#[test]
+fn codeforiati_is_found_under_a_dynamically_named_root() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("iati-data-main");
+    write(&root, "data/gb-1/gb-1-acts.xml", "<iati-activities/>");
+    write(
+        &root,
+        "metadata/gb-1/gb-1-acts.json",
+        r#"{"name": "gb-1-acts"}"#,
+    );
+
+    let source = detect_source(dir.path()).unwrap();
+    assert_eq!(source.layout.name(), "codeforiati");
+    assert_eq!(source.root, root);
+
+    let info = source.dataset_info(&root.join("data/gb-1/gb-1-acts.xml"));
+    assert_eq!(info.publisher.as_deref(), Some("gb-1"));
+    assert_eq!(info.metadata, Some(json!({"name": "gb-1-acts"})));
+}
+
+#[test]
+fn registry_metadata_names_the_publisher() {
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path(), "data/folder/acts.xml", "<iati-activities/>");
+    write(
+        dir.path(),
+        "metadata/acts.json",
+        r#"{"organization": {"name": "registered-pub"}}"#,
+    );
+
+    let source = detect_source(dir.path()).unwrap();
+    assert_eq!(source.layout.name(), "registry-metadata");
+    let info = source.dataset_info(&dir.path().join("data/folder/acts.xml"));
+    assert_eq!(info.publisher.as_deref(), Some("registered-pub"));
+}
+
+#[test]
+fn anything_else_is_a_flat_folder() {
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path(), "pub/acts.xml", "<iati-activities/>");
+
+    let source = detect_source(dir.path()).unwrap();
+    assert_eq!(source.layout.name(), "flat");
+    assert_eq!(source.data_dir(), dir.path());
+    let info = source.dataset_info(&dir.path().join("pub/acts.xml"));
+    assert_eq!(info.publisher.as_deref(), Some("pub"));
+    assert_eq!(info.metadata, None);
+
+    assert!(detect_source(&dir.path().join("pub/acts.xml")).is_err());
+}
@@ -0,0 +1,287 @@
+use iati_json_parser::converter::model::{transaction_type_code, Activity, FilteredActivity};
+use iati_json_parser::converter::settings::Settings;
+use iati_json_parser::converter::stats::dump_stats;
+use iati_json_parser::converter::utils::{filter_activity, filter_activity_with, FilterOptions};
+use iati_json_parser::converter::{convert_file, convert_xml_to_json};
+use serde_json::{json, Value};
+
+use std::fs;
+
+fn raw_activities(path: &str) -> Vec<Value> {
+    let json = convert_xml_to_json(&fs::read_to_string(path).unwrap()).unwrap();
+    match &json["iati-activities"]["iati-activity"] {
+        Value::Array(a) => a.clone(),
+        other => vec![other.clone()],
+    }
+}
+
+#[test]
+fn activity_round_trips_to_the_same_json() {
+    for path in [
+        "tests/fixtures/activities/rich.xml",
+        "tests/fixtures/activities/v1.xml",
+        "tests/fixtures/ods-dump/data/pubA/a.xml",
+    ] {
+        for raw in raw_activities(path) {
+            let activity = Activity::from_value(&raw).unwrap();
+            assert_eq!(serde_json::to_value(&activity).unwrap(), raw, "{}", path);
+        }
+    }
+}
+
+#[test]
+fn typed_accessors_read_nested_elements() {
+    let activity =
+        Activity::from_value(&raw_activities("tests/fixtures/activities/rich.xml")[0]).unwrap();
+
+    assert_eq!(activity.identifier().as_deref(), Some("XM-3-R1"));
+    assert_eq!(activity.lang.as_deref(), Some("en"));
+
+    let org = activity.reporting_org().unwrap();
+    assert_eq!(
+        org.reference.as_ref().unwrap().as_string().as_deref(),
+        Some("XM-3")
+    );
+    let names: Vec<_> = org.narrative.as_ref().unwrap().iter().collect();
+    assert_eq!(names[1].lang(), Some("fr"));
+    assert_eq!(names[1].text().as_deref(), Some("Org Trois"));
+
+    let transactions: Vec<_> = activity.transactions().collect();
+    assert_eq!(transactions.len(), 3);
+    assert_eq!(
+        transactions[0].type_code().unwrap().as_string().as_deref(),
+        Some("11")
+    );
+    assert_eq!(
+        transactions[1].amount().unwrap().amount().unwrap().as_f64(),
+        Some(25.5)
+    );
+    assert!(transactions[2].date().is_none());
+
+    let budget = activity.budgets().next().unwrap();
+    assert_eq!(
+        budget.amount().unwrap().amount().unwrap().as_f64(),
+        Some(1000.0)
+    );
+    assert_eq!(activity.locations().count(), 2);
+    assert_eq!(activity.sectors().count(), 2);
+}
+
+#[test]
+fn plain_text_elements_are_kept_raw() {
+    let raw = &raw_activities("tests/fixtures/activities/v1.xml")[0];
+    let activity = Activity::from_value(raw).unwrap();
+
+    assert!(activity.title.as_ref().unwrap().parsed().is_none());
+    assert!(activity.reporting_org().unwrap().narrative.is_none());
+    assert_eq!(
+        activity.reporting_org().unwrap().extra["#text"],
+        "Old style"
+    );
+
+    let filtered = filter_activity(raw).unwrap();
+    assert_eq!(filtered["title"], json!({"en": "Plain title"}));
+    assert_eq!(
+        filtered["description"],
+        json!([{"narrative": {"en": "Plain description"}}])
+    );
+    assert_eq!(
+        filtered["reporting-org"],
+        json!({"id": "X", "name": {"en": "Old style"}})
+    );
+}
+
+#[test]
+fn filter_builds_meta_and_flattens_transactions() {
+    let filtered = filter_activity(&raw_activities("tests/fixtures/activities/rich.xml")[0]).unwrap();
+
+    assert_eq!(
+        filtered["transaction"],
+        json!([
+            {
+                "transaction-type": 11,
+                "transaction-date": "2023-02-01",
+                "transaction-value": 300
+            },
+            {
+                "transaction-type": 4,
+                "transaction-date": "2023-03-01",
+                "transaction-value": 25.5,
+                "transaction-currency": "USD"
+            }
+        ])
+    );
+    assert_eq!(filtered["meta"]["aid_type"], json!({"code": "C01"}));
+    assert_eq!(filtered["meta"]["finance_type"], json!({"code": 110}));
+    assert_eq!(
+        filtered["meta"]["related_activities"],
+        json!([{"ref": "XM-3-R0", "type": 1}])
+    );
+    assert_eq!(
+        filtered["meta"]["policy_markers"].as_array().unwrap().len(),
+        2
+    );
+    assert_eq!(filtered["reporting-org"]["id"], "XM-3");
+    assert_eq!(filtered["description"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn purpose_is_the_highest_percentage_sector() {
+    let filtered = filter_activity(&raw_activities("tests/fixtures/ods-dump/data/pubA/a.xml")[0]).unwrap();
+
+    assert_eq!(filtered["meta"]["purpose"]["dac_code"], 14030);
+    assert_eq!(filtered["meta"]["purpose"]["dac_category"], "140");
+    assert_eq!(filtered["meta"]["sectors"]["1"][1]["dac_code"], 12220);
+}
+
+#[test]
+fn sectors_are_grouped_by_vocabulary_and_purpose_is_dac_only() {
+    let filtered = filter_activity(&raw_activities("tests/fixtures/ods-dump/data/pubA/a.xml")[0]).unwrap();
+    let sectors = &filtered["meta"]["sectors"];
+
+    assert_eq!(
+        sectors["7"],
+        json!([{"code": 6.1, "vocabulary": "7"}]),
+        "SDG targets have no DAC code or category"
+    );
+    assert_eq!(sectors["1"].as_array().unwrap().len(), 2);
+
+    // A DAC 3-digit sector listed first doesn't beat a 5-digit one.
+    let rich = filter_activity(&raw_activities("tests/fixtures/activities/rich.xml")[0]).unwrap();
+    assert_eq!(rich["meta"]["purpose"]["code"], 12220);
+    assert_eq!(rich["meta"]["sectors"]["2"][0]["dac_category"], "121");
+    assert!(rich["meta"]["sectors"]["2"][0].get("dac_code").is_none());
+
+    let xml = String::from(r#"<iati-activities version="1.05"><iati-activity>
+<iati-identifier>XM-VOC-1</iati-identifier>
+<sector code="9" vocabulary="99" percentage="80"/>
+<sector code="151" vocabulary="DAC-3" percentage="20"/>
+</iati-activity></iati-activities>"#);
+    let raw = convert_xml_to_json(&xml).unwrap()["iati-activities"]["iati-activity"].clone();
+    let filtered = filter_activity(&raw).unwrap();
+    // The publisher's own vocabulary is never the purpose; 1.0x DAC-3 is vocabulary 2.
+    assert_eq!(filtered["meta"]["purpose"]["code"], 151);
+    assert_eq!(filtered["meta"]["purpose"]["vocabulary"], "2");
+    assert_eq!(filtered["meta"]["sectors"]["99"][0]["code"], 9);
+
+    let activity = FilteredActivity::from_value(&filtered).unwrap();
+    assert_eq!(activity.sectors().count(), 2);
+    assert_eq!(activity.dac_sectors().count(), 0);
+}
+
+#[test]
+fn converted_records_read_back_as_filtered_activities() {
+    let records = convert_file("tests/fixtures/ods-dump/data/pubA/a.xml").unwrap();
+    let activity = FilteredActivity::from_value(&records[0]).unwrap();
+
+    assert_eq!(activity.identifier().as_deref(), Some("XM-1-A1"));
+    assert_eq!(activity.transactions().count(), 2);
+    assert_eq!(activity.recipient_countries().count(), 2);
+    assert_eq!(
+        activity.title.as_ref().unwrap().get("fr"),
+        Some("Projet eau")
+    );
+    assert_eq!(activity.extra["_source"]["publisher"], "pubA");
+    assert_eq!(serde_json::to_value(&activity).unwrap(), records[0]);
+}
+
+#[test]
+fn totals_use_2x_type_codes() {
+    let records = convert_file("tests/fixtures/activities/rich.xml").unwrap();
+    let activity = FilteredActivity::from_value(&records[0]).unwrap();
+    assert_eq!(activity.budget_total(), 1000.0);
+    assert_eq!(activity.transaction_total(4), 25.5);
+    assert_eq!(activity.transaction_total(3), 0.0);
+
+    let v1 =
+        FilteredActivity::from_value(&convert_file("tests/fixtures/activities/v1.xml").unwrap()[0])
+            .unwrap();
+    assert_eq!(v1.transaction_total(3), 60.0);
+    assert_eq!(transaction_type_code("d"), Some(3));
+    assert_eq!(transaction_type_code("13"), Some(13));
+}
+
+#[test]
+fn narratives_are_keyed_by_language() {
+    let raw = &raw_activities("tests/fixtures/activities/rich.xml")[0];
+
+    let filtered = filter_activity(raw).unwrap();
+    assert_eq!(
+        filtered["reporting-org"]["name"],
+        json!({"en": "Org Three", "fr": "Org Trois"})
+    );
+    assert_eq!(filtered["title"], json!({"en": "Rich"}));
+    assert_eq!(
+        filtered["recipient-region"],
+        json!([{
+            "code": 289,
+            "percentage": 100,
+            "vocabulary": 1,
+            "name": {"en": "South of Sahara"}
+        }])
+    );
+
+    // Narratives without xml:lang take the activity's language before the default.
+    let mut untagged = raw.clone();
+    untagged["xml:lang"] = json!("es");
+    let options = FilterOptions {
+        default_lang: "de".to_owned(),
+        ..Default::default()
+    };
+    assert_eq!(
+        filter_activity_with(&untagged, &options).unwrap()["title"],
+        json!({"es": "Rich"})
+    );
+    untagged.as_object_mut().unwrap().remove("xml:lang");
+    assert_eq!(
+        filter_activity_with(&untagged, &options).unwrap()["title"],
+        json!({"de": "Rich"})
+    );
+}
+
+#[test]
+fn preferred_language_picks_one_string_with_fallback() {
+    let raw = &raw_activities("tests/fixtures/activities/rich.xml")[0];
+    let options = |lang: &str| FilterOptions {
+        preferred_lang: Some(lang.to_owned()),
+        ..Default::default()
+    };
+
+    let french = filter_activity_with(raw, &options("fr")).unwrap();
+    assert_eq!(french["reporting-org"]["name"], "Org Trois");
+    // No French title, so the activity's own language is used.
+    assert_eq!(french["title"], "Rich");
+    assert_eq!(french["recipient-region"][0]["name"], "South of Sahara");
+    assert_eq!(
+        french["description"][0]["narrative"],
+        "Main description text"
+    );
+
+    let activity = FilteredActivity::from_value(&french).unwrap();
+    assert_eq!(activity.title.unwrap().text(), Some("Rich"));
+}
+
+#[test]
+fn activities_that_dont_fit_the_model_are_skipped() {
+    // With json_types, `xml:lang="1"` arrives as a number the model can't hold.
+    let xml = r#"<iati-activities><iati-activity xml:lang="1"><iati-identifier>XM-1</iati-identifier>
+</iati-activity><iati-activity><iati-identifier>XM-2</iati-identifier></iati-activity>
+</iati-activities>"#;
+    let raw = convert_xml_to_json(&xml.to_owned()).unwrap();
+    let activity = &raw["iati-activities"]["iati-activity"][0];
+    assert!(Activity::from_value(activity).is_err());
+    assert!(filter_activity(activity).is_err());
+    assert!(Activity::from_value(&json!([])).is_err());
+    assert!(FilteredActivity::from_value(&json!("XM-1")).is_err());
+
+    // Only the activity that doesn't fit is left out of its file.
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("odd.xml");
+    fs::write(&path, xml).unwrap();
+    let records = convert_file(&path).unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0]["iati-identifier"], "XM-2");
+
+    let stats = dump_stats(path.to_str().unwrap(), &Settings::default()).unwrap();
+    assert_eq!((stats.files, stats.files_failed, stats.activities), (1, 0, 1));
+}
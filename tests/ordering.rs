@@ -0,0 +1,47 @@
+use iati_json_parser::converter::Converter;
+use serde_json::Value;
+
+mod common;
+
+use common::{dump_zip, DUMP};
+
+fn identifiers(records: &[Value]) -> Vec<&str> {
+    records
+        .iter()
+        .filter_map(|r| r["iati-identifier"].as_str())
+        .collect()
+}
+
+#[test]
+fn deterministic_mode_orders_by_path_then_document() {
+    let converter = Converter::builder().deterministic(true).build().unwrap();
+
+    let first = converter.convert_dir(DUMP).unwrap();
+    let second = converter.convert_dir(DUMP).unwrap();
+    assert_eq!(identifiers(&first), ["XM-1-A1", "XM-1-A2", "GB-2-B1"]);
+    assert_eq!(
+        serde_json::to_string(&first).unwrap(),
+        serde_json::to_string(&second).unwrap()
+    );
+}
+
+#[test]
+fn zip_entries_are_sorted_regardless_of_archive_order() {
+    let dir = tempfile::tempdir().unwrap();
+    let zip_path = dump_zip(dir.path(), &["pubB/b.xml", "pubA/a.xml"]);
+
+    let converter = Converter::builder().deterministic(true).build().unwrap();
+    let records = converter.convert_zip(&zip_path).unwrap();
+    assert_eq!(identifiers(&records), ["XM-1-A1", "XM-1-A2", "GB-2-B1"]);
+
+    let streamed: Vec<Value> = converter
+        .activities(&zip_path)
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(identifiers(&streamed), identifiers(&records));
+
+    // Without the mode, the archive order is kept.
+    let records = Converter::default().convert_zip(&zip_path).unwrap();
+    assert_eq!(identifiers(&records), ["GB-2-B1", "XM-1-A1", "XM-1-A2"]);
+}
@@ -0,0 +1,101 @@
+use iati_json_parser::converter::convert_xml_to_json;
+use iati_json_parser::converter::model::Activity;
+use iati_json_parser::converter::organisations::{dump_organisations, Registry};
+use iati_json_parser::converter::orgid::{AgencyList, RefIssue};
+use iati_json_parser::converter::settings::Settings;
+
+use std::collections::BTreeMap;
+
+fn activity(body: &str) -> Activity {
+    let xml = format!(
+        r#"<iati-activities version="2.03"><iati-activity>
+<iati-identifier>XM-REG-1</iati-identifier>{}</iati-activity></iati-activities>"#,
+        body
+    );
+    let json = convert_xml_to_json(&xml).unwrap();
+    Activity::from_value(&json["iati-activities"]["iati-activity"]).unwrap()
+}
+
+fn roles(pairs: &[(&str, usize)]) -> BTreeMap<String, usize> {
+    pairs.iter().map(|(r, n)| (r.to_string(), *n)).collect()
+}
+
+#[test]
+fn dump_orgs_are_merged_by_ref() {
+    let settings = Settings {
+        org_id_list: Some("tests/fixtures/org-id/lists.json".to_owned()),
+        ..Default::default()
+    };
+    let orgs = dump_organisations("tests/fixtures/ods-dump", &settings).unwrap();
+
+    let refs: Vec<_> = orgs
+        .iter()
+        .map(|o| o.reference.as_deref().unwrap())
+        .collect();
+    assert_eq!(refs, ["XM-1", "GB-2", "GB-COH-123"]);
+
+    // Reporting both its activities, funding and paying out in one of them.
+    assert_eq!(orgs[0].name.as_deref(), Some("Org One"));
+    assert_eq!(orgs[0].activities, 2);
+    assert_eq!(
+        orgs[0].roles,
+        roles(&[("funding", 1), ("provider", 1), ("reporting", 2)])
+    );
+    assert_eq!(orgs[0].ref_issues, [RefIssue::NoPrefix]);
+
+    assert_eq!(
+        orgs[2].roles,
+        roles(&[("implementing", 1), ("receiver", 1)])
+    );
+    assert_eq!(orgs[2].types, ["22"]);
+    assert_eq!(orgs[2].prefix.as_deref(), Some("GB-COH"));
+    assert_eq!(orgs[2].local_id.as_deref(), Some("123"));
+    assert!(orgs[2].ref_issues.is_empty());
+}
+
+#[test]
+fn names_are_collected_and_orgs_without_refs_merge_by_name() {
+    let mut registry = Registry::default();
+    registry.add_activity(
+        &activity(
+            r#"<reporting-org ref="gb-coh-1" type="70"><narrative>Acme Ltd</narrative></reporting-org>
+<participating-org ref="N/A" role="Funding"><narrative>Some  Trust</narrative></participating-org>"#,
+        ),
+        "en",
+    );
+    registry.add_activity(
+        &activity(
+            r#"<participating-org ref="GB-COH-1" role="4"><narrative>ACME Limited</narrative><narrative xml:lang="fr">Acme SARL</narrative></participating-org>
+<transaction><transaction-type code="3"/><receiver-org ref="GB-COH-1"><narrative>Acme Ltd</narrative></receiver-org></transaction>
+<participating-org>some trust</participating-org>"#,
+        ),
+        "en",
+    );
+    let orgs = registry.organisations(&AgencyList::default());
+    assert_eq!(orgs.len(), 2);
+
+    let acme = &orgs[0];
+    assert_eq!(acme.reference.as_deref(), Some("GB-COH-1"));
+    assert_eq!(acme.activities, 2);
+    assert_eq!(acme.name.as_deref(), Some("Acme Ltd"));
+    let names: Vec<_> = acme
+        .names
+        .iter()
+        .map(|n| (n.name.as_str(), n.activities))
+        .collect();
+    assert_eq!(
+        names,
+        [("Acme Ltd", 2), ("ACME Limited", 1), ("Acme SARL", 1)]
+    );
+    assert_eq!(
+        acme.roles,
+        roles(&[("implementing", 1), ("receiver", 1), ("reporting", 1)])
+    );
+
+    // A placeholder ref is no ref: merged by name, whitespace and case aside.
+    let trust = &orgs[1];
+    assert_eq!(trust.reference, None);
+    assert_eq!(trust.activities, 2);
+    assert_eq!(trust.roles, roles(&[("funding", 1), ("participating", 1)]));
+    assert!(trust.ref_issues.is_empty());
+}
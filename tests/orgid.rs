@@ -0,0 +1,144 @@
+use iati_json_parser::converter::convert_xml_to_json;
+use iati_json_parser::converter::orgid::{parse_org_ref, AgencyList, RefIssue};
+use iati_json_parser::converter::settings::Settings;
+use iati_json_parser::converter::utils::{filter_activity, filter_activity_with};
+use serde_json::{json, Value};
+
+use std::fs;
+use std::path::Path;
+
+const LISTS: &str = "tests/fixtures/org-id/lists.json";
+
+fn participants(orgs: &str, settings: &Settings) -> Value {
+    let xml = format!(
+        r#"<iati-activities version="2.03"><iati-activity>
+<iati-identifier>XM-ORG-1</iati-identifier>{}</iati-activity></iati-activities>"#,
+        orgs
+    );
+    let json = convert_xml_to_json(&xml).unwrap();
+    let options = settings.filter_options().unwrap();
+    filter_activity_with(&json["iati-activities"]["iati-activity"], &options).unwrap()["participating-org"]
+        .clone()
+}
+
+#[test]
+fn participants_are_normalised() {
+    let json =
+        convert_xml_to_json(&fs::read_to_string("tests/fixtures/activities/rich.xml").unwrap())
+            .unwrap();
+    let activity = filter_activity(&json["iati-activities"]["iati-activity"]).unwrap();
+
+    assert_eq!(
+        activity["participating-org"],
+        json!([{
+            "ref": "XM-3",
+            "type": 21,
+            "role": 1,
+            "role-name": "Funding",
+            "activity-id": "XM-3-R0",
+            "crs-channel-code": 21000,
+            "name": {"en": "Org Three"},
+            "org-id": {"issues": ["no-prefix"]}
+        }])
+    );
+
+    // 1.0x roles are names and organisations are plain text.
+    let v1 = participants(
+        r#"<participating-org role="Implementing" ref="GB-CHC-202918">Oxfam</participating-org>"#,
+        &Settings::default(),
+    );
+    assert_eq!(v1[0]["role"], 4);
+    assert_eq!(v1[0]["role-name"], "Implementing");
+    assert_eq!(v1[0]["name"], json!({"en": "Oxfam"}));
+    assert_eq!(
+        v1[0]["org-id"],
+        json!({"prefix": "GB-CHC", "local-id": "202918"})
+    );
+}
+
+#[test]
+fn refs_are_checked_against_the_agency_list() {
+    let settings = Settings {
+        org_id_list: Some(LISTS.to_owned()),
+        ..Default::default()
+    };
+    let orgs = participants(
+        r#"<participating-org ref="XM-DAC-41114" role="1"/>
+<participating-org ref="gb-coh-01234567" role="4"/>
+<participating-org ref="XX-ABC-1" role="4"/>
+<participating-org ref="XI-GRID-grid.1234.5" role="2"/>
+<participating-org ref="N/A" role="3"/>
+<participating-org ref="GB-COH-0123 4567" role="3"/>"#,
+        &settings,
+    );
+    let org_ids: Vec<&Value> = orgs
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|o| &o["org-id"])
+        .collect();
+
+    assert_eq!(
+        org_ids[0],
+        &json!({
+            "prefix": "XM-DAC",
+            "local-id": "41114",
+            "agency-name": "OECD Development Assistance Committee"
+        })
+    );
+    // Matched regardless of case, and named as on the list.
+    assert_eq!(org_ids[1]["prefix"], "GB-COH");
+    assert_eq!(org_ids[1]["local-id"], "01234567");
+    assert_eq!(org_ids[2]["issues"], json!(["unknown-agency"]));
+    assert_eq!(org_ids[2]["prefix"], "XX-ABC");
+    assert_eq!(org_ids[3]["issues"], json!(["deprecated-agency"]));
+    assert_eq!(org_ids[4], &json!({"issues": ["placeholder"]}));
+    assert_eq!(org_ids[5]["issues"], json!(["malformed"]));
+    assert_eq!(org_ids[5]["local-id"], "0123 4567");
+
+    let missing = Settings {
+        org_id_list: Some("tests/fixtures/org-id/missing.json".to_owned()),
+        ..Default::default()
+    };
+    assert!(missing.filter_options().is_err());
+}
+
+#[test]
+fn placeholders_and_prefixes() {
+    let agencies = AgencyList::load(Path::new(LISTS)).unwrap();
+    for placeholder in [
+        "",
+        " ",
+        "-",
+        "0",
+        "000",
+        "NA",
+        "n/a",
+        "Unknown",
+        "not applicable",
+        "XXX",
+    ] {
+        assert_eq!(
+            parse_org_ref(placeholder, &agencies).issues,
+            [RefIssue::Placeholder],
+            "{:?}",
+            placeholder
+        );
+    }
+
+    let id = parse_org_ref("GB-GOV-1", &agencies);
+    assert!(id.is_valid());
+    assert_eq!(id.prefix.as_deref(), Some("GB-GOV"));
+    assert_eq!(id.local_id.as_deref(), Some("1"));
+
+    // A prefix with nothing after it isn't an identifier.
+    assert_eq!(
+        parse_org_ref("GB-COH-", &agencies).issues,
+        [RefIssue::NoPrefix]
+    );
+
+    // Without a list, anything shaped like a prefix is taken as one.
+    let id = parse_org_ref("XX-ABC-1", &AgencyList::default());
+    assert!(id.is_valid());
+    assert_eq!(id.prefix.as_deref(), Some("XX-ABC"));
+}
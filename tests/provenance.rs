@@ -0,0 +1,100 @@
+use iati_json_parser::converter::layout::detect_source;
+use iati_json_parser::converter::provenance::{sha256_hex, source_record};
+use iati_json_parser::converter::{convert_dir, Converter};
+use serde_json::json;
+
+use std::fs;
+use std::path::Path;
+
+mod common;
+
+use common::{dump_zip, write_zip, DUMP};
+
+#[test]
+fn records_carry_the_hash_of_their_file() {
+    assert_eq!(
+        sha256_hex(b"abc"),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+
+    let bytes = fs::read(Path::new(DUMP).join("data/pubA/a.xml")).unwrap();
+    let records = convert_dir(DUMP).unwrap();
+    let from_a: Vec<_> = records
+        .iter()
+        .filter(|r| r["_source"]["dataset"] == "a")
+        .collect();
+    assert_eq!(from_a.len(), 2);
+    for record in from_a {
+        assert_eq!(record["_source"]["sha256"], sha256_hex(&bytes));
+        assert_eq!(record["_source"]["path"], "pubA/a.xml");
+    }
+
+    // The same file hashes the same read from a zip.
+    let dir = tempfile::tempdir().unwrap();
+    let zip_path = dump_zip(dir.path(), &["pubA/a.xml"]);
+    let zipped = Converter::default().convert_zip(&zip_path).unwrap();
+    assert_eq!(zipped[0]["_source"]["sha256"], sha256_hex(&bytes));
+    assert_eq!(zipped[0]["_source"]["publisher"], "pubA");
+    assert_eq!(zipped[0]["_source"]["path"], "pubA/a.xml");
+}
+
+#[test]
+fn registry_metadata_gives_the_source_url() {
+    let dir = tempfile::tempdir().unwrap();
+    let xml_path = dir.path().join("data/gb-1/gb-1-acts.xml");
+    fs::create_dir_all(xml_path.parent().unwrap()).unwrap();
+    fs::write(&xml_path, "<iati-activities/>").unwrap();
+    let metadata =
+        json!({"name": "gb-1-acts", "resources": [{"url": "https://example.org/acts.xml"}]});
+    fs::create_dir_all(dir.path().join("metadata/gb-1")).unwrap();
+    fs::write(
+        dir.path().join("metadata/gb-1/gb-1-acts.json"),
+        metadata.to_string(),
+    )
+    .unwrap();
+
+    let source = detect_source(dir.path()).unwrap();
+    let record = source_record(
+        Some(&source),
+        &xml_path,
+        b"<iati-activities/>",
+        Some("2024-05-01T00:00:00+00:00"),
+    );
+    assert_eq!(
+        record,
+        json!({
+            "publisher": "gb-1",
+            "dataset": "gb-1-acts",
+            "file_name": "gb-1-acts.xml",
+            "path": "gb-1/gb-1-acts.xml",
+            "sha256": sha256_hex(b"<iati-activities/>"),
+            "downloaded_at": "2024-05-01T00:00:00+00:00",
+            "url": "https://example.org/acts.xml",
+            "registry": metadata
+        })
+    );
+}
+
+#[test]
+fn zipped_dumps_carry_their_registry_metadata() {
+    let dir = tempfile::tempdir().unwrap();
+    let zip_path = dir.path().join("dump.zip");
+    let xml = fs::read(Path::new(DUMP).join("data/pubA/a.xml")).unwrap();
+    let metadata = json!({"name": "a", "resources": [{"url": "https://example.org/a.xml"}]});
+    write_zip(
+        &zip_path,
+        &[
+            ("iati-data-main/data/pubA/a.xml", &xml),
+            (
+                "iati-data-main/metadata/pubA/a.json",
+                metadata.to_string().as_bytes(),
+            ),
+        ],
+    );
+
+    let zipped = Converter::default().convert_zip(&zip_path).unwrap();
+    assert_eq!(zipped.len(), 2);
+    assert_eq!(zipped[0]["_source"]["path"], "pubA/a.xml");
+    assert_eq!(zipped[0]["_source"]["url"], "https://example.org/a.xml");
+    assert_eq!(zipped[0]["_source"]["registry"], metadata);
+}
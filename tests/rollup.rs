@@ -0,0 +1,139 @@
+use iati_json_parser::converter::{convert_file, convert_xml_to_json};
+use iati_json_parser::converter::settings::Settings;
+use iati_json_parser::converter::utils::{filter_activity, filter_activity_with};
+use serde_json::{json, Value};
+
+use std::collections::BTreeMap;
+
+fn activity(body: &str) -> Value {
+    let xml = format!(
+        r#"<iati-activities version="2.03"><iati-activity default-currency="USD">
+<iati-identifier>XM-ROLL-1</iati-identifier>{}</iati-activity></iati-activities>"#,
+        body
+    );
+    convert_xml_to_json(&xml).unwrap()["iati-activities"]["iati-activity"].clone()
+}
+
+fn transaction(kind: u8, date: &str, value: &str) -> String {
+    format!(
+        r#"<transaction><transaction-type code="{}"/><transaction-date iso-date="{}"/><value value-date="{}"{}</value></transaction>"#,
+        kind, date, date, value
+    )
+}
+
+const MIXED: &str = r#"
+<budget type="1"><period-start iso-date="2023-01-01"/><period-end iso-date="2023-12-31"/><value value-date="2023-01-01">100</value></budget>
+<budget type="1"><period-start iso-date="2024-01-01"/><period-end iso-date="2024-12-31"/><value value-date="2024-01-01">200</value></budget>
+<budget type="2"><period-start iso-date="2024-01-01"/><period-end iso-date="2024-12-31"/><value value-date="2024-06-01">250</value></budget>"#;
+
+fn mixed() -> Value {
+    let transactions = [
+        transaction(2, "2023-02-01", ">1000.10"),
+        transaction(3, "2023-05-01", ">400.20"),
+        transaction(3, "2024-05-01", ">300"),
+        transaction(4, "2024-06-01", ">50"),
+        transaction(1, "2023-01-10", ">900"),
+        transaction(3, "2024-07-01", r#" currency="EUR">100"#),
+    ];
+    activity(&format!("{}{}", transactions.concat(), MIXED))
+}
+
+#[test]
+fn budgets_are_flattened_for_the_totals() {
+    let budgets = convert_file("tests/fixtures/activities/rich.xml").unwrap()[0]["budget"].clone();
+    assert_eq!(
+        budgets,
+        json!([{
+            "budget-type": 1,
+            "budget-status": 2,
+            "period-start": "2023-01-01",
+            "period-end": "2023-12-31",
+            "budget-value": 1000,
+            "budget-currency": "EUR"
+        }])
+    );
+
+    // Every budget is listed, including the originals the totals leave out.
+    let filtered = filter_activity(&mixed()).unwrap();
+    let types: Vec<_> = filtered["budget"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|b| b["budget-type"].clone())
+        .collect();
+    assert_eq!(types, [json!(1), json!(1), json!(2)]);
+}
+
+#[test]
+fn totals_are_by_type_and_year_in_the_activity_currency() {
+    let totals = filter_activity(&mixed()).unwrap()["meta"]["totals"].clone();
+
+    assert_eq!(
+        totals,
+        json!({
+            "currency": "USD",
+            "commitment": {"total": 1000.1, "by_year": {"2023": 1000.1}},
+            "disbursement": {"total": 700.2, "by_year": {"2023": 400.2, "2024": 300.0}},
+            "expenditure": {"total": 50.0, "by_year": {"2024": 50.0}},
+            "incoming_funds": {"total": 900.0, "by_year": {"2023": 900.0}},
+            // The revised 2024 budget replaces the original one.
+            "budget": {"total": 350.0, "by_year": {"2023": 100.0, "2024": 250.0}},
+            "unconverted": 1
+        })
+    );
+}
+
+#[test]
+fn exchange_rates_convert_other_currencies_and_add_converted_totals() {
+    let settings = Settings {
+        report_currency: Some("eur".to_owned()),
+        exchange_rates: BTreeMap::from([("USD".to_owned(), 0.5)]),
+        ..Default::default()
+    };
+    let filtered = filter_activity_with(&mixed(), &settings.filter_options().unwrap()).unwrap();
+    let meta = &filtered["meta"];
+
+    // 100 EUR is 200 USD at 0.5 EUR per dollar.
+    assert_eq!(
+        meta["totals"]["disbursement"],
+        json!({"total": 900.2, "by_year": {"2023": 400.2, "2024": 500.0}})
+    );
+    assert!(meta["totals"].get("unconverted").is_none());
+
+    assert_eq!(meta["converted_totals"]["currency"], "EUR");
+    assert_eq!(
+        meta["converted_totals"]["disbursement"],
+        json!({"total": 450.1, "by_year": {"2023": 200.1, "2024": 250.0}})
+    );
+    assert_eq!(meta["converted_totals"]["budget"]["total"], 175.0);
+}
+
+#[test]
+fn activities_without_money_have_no_totals_and_bad_rates_are_rejected() {
+    let filtered = filter_activity(&activity("<title><narrative>No money</narrative></title>")).unwrap();
+    assert!(filtered["meta"].get("totals").is_none());
+
+    let settings = Settings {
+        exchange_rates: BTreeMap::from([("GBP".to_owned(), 0.0)]),
+        ..Default::default()
+    };
+    assert!(settings.filter_options().is_err());
+}
+
+#[test]
+fn dates_without_a_year_are_totalled_as_unknown() {
+    let activity = activity(
+        &[
+            transaction(3, "201é-01-01", ">10"),
+            transaction(3, "20x4-01-01", ">20"),
+            transaction(3, "2024-01-01", ">5"),
+        ]
+        .concat(),
+    );
+    let totals = filter_activity(&activity).unwrap()["meta"]["totals"].clone();
+
+    assert_eq!(
+        totals["disbursement"],
+        json!({"total": 35.0, "by_year": {"2024": 5.0, "unknown": 30.0}})
+    );
+}
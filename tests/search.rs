@@ -0,0 +1,86 @@
+use iati_json_parser::converter::convert_xml_to_json;
+use iati_json_parser::converter::model::Activity;
+use iati_json_parser::converter::search::{default_weights, search_document};
+use iati_json_parser::converter::settings::Settings;
+use iati_json_parser::converter::utils::{filter_activity, filter_activity_with};
+use iati_json_parser::converter::Converter;
+use serde_json::{json, Value};
+
+use std::collections::BTreeMap;
+use std::fs;
+
+fn rich_activity() -> Value {
+    let xml = fs::read_to_string("tests/fixtures/activities/rich.xml").unwrap();
+    convert_xml_to_json(&xml).unwrap()["iati-activities"]["iati-activity"].clone()
+}
+
+#[test]
+fn search_document_is_ordered_by_field_then_document() {
+    let filtered = filter_activity(&rich_activity()).unwrap();
+    let fields: Vec<(&str, &str, &str)> = filtered["meta"]["search"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| {
+            (
+                e["field"].as_str().unwrap(),
+                e["lang"].as_str().unwrap(),
+                e["text"].as_str().unwrap(),
+            )
+        })
+        .collect();
+
+    assert_eq!(
+        fields,
+        [
+            ("title", "en", "Rich"),
+            ("description-general", "en", "Main description text"),
+            (
+                "description-objectives",
+                "en",
+                "Objectives of the project here"
+            ),
+            ("result", "en", "Result title"),
+            ("indicator", "en", "Indicator title"),
+            ("reporting-org", "en", "Org Three"),
+            ("reporting-org", "fr", "Org Trois"),
+            ("participating-org", "en", "Org Three"),
+        ]
+    );
+    assert_eq!(filtered["meta"]["search"][0]["weight"], 3.0);
+    assert!(filtered["meta"].get("results").is_none());
+}
+
+#[test]
+fn weights_select_and_reweight_fields() {
+    let activity = Activity::from_value(&rich_activity()).unwrap();
+
+    let weights = BTreeMap::from([("title".to_owned(), 5.0), ("sector".to_owned(), 0.0)]);
+    assert_eq!(
+        serde_json::to_value(search_document(&activity, "en", &weights)).unwrap(),
+        json!([{"field": "title", "lang": "en", "weight": 5.0, "text": "Rich"}])
+    );
+
+    let doc = search_document(&activity, "en", &default_weights());
+    let again = search_document(&activity, "en", &default_weights());
+    assert_eq!(doc, again);
+
+    let settings = Settings {
+        search_weights: BTreeMap::new(),
+        ..Default::default()
+    };
+    let filtered = filter_activity_with(&rich_activity(), &settings.filter_options().unwrap()).unwrap();
+    assert!(filtered["meta"].get("search").is_none());
+}
+
+#[test]
+fn unknown_fields_and_negative_weights_are_rejected() {
+    for (field, weight) in [("titel", 1.0), ("title", -1.0)] {
+        let settings = Settings {
+            search_weights: BTreeMap::from([(field.to_owned(), weight)]),
+            ..Default::default()
+        };
+        assert!(settings.filter_options().is_err());
+        assert!(Converter::builder().settings(settings).build().is_err());
+    }
+}
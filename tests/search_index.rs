@@ -0,0 +1,111 @@
+#![cfg(feature = "index")]
+
+use iati_json_parser::converter::convert_dir;
+use iati_json_parser::converter::index::{build_index, index_records, search_index, SearchFilters};
+use iati_json_parser::converter::search::default_weights;
+use iati_json_parser::converter::settings::Settings;
+use serde_json::Value;
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+mod common;
+
+use common::{dump_zip, DUMP};
+
+fn ids(index_dir: &Path, query: &str, filters: &SearchFilters) -> Vec<String> {
+    let mut ids: Vec<String> = search_index(index_dir, query, filters, &default_weights(), 10)
+        .unwrap()
+        .into_iter()
+        .filter_map(|hit| hit.iati_identifier)
+        .collect();
+    ids.sort();
+    ids
+}
+
+#[test]
+fn search_matches_text_and_filters_by_code() {
+    let dir = tempfile::tempdir().unwrap();
+    assert_eq!(
+        build_index(DUMP, &Settings::default(), dir.path()).unwrap(),
+        3
+    );
+
+    let none = SearchFilters::default();
+    assert_eq!(ids(dir.path(), "water", &none), ["XM-1-A1"]);
+    assert_eq!(ids(dir.path(), "", &none).len(), 3);
+
+    let kenya_wash = SearchFilters {
+        country: vec!["ke".to_owned()],
+        sector: vec!["140".to_owned()],
+        ..Default::default()
+    };
+    assert_eq!(ids(dir.path(), "", &kenya_wash), ["XM-1-A1"]);
+    assert!(ids(dir.path(), "health", &kenya_wash).is_empty());
+    let pub_b = SearchFilters {
+        publisher: vec!["pubB".to_owned()],
+        ..Default::default()
+    };
+    assert_eq!(ids(dir.path(), "", &pub_b), ["GB-2-B1"]);
+
+    // Hits carry only what is needed to show them.
+    let hits = search_index(dir.path(), "water", &none, &default_weights(), 1).unwrap();
+    assert_eq!(hits[0].title.as_deref(), Some("Water project"));
+    assert_eq!(hits[0].reporting_org.as_deref(), Some("Org One"));
+    assert_eq!(hits[0].publisher.as_deref(), Some("pubA"));
+}
+
+#[test]
+fn zips_and_converted_records_index_like_the_folder() {
+    let dir = tempfile::tempdir().unwrap();
+    let zip_path = dump_zip(dir.path(), &["pubA/a.xml", "pubB/b.xml"]);
+    let from_zip = dir.path().join("zip-index");
+    let count = build_index(zip_path.to_str().unwrap(), &Settings::default(), &from_zip).unwrap();
+    assert_eq!(count, 3);
+
+    let from_records = dir.path().join("records-index");
+    index_records(&convert_dir(DUMP).unwrap(), &from_records).unwrap();
+
+    let pub_a = SearchFilters {
+        publisher: vec!["pubA".to_owned()],
+        ..Default::default()
+    };
+    for index_dir in [&from_zip, &from_records] {
+        assert_eq!(ids(index_dir, "water", &pub_a), ["XM-1-A1"]);
+    }
+}
+
+#[test]
+fn rebuilding_replaces_the_index() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut records = convert_dir(DUMP).unwrap();
+    index_records(&records, dir.path()).unwrap();
+
+    // Records converted without a search document are indexed by title.
+    records.truncate(1);
+    if let Some(meta) = records[0].get_mut("meta").and_then(Value::as_object_mut) {
+        meta.remove("search");
+    }
+    index_records(&records, dir.path()).unwrap();
+
+    let id = records[0]["iati-identifier"].as_str().unwrap();
+    assert_eq!(ids(dir.path(), "", &SearchFilters::default()), [id]);
+    let title = records[0]["title"]["en"].as_str().unwrap();
+    assert_eq!(ids(dir.path(), title, &SearchFilters::default()), [id]);
+}
+
+#[test]
+fn records_that_arent_activities_are_skipped() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut records = convert_dir(DUMP).unwrap();
+    records.push(Value::String("not an activity".to_owned()));
+
+    assert_eq!(index_records(&records, dir.path()).unwrap(), 3);
+    assert_eq!(ids(dir.path(), "", &SearchFilters::default()).len(), 3);
+}
+
+#[test]
+fn unknown_filters_are_rejected() {
+    let filters = BTreeMap::from([("contry".to_owned(), vec!["KE".to_owned()])]);
+    assert!(SearchFilters::from_map(filters).is_err());
+}
@@ -0,0 +1,146 @@
+use iati_json_parser::converter::health::FileStatus;
+use iati_json_parser::converter::settings::Settings;
+use iati_json_parser::converter::snapshot::{is_snapshot_name, SnapshotStore};
+use iati_json_parser::converter::utils::supplement_root_files;
+
+use std::fs;
+
+mod common;
+
+use common::write_zip;
+
+fn zipped(entries: &[(&str, &[u8])]) -> Vec<u8> {
+    let dir = tempfile::tempdir().unwrap();
+    let zip_path = dir.path().join("data.zip");
+    write_zip(&zip_path, entries);
+    fs::read(zip_path).unwrap()
+}
+
+#[test]
+fn snapshots_are_dated_folders() {
+    assert!(is_snapshot_name("2024-05-01"));
+    for name in ["2024-5-1", "2024-02-30", "latest", "../2024-05-01", ".."] {
+        assert!(!is_snapshot_name(name), "{}", name);
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let store = SnapshotStore::new(dir.path().join("snapshots"));
+    // Only the dump's own metadata.json counts, not dataset metadata files.
+    let bytes = zipped(&[
+        ("iati-data-main/old-metadata.json", b"{\"updated\": 0}"),
+        ("iati-data-main/data/metadata.json", b"{\"updated\": 0}"),
+        ("iati-data-main/metadata.json", b"{\"updated\": 1}"),
+    ]);
+    store.save("2024-05-02", &bytes).unwrap();
+    store.save("2024-05-01", &bytes).unwrap();
+    assert!(store.save("../escape", &bytes).is_err());
+
+    // Stray folders and names that aren't dates are no snapshot.
+    fs::create_dir_all(dir.path().join("snapshots/notes")).unwrap();
+    assert!(store.get("notes").is_none());
+    assert!(store.get("../snapshots/2024-05-01").is_none());
+
+    let names: Vec<_> = store.list().unwrap().into_iter().map(|s| s.name).collect();
+    assert_eq!(names, ["2024-05-01", "2024-05-02"]);
+    let latest = store.latest().unwrap().unwrap();
+    assert_eq!(latest.name, "2024-05-02");
+    assert_eq!(latest.metadata.unwrap()["updated"], 1);
+}
+
+#[test]
+fn prune_keeps_the_most_recent() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = SnapshotStore::new(dir.path().to_path_buf());
+    let bytes = zipped(&[("data/pub/a.xml", b"<iati-activities/>")]);
+    for name in ["2024-05-03", "2024-05-01", "2024-05-02"] {
+        store.save(name, &bytes).unwrap();
+    }
+
+    assert_eq!(store.prune(1).unwrap(), ["2024-05-01", "2024-05-02"]);
+    assert!(!dir.path().join("2024-05-01").exists());
+    assert_eq!(store.list().unwrap().len(), 1);
+    assert!(store.prune(5).unwrap().is_empty());
+}
+
+#[test]
+fn broken_files_are_back_filled_from_the_previous_snapshot() {
+    let dir = tempfile::tempdir().unwrap();
+    let settings = Settings {
+        cache_dir: dir.path().join("cache").to_string_lossy().into_owned(),
+        offline: true,
+        ..Default::default()
+    };
+    let store = settings.snapshot_store();
+    store
+        .save(
+            "2024-05-01",
+            &zipped(&[("iati-data-main/data/pub/gone.xml", b"<iati-activities/>")]),
+        )
+        .unwrap();
+    // The dump being converted is the latest snapshot, so it isn't used.
+    store
+        .save("2024-05-02", &zipped(&[("iati-data-main/errors.txt", b"")]))
+        .unwrap();
+
+    let data_dir = settings.cache_path().join("2024-05-02/iati-data-main/data");
+    fs::create_dir_all(&data_dir).unwrap();
+    fs::write(
+        data_dir.parent().unwrap().join("errors.txt"),
+        "404 pub gone.xml\n",
+    )
+    .unwrap();
+
+    let report = supplement_root_files(&data_dir, &settings).unwrap();
+    let entry = &report.entries[0];
+    assert_eq!(entry.status, FileStatus::Supplemented);
+    assert_eq!(entry.supplied_by.as_deref(), Some("snapshot"));
+    assert_eq!(
+        entry.note.as_deref(),
+        Some("extracted from snapshot 2024-05-01")
+    );
+    assert_eq!(
+        fs::read_to_string(data_dir.join("pub/gone.xml")).unwrap(),
+        "<iati-activities/>"
+    );
+}
+
+#[test]
+fn an_older_snapshot_is_back_filled_from_the_one_before_it() {
+    let dir = tempfile::tempdir().unwrap();
+    let settings = Settings {
+        cache_dir: dir.path().join("cache").to_string_lossy().into_owned(),
+        offline: true,
+        ..Default::default()
+    };
+    let store = settings.snapshot_store();
+    for name in ["2024-04-01", "2024-05-01", "2024-06-01"] {
+        let body = format!("<iati-activities snapshot=\"{}\"/>", name);
+        store
+            .save(
+                name,
+                &zipped(&[("iati-data-main/data/pub/gone.xml", body.as_bytes())]),
+            )
+            .unwrap();
+    }
+
+    // Converting the middle snapshot must not borrow from the later one.
+    let data_dir = settings.cache_path().join("2024-05-01/iati-data-main/data");
+    fs::create_dir_all(&data_dir).unwrap();
+    fs::write(
+        data_dir.parent().unwrap().join("errors.txt"),
+        "404 pub gone.xml\n",
+    )
+    .unwrap();
+
+    let report = supplement_root_files(&data_dir, &settings).unwrap();
+    let entry = &report.entries[0];
+    assert_eq!(entry.supplied_by.as_deref(), Some("snapshot"));
+    assert_eq!(
+        entry.note.as_deref(),
+        Some("extracted from snapshot 2024-04-01")
+    );
+    assert_eq!(
+        fs::read_to_string(data_dir.join("pub/gone.xml")).unwrap(),
+        "<iati-activities snapshot=\"2024-04-01\"/>"
+    );
+}
@@ -0,0 +1,139 @@
+use iati_json_parser::converter::allocation::{dump_allocations, AllocationOptions};
+use iati_json_parser::converter::organisations::dump_organisations;
+use iati_json_parser::converter::settings::Settings;
+use iati_json_parser::converter::stats::dump_stats;
+use iati_json_parser::converter::{convert_dir, pipeline::to_output};
+use serde_json::{json, Value};
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+mod common;
+
+use common::{dump_zip, write_zip, DUMP};
+
+fn stats_json(path: &str) -> Value {
+    serde_json::to_value(dump_stats(path, &Settings::default()).unwrap()).unwrap()
+}
+
+#[test]
+fn dir_stats_count_activities_and_sum_transactions() {
+    let stats = stats_json(DUMP);
+
+    assert_eq!(stats["files"], 2);
+    assert_eq!(stats["files_failed"], 0);
+    assert_eq!(stats["activities"], 3);
+    assert_eq!(stats["publishers"], json!({"pubA": 2, "pubB": 1}));
+    assert_eq!(stats["recipient_countries"], json!({"KE": 2, "UG": 1}));
+    assert_eq!(stats["statuses"], json!({"2": 2, "3": 1}));
+    assert_eq!(
+        stats["transactions"]["2"],
+        json!({"count": 1, "by_currency": {"USD": {"total": 1000.0, "by_year": {"2024": 1000.0}}}})
+    );
+}
+
+#[test]
+fn zip_and_converted_json_match_the_folder() {
+    let dir = tempfile::tempdir().unwrap();
+    let zip_path = dump_zip(dir.path(), &["pubA/a.xml", "pubB/b.xml"]);
+
+    let json_path = dir.path().join("activities.json");
+    let records = Value::Array(convert_dir(DUMP).unwrap());
+    fs::write(&json_path, to_output(&records, None).unwrap()).unwrap();
+
+    let expected = stats_json(DUMP);
+    assert_eq!(stats_json(zip_path.to_str().unwrap()), expected);
+    assert_eq!(stats_json(json_path.to_str().unwrap()), expected);
+}
+
+#[test]
+fn zips_take_publishers_from_their_layout() {
+    let dir = tempfile::tempdir().unwrap();
+    let xml = fs::read(Path::new(DUMP).join("data/pubA/a.xml")).unwrap();
+    let metadata = r#"{"organization": {"name": "registered-pub"}}"#;
+    let folder = dir.path().join("dump");
+    fs::create_dir_all(folder.join("data/folder")).unwrap();
+    fs::create_dir_all(folder.join("metadata")).unwrap();
+    fs::write(folder.join("data/folder/a.xml"), &xml).unwrap();
+    fs::write(folder.join("metadata/a.json"), metadata).unwrap();
+    let zip_path = dir.path().join("dump.zip");
+    write_zip(
+        &zip_path,
+        &[
+            ("dump/data/folder/a.xml", &xml),
+            ("dump/metadata/a.json", metadata.as_bytes()),
+        ],
+    );
+
+    let expected = json!({"registered-pub": 2});
+    assert_eq!(stats_json(folder.to_str().unwrap())["publishers"], expected);
+    assert_eq!(stats_json(zip_path.to_str().unwrap())["publishers"], expected);
+}
+
+#[test]
+fn failed_files_are_counted_and_listed() {
+    let dir = tempfile::tempdir().unwrap();
+    let publisher = dir.path().join("pubC");
+    fs::create_dir(&publisher).unwrap();
+    fs::write(
+        publisher.join("old.xml"),
+        r#"<iati-activities version="1.05"><iati-activity>
+<iati-identifier>XM-OLD-1</iati-identifier>
+<transaction><transaction-type code="D"/><transaction-date iso-date="2012-03-01"/><value currency="EUR">50</value></transaction>
+</iati-activity></iati-activities>"#,
+    )
+    .unwrap();
+    fs::write(publisher.join("broken.xml"), "<!DOCTYPE html><html></html>").unwrap();
+
+    let stats = stats_json(dir.path().to_str().unwrap());
+
+    assert_eq!(stats["files"], 2);
+    assert_eq!(stats["files_failed"], 1);
+    assert!(stats["failures"][0]["path"]
+        .as_str()
+        .unwrap()
+        .ends_with("broken.xml"));
+    // The 1.0x letter code D is counted with type 3 disbursements.
+    assert_eq!(
+        stats["transactions"]["3"],
+        json!({"count": 1, "by_currency": {"EUR": {"total": 50.0, "by_year": {"2012": 50.0}}}})
+    );
+}
+
+#[test]
+fn dates_without_a_year_are_counted_as_unknown() {
+    let dir = tempfile::tempdir().unwrap();
+    let publisher = dir.path().join("pubD");
+    fs::create_dir(&publisher).unwrap();
+    fs::write(
+        publisher.join("dates.xml"),
+        r#"<iati-activities version="2.03"><iati-activity>
+<iati-identifier>XM-DATE-1</iati-identifier>
+<transaction><transaction-type code="3"/><transaction-date iso-date="201é-01-01"/><value currency="EUR">10</value></transaction>
+<transaction><transaction-type code="3"/><transaction-date iso-date="20x4-01-01"/><value currency="EUR">20</value></transaction>
+<transaction><transaction-type code="3"/><transaction-date iso-date="2024-01-01"/><value currency="EUR">5</value></transaction>
+</iati-activity></iati-activities>"#,
+    )
+    .unwrap();
+
+    let stats = stats_json(dir.path().to_str().unwrap());
+
+    assert_eq!(stats["files_failed"], 0);
+    assert_eq!(
+        stats["transactions"]["3"]["by_currency"]["EUR"]["by_year"],
+        json!({"2024": 5.0, "unknown": 30.0})
+    );
+}
+
+#[test]
+fn dump_reports_use_the_conversion_settings() {
+    let settings = Settings {
+        exchange_rates: BTreeMap::from([("GBP".to_owned(), 0.0)]),
+        ..Default::default()
+    };
+    // Settings `convert` would reject are rejected here too.
+    assert!(dump_stats(DUMP, &settings).is_err());
+    assert!(dump_organisations(DUMP, &settings).is_err());
+    assert!(dump_allocations(DUMP, &settings, &AllocationOptions::default()).is_err());
+}
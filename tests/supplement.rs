@@ -0,0 +1,60 @@
+use iati_json_parser::converter::health::FileStatus;
+use iati_json_parser::converter::settings::Settings;
+use iati_json_parser::converter::utils::supplement_root_files;
+
+use std::fs;
+use std::path::Path;
+
+fn write(root: &Path, name: &str, contents: &str) {
+    let path = root.join(name);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+}
+
+#[test]
+fn broken_files_are_back_filled_from_the_mirror() {
+    let dir = tempfile::tempdir().unwrap();
+    let dump = dir.path().join("dump");
+    write(&dump, "data/pub/empty.xml", "");
+    write(
+        &dump,
+        "errors.txt",
+        "404 pub empty.xml\n404 pub gone.xml\n404 pub unknown.xml\n",
+    );
+    // Mirrors may or may not keep the `data/` folder.
+    let mirror = dir.path().join("mirror");
+    write(&mirror, "pub/empty.xml", "<iati-activities/>");
+    write(&mirror, "data/pub/gone.xml", "<iati-activities/>");
+
+    let settings = Settings {
+        cache_dir: dir.path().join("cache").to_string_lossy().into_owned(),
+        mirror_dir: Some(mirror.to_string_lossy().into_owned()),
+        offline: true,
+        ..Default::default()
+    };
+    let report = supplement_root_files(&dump.join("data"), &settings).unwrap();
+
+    let outcomes: Vec<_> = report
+        .entries
+        .iter()
+        .map(|e| (e.dataset.as_str(), e.status, e.supplied_by.as_deref()))
+        .collect();
+    assert_eq!(
+        outcomes,
+        [
+            ("empty", FileStatus::Supplemented, Some("mirror")),
+            ("gone", FileStatus::Supplemented, Some("mirror")),
+            ("unknown", FileStatus::Missing, None),
+        ]
+    );
+    assert_eq!(
+        fs::read_to_string(dump.join("data/pub/gone.xml")).unwrap(),
+        "<iati-activities/>"
+    );
+
+    // Each source tried is recorded, the last failure as the note.
+    let unknown = &report.entries[2];
+    assert_eq!(unknown.attempts.len(), 2);
+    assert!(unknown.attempts[1].starts_with("mirror: "));
+    assert_eq!(unknown.note.as_ref(), unknown.attempts.last());
+}